@@ -0,0 +1,18 @@
+use netlink_tc::{AsyncTcHandle, ParseOptions};
+
+#[tokio::main]
+async fn main() {
+    let mut opts = ParseOptions::new();
+    opts.fail_on_unknown_netlink_message(false)
+        .fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false);
+    let handle = AsyncTcHandle::with_options(opts).unwrap();
+
+    // the kernel runs one dump at a time per socket, so the dumps run one after another
+    // while requests such as `QDiscRequest` messages are still sent concurrently
+    let (qdiscs, classes) = tokio::join!(handle.qdiscs(), handle.classes(1));
+    let qdiscs = qdiscs.unwrap();
+    println!("length: {}, qdiscs: {:#?}", qdiscs.len(), qdiscs);
+    let classes = classes.unwrap();
+    println!("length: {}, classes: {:#?}", classes.len(), classes);
+}
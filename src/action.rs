@@ -0,0 +1,35 @@
+pub mod act_bpf;
+pub mod act_connmark;
+pub mod act_csum;
+pub mod act_ct;
+pub mod act_ctinfo;
+pub mod act_gact;
+pub mod act_gen;
+pub mod act_mirred;
+pub mod act_mpls;
+pub mod act_nat;
+pub mod act_pedit;
+pub mod act_police;
+pub mod act_skbedit;
+pub mod act_skbmod;
+pub mod act_tunnel_key;
+pub mod act_vlan;
+pub mod message;
+
+pub use act_bpf::*;
+pub use act_connmark::*;
+pub use act_csum::*;
+pub use act_ct::*;
+pub use act_ctinfo::*;
+pub use act_gact::*;
+pub use act_gen::*;
+pub use act_mirred::*;
+pub use act_mpls::*;
+pub use act_nat::*;
+pub use act_pedit::*;
+pub use act_police::*;
+pub use act_skbedit::*;
+pub use act_skbmod::*;
+pub use act_tunnel_key::*;
+pub use act_vlan::*;
+pub use message::*;
@@ -0,0 +1,129 @@
+use std::os::fd::RawFd;
+
+use crate::action::{ActGen, Tcft};
+use crate::filter::{marshal_bpf_ops, unmarshal_bpf_ops, BpfOp};
+use crate::{errors::Error, types::*};
+
+/// Options of the `bpf` action, defined in `net/sched/act_bpf.c`.
+///
+/// Like the `bpf` classifier, the action runs either an eBPF program,
+/// identified by `id`, `name` and `tag`, or a classic BPF program whose instructions are in `ops`.
+#[derive(Default, Debug, PartialEq)]
+pub struct ActBpf {
+    pub parms: ActGen,
+    pub tm: Option<Tcft>,
+    /// Instructions of a classic BPF program.
+    pub ops: Vec<BpfOp>,
+    pub name: Option<String>,
+    pub id: Option<u32>,
+    pub tag: Option<[u8; 8]>,
+    /// File descriptor of the eBPF program to run.
+    /// Only used when creating the action, the kernel reports `id` instead.
+    pub fd: Option<RawFd>,
+}
+
+pub enum TcaActBpf {
+    Unspec = 0,
+    Tm,
+    Parms,
+    OpsLen,
+    Ops,
+    Fd,
+    Name,
+    Pad,
+    Tag,
+    Id,
+    Max,
+}
+
+impl From<u16> for TcaActBpf {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaActBpf::Unspec,
+            1 => TcaActBpf::Tm,
+            2 => TcaActBpf::Parms,
+            3 => TcaActBpf::OpsLen,
+            4 => TcaActBpf::Ops,
+            5 => TcaActBpf::Fd,
+            6 => TcaActBpf::Name,
+            7 => TcaActBpf::Pad,
+            8 => TcaActBpf::Tag,
+            9 => TcaActBpf::Id,
+            _ => TcaActBpf::Max,
+        }
+    }
+}
+
+impl ActBpf {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_act_bpf(opts)
+    }
+
+    /// Encodes the action into `TCA_ACT_BPF_*` options.
+    /// Either an eBPF program `fd` or classic BPF `ops` is required.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_act_bpf(self)
+    }
+}
+
+fn unmarshal_act_bpf(opts: Vec<TcOption>) -> ActBpf {
+    let mut bpf = ActBpf::default();
+
+    for opt in opts {
+        let kind = TcaActBpf::from(opt.kind);
+        match kind {
+            TcaActBpf::Tm => bpf.tm = bincode::deserialize(&opt.bytes).ok(),
+            TcaActBpf::Parms => bpf.parms = bincode::deserialize(&opt.bytes).unwrap_or_default(),
+            TcaActBpf::Ops => bpf.ops = unmarshal_bpf_ops(&opt.bytes).unwrap_or_default(),
+            TcaActBpf::Name => {
+                let name = opt.bytes.split(|b| *b == 0).next().unwrap_or_default();
+                bpf.name = String::from_utf8(name.to_vec()).ok();
+            }
+            TcaActBpf::Tag => bpf.tag = opt.bytes.get(..8).map(|b| b.try_into().unwrap()),
+            TcaActBpf::Id => bpf.id = unmarshal_u32_value(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    bpf
+}
+
+fn marshal_act_bpf(bpf: &ActBpf) -> Result<Vec<TcOption>, Error> {
+    let mut opts = vec![TcOption {
+        kind: TcaActBpf::Parms as u16,
+        bytes: bincode::serialize(&bpf.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    match (bpf.fd, bpf.ops.is_empty()) {
+        (Some(fd), _) => {
+            opts.push(TcOption {
+                kind: TcaActBpf::Fd as u16,
+                bytes: fd.to_ne_bytes().to_vec(),
+            });
+            let name = bpf.name.as_deref().unwrap_or_default();
+            let mut bytes = name.as_bytes().to_vec();
+            bytes.push(0);
+            opts.push(TcOption {
+                kind: TcaActBpf::Name as u16,
+                bytes,
+            });
+        }
+        (None, false) => {
+            opts.push(TcOption {
+                kind: TcaActBpf::OpsLen as u16,
+                bytes: (bpf.ops.len() as u16).to_ne_bytes().to_vec(),
+            });
+            opts.push(TcOption {
+                kind: TcaActBpf::Ops as u16,
+                bytes: marshal_bpf_ops(&bpf.ops),
+            });
+        }
+        (None, true) => {
+            return Err(Error::Encode(
+                "BPF action requires a program fd or ops".to_string(),
+            ))
+        }
+    }
+
+    Ok(opts)
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft, TC_ACT_PIPE};
+use crate::{errors::Error, types::*};
+
+/// Options of the `connmark` action, defined in `net/sched/act_connmark.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Connmark {
+    pub parms: ConnmarkParms,
+    pub tm: Option<Tcft>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_connmark.h` as `struct tc_connmark`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnmarkParms {
+    pub gen: ActGen,
+    pub zone: u16,
+}
+
+/// Size of `struct tc_connmark` including its trailing padding.
+const TC_CONNMARK_LEN: usize = 24;
+
+pub enum TcaConnmark {
+    Unspec = 0,
+    Parms,
+    Tm,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaConnmark {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaConnmark::Unspec,
+            1 => TcaConnmark::Parms,
+            2 => TcaConnmark::Tm,
+            3 => TcaConnmark::Pad,
+            _ => TcaConnmark::Max,
+        }
+    }
+}
+
+impl Connmark {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_connmark(opts)
+    }
+
+    /// Creates an action restoring the packet mark from the conntrack mark of `zone`.
+    pub fn with_zone(zone: u16) -> Self {
+        Self {
+            parms: ConnmarkParms {
+                gen: ActGen {
+                    action: TC_ACT_PIPE,
+                    ..Default::default()
+                },
+                zone,
+            },
+            tm: None,
+        }
+    }
+
+    /// Encodes the action into `TCA_CONNMARK_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_connmark(self)
+    }
+}
+
+fn unmarshal_connmark(opts: Vec<TcOption>) -> Connmark {
+    let mut connmark = Connmark::default();
+
+    for opt in opts {
+        let kind = TcaConnmark::from(opt.kind);
+        match kind {
+            TcaConnmark::Parms => {
+                connmark.parms = bincode::deserialize(&opt.bytes).unwrap_or_default()
+            }
+            TcaConnmark::Tm => connmark.tm = bincode::deserialize(&opt.bytes).ok(),
+            _ => (),
+        }
+    }
+
+    connmark
+}
+
+fn marshal_connmark(connmark: &Connmark) -> Result<Vec<TcOption>, Error> {
+    let mut bytes =
+        bincode::serialize(&connmark.parms).map_err(|e| Error::Encode(e.to_string()))?;
+    // the kernel rejects the parameters without the padding of the structure
+    bytes.resize(TC_CONNMARK_LEN, 0);
+
+    Ok(vec![TcOption {
+        kind: TcaConnmark::Parms as u16,
+        bytes,
+    }])
+}
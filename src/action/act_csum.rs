@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft};
+use crate::types::*;
+
+/// Options of the `csum` action, defined in `net/sched/act_csum.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Csum {
+    pub parms: CsumParms,
+    pub tm: Option<Tcft>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_csum.h` as `struct tc_csum`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CsumParms {
+    pub gen: ActGen,
+    /// `TCA_CSUM_UPDATE_FLAG_*` checksums to update.
+    pub update_flags: u32,
+}
+
+pub const TCA_CSUM_UPDATE_FLAG_IPV4HDR: u32 = 1;
+pub const TCA_CSUM_UPDATE_FLAG_ICMP: u32 = 2;
+pub const TCA_CSUM_UPDATE_FLAG_IGMP: u32 = 4;
+pub const TCA_CSUM_UPDATE_FLAG_TCP: u32 = 8;
+pub const TCA_CSUM_UPDATE_FLAG_UDP: u32 = 16;
+pub const TCA_CSUM_UPDATE_FLAG_UDPLITE: u32 = 32;
+pub const TCA_CSUM_UPDATE_FLAG_SCTP: u32 = 64;
+
+pub enum TcaCsum {
+    Unspec = 0,
+    Parms,
+    Tm,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaCsum {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCsum::Unspec,
+            1 => TcaCsum::Parms,
+            2 => TcaCsum::Tm,
+            3 => TcaCsum::Pad,
+            _ => TcaCsum::Max,
+        }
+    }
+}
+
+impl Csum {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_csum(opts)
+    }
+}
+
+fn unmarshal_csum(opts: Vec<TcOption>) -> Csum {
+    let mut csum = Csum::default();
+
+    for opt in opts {
+        let kind = TcaCsum::from(opt.kind);
+        match kind {
+            TcaCsum::Parms => csum.parms = bincode::deserialize(&opt.bytes).unwrap_or_default(),
+            TcaCsum::Tm => csum.tm = bincode::deserialize(&opt.bytes).ok(),
+            _ => (),
+        }
+    }
+
+    csum
+}
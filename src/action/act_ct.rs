@@ -0,0 +1,255 @@
+use std::net::IpAddr;
+
+use crate::action::{unmarshal_act_ipv4, unmarshal_act_ipv6, ActGen, Tcft, TC_ACT_PIPE};
+use crate::{errors::Error, types::*};
+
+/// Options of the `ct` action, defined in `net/sched/act_ct.c`.
+///
+/// NAT ports are converted to host byte order.
+#[derive(Default, Debug, PartialEq)]
+pub struct Ct {
+    pub parms: ActGen,
+    pub tm: Option<Tcft>,
+    /// `TCA_CT_ACT_*` flags.
+    pub ct_action: Option<u16>,
+    pub zone: Option<u16>,
+    pub mark: Option<u32>,
+    pub mark_mask: Option<u32>,
+    pub labels: Option<[u8; 16]>,
+    pub labels_mask: Option<[u8; 16]>,
+    pub nat_min: Option<IpAddr>,
+    pub nat_max: Option<IpAddr>,
+    pub nat_port_min: Option<u16>,
+    pub nat_port_max: Option<u16>,
+    pub helper_name: Option<String>,
+    pub helper_family: Option<u8>,
+    pub helper_proto: Option<u8>,
+}
+
+pub const TCA_CT_ACT_COMMIT: u16 = 1 << 0;
+pub const TCA_CT_ACT_FORCE: u16 = 1 << 1;
+pub const TCA_CT_ACT_CLEAR: u16 = 1 << 2;
+pub const TCA_CT_ACT_NAT: u16 = 1 << 3;
+pub const TCA_CT_ACT_NAT_SRC: u16 = 1 << 4;
+pub const TCA_CT_ACT_NAT_DST: u16 = 1 << 5;
+
+pub enum TcaCt {
+    Unspec = 0,
+    Parms,
+    Tm,
+    Action,
+    Zone,
+    Mark,
+    MarkMask,
+    Labels,
+    LabelsMask,
+    NatIpv4Min,
+    NatIpv4Max,
+    NatIpv6Min,
+    NatIpv6Max,
+    NatPortMin,
+    NatPortMax,
+    Pad,
+    HelperName,
+    HelperFamily,
+    HelperProto,
+    Max,
+}
+
+impl From<u16> for TcaCt {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCt::Unspec,
+            1 => TcaCt::Parms,
+            2 => TcaCt::Tm,
+            3 => TcaCt::Action,
+            4 => TcaCt::Zone,
+            5 => TcaCt::Mark,
+            6 => TcaCt::MarkMask,
+            7 => TcaCt::Labels,
+            8 => TcaCt::LabelsMask,
+            9 => TcaCt::NatIpv4Min,
+            10 => TcaCt::NatIpv4Max,
+            11 => TcaCt::NatIpv6Min,
+            12 => TcaCt::NatIpv6Max,
+            13 => TcaCt::NatPortMin,
+            14 => TcaCt::NatPortMax,
+            15 => TcaCt::Pad,
+            16 => TcaCt::HelperName,
+            17 => TcaCt::HelperFamily,
+            18 => TcaCt::HelperProto,
+            _ => TcaCt::Max,
+        }
+    }
+}
+
+impl Ct {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_ct(opts)
+    }
+
+    /// Creates an action sending packets through conntrack with the `TCA_CT_ACT_*` flags,
+    /// e.g. `TCA_CT_ACT_COMMIT` for `tc ... action ct commit`.
+    pub fn with_flags(flags: u16) -> Self {
+        Self {
+            parms: ActGen {
+                action: TC_ACT_PIPE,
+                ..Default::default()
+            },
+            ct_action: Some(flags),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the conntrack zone.
+    pub fn zone(&mut self, zone: u16) -> &mut Self {
+        self.zone = Some(zone);
+        self
+    }
+
+    /// Sets the conntrack mark, only applied on commit.
+    pub fn mark(&mut self, mark: u32, mask: u32) -> &mut Self {
+        self.mark = Some(mark);
+        self.mark_mask = Some(mask);
+        self
+    }
+
+    /// Sets the conntrack labels, only applied on commit.
+    pub fn labels(&mut self, labels: [u8; 16], mask: [u8; 16]) -> &mut Self {
+        self.labels = Some(labels);
+        self.labels_mask = Some(mask);
+        self
+    }
+
+    /// Sets the address and port ranges of a NAT and the `TCA_CT_ACT_NAT` flag.
+    /// `TCA_CT_ACT_NAT_SRC` or `TCA_CT_ACT_NAT_DST` select the translated side.
+    pub fn nat(&mut self, min: IpAddr, max: IpAddr, ports: Option<(u16, u16)>) -> &mut Self {
+        self.ct_action = Some(self.ct_action.unwrap_or_default() | TCA_CT_ACT_NAT);
+        self.nat_min = Some(min);
+        self.nat_max = Some(max);
+        if let Some((min, max)) = ports {
+            self.nat_port_min = Some(min);
+            self.nat_port_max = Some(max);
+        }
+        self
+    }
+
+    /// Whether the connection is committed to the conntrack table.
+    pub fn is_commit(&self) -> bool {
+        self.ct_action.unwrap_or_default() & TCA_CT_ACT_COMMIT != 0
+    }
+
+    /// Encodes the action into `TCA_CT_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_ct(self)
+    }
+}
+
+fn unmarshal_ct(opts: Vec<TcOption>) -> Ct {
+    let mut ct = Ct::default();
+
+    for opt in opts {
+        let kind = TcaCt::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaCt::Parms => ct.parms = bincode::deserialize(b).unwrap_or_default(),
+            TcaCt::Tm => ct.tm = bincode::deserialize(b).ok(),
+            TcaCt::Action => ct.ct_action = unmarshal_u16_value(b),
+            TcaCt::Zone => ct.zone = unmarshal_u16_value(b),
+            TcaCt::Mark => ct.mark = unmarshal_u32_value(b),
+            TcaCt::MarkMask => ct.mark_mask = unmarshal_u32_value(b),
+            TcaCt::Labels => ct.labels = b.get(..16).and_then(|b| b.try_into().ok()),
+            TcaCt::LabelsMask => ct.labels_mask = b.get(..16).and_then(|b| b.try_into().ok()),
+            TcaCt::NatIpv4Min => ct.nat_min = unmarshal_act_ipv4(b),
+            TcaCt::NatIpv4Max => ct.nat_max = unmarshal_act_ipv4(b),
+            TcaCt::NatIpv6Min => ct.nat_min = unmarshal_act_ipv6(b),
+            TcaCt::NatIpv6Max => ct.nat_max = unmarshal_act_ipv6(b),
+            TcaCt::NatPortMin => ct.nat_port_min = unmarshal_u16_value(b).map(u16::from_be),
+            TcaCt::NatPortMax => ct.nat_port_max = unmarshal_u16_value(b).map(u16::from_be),
+            TcaCt::HelperName => {
+                ct.helper_name = Some(
+                    String::from_utf8_lossy(b)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                )
+            }
+            TcaCt::HelperFamily => ct.helper_family = b.first().copied(),
+            TcaCt::HelperProto => ct.helper_proto = b.first().copied(),
+            _ => (),
+        }
+    }
+
+    ct
+}
+
+fn marshal_ct(ct: &Ct) -> Result<Vec<TcOption>, Error> {
+    let mut opts = vec![TcOption {
+        kind: TcaCt::Parms as u16,
+        bytes: bincode::serialize(&ct.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    let mut push = |kind: TcaCt, bytes: Vec<u8>| {
+        opts.push(TcOption {
+            kind: kind as u16,
+            bytes,
+        })
+    };
+
+    if let Some(action) = ct.ct_action {
+        push(TcaCt::Action, action.to_ne_bytes().to_vec());
+    }
+    if let Some(zone) = ct.zone {
+        push(TcaCt::Zone, zone.to_ne_bytes().to_vec());
+    }
+    if let Some(mark) = ct.mark {
+        push(TcaCt::Mark, mark.to_ne_bytes().to_vec());
+    }
+    if let Some(mask) = ct.mark_mask {
+        push(TcaCt::MarkMask, mask.to_ne_bytes().to_vec());
+    }
+    if let Some(labels) = ct.labels {
+        push(TcaCt::Labels, labels.to_vec());
+    }
+    if let Some(mask) = ct.labels_mask {
+        push(TcaCt::LabelsMask, mask.to_vec());
+    }
+    match (ct.nat_min, ct.nat_max) {
+        (Some(IpAddr::V4(min)), max) => {
+            push(TcaCt::NatIpv4Min, min.octets().to_vec());
+            if let Some(IpAddr::V4(max)) = max {
+                push(TcaCt::NatIpv4Max, max.octets().to_vec());
+            }
+        }
+        (Some(IpAddr::V6(min)), max) => {
+            push(TcaCt::NatIpv6Min, min.octets().to_vec());
+            if let Some(IpAddr::V6(max)) = max {
+                push(TcaCt::NatIpv6Max, max.octets().to_vec());
+            }
+        }
+        (None, Some(_)) => {
+            return Err(Error::Encode(
+                "NAT address range requires a minimum address".to_string(),
+            ))
+        }
+        (None, None) => (),
+    }
+    if let Some(port) = ct.nat_port_min {
+        push(TcaCt::NatPortMin, port.to_be_bytes().to_vec());
+    }
+    if let Some(port) = ct.nat_port_max {
+        push(TcaCt::NatPortMax, port.to_be_bytes().to_vec());
+    }
+    if let Some(name) = &ct.helper_name {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.push(0);
+        push(TcaCt::HelperName, bytes);
+    }
+    if let Some(family) = ct.helper_family {
+        push(TcaCt::HelperFamily, vec![family]);
+    }
+    if let Some(proto) = ct.helper_proto {
+        push(TcaCt::HelperProto, vec![proto]);
+    }
+
+    Ok(opts)
+}
@@ -0,0 +1,148 @@
+use crate::action::{ActGen, Tcft, TC_ACT_PIPE};
+use crate::{errors::Error, types::*};
+
+/// Options of the `ctinfo` action, defined in `net/sched/act_ctinfo.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Ctinfo {
+    pub parms: ActGen,
+    pub tm: Option<Tcft>,
+    pub zone: Option<u16>,
+    /// Bits of the conntrack mark copied to the DSCP field.
+    pub dscp_mask: Option<u32>,
+    /// Bits of the conntrack mark which must be set for the DSCP to be restored.
+    pub dscp_statemask: Option<u32>,
+    /// Bits of the conntrack mark copied to the packet mark.
+    pub cpmark_mask: Option<u32>,
+    pub stats_dscp_set: Option<u64>,
+    pub stats_dscp_error: Option<u64>,
+    pub stats_cpmark_set: Option<u64>,
+}
+
+pub enum TcaCtinfo {
+    Unspec = 0,
+    Pad,
+    Tm,
+    Act,
+    Zone,
+    ParmsDscpMask,
+    ParmsDscpStatemask,
+    ParmsCpmarkMask,
+    StatsDscpSet,
+    StatsDscpError,
+    StatsCpmarkSet,
+    Max,
+}
+
+impl From<u16> for TcaCtinfo {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCtinfo::Unspec,
+            1 => TcaCtinfo::Pad,
+            2 => TcaCtinfo::Tm,
+            3 => TcaCtinfo::Act,
+            4 => TcaCtinfo::Zone,
+            5 => TcaCtinfo::ParmsDscpMask,
+            6 => TcaCtinfo::ParmsDscpStatemask,
+            7 => TcaCtinfo::ParmsCpmarkMask,
+            8 => TcaCtinfo::StatsDscpSet,
+            9 => TcaCtinfo::StatsDscpError,
+            10 => TcaCtinfo::StatsCpmarkSet,
+            _ => TcaCtinfo::Max,
+        }
+    }
+}
+
+impl Ctinfo {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_ctinfo(opts)
+    }
+
+    /// Creates an action restoring the DSCP from the bits of the conntrack mark in `mask`,
+    /// like `tc ... action ctinfo dscp <mask> [<statemask>]`.
+    pub fn with_dscp(mask: u32, statemask: Option<u32>) -> Self {
+        Self {
+            parms: ActGen {
+                action: TC_ACT_PIPE,
+                ..Default::default()
+            },
+            dscp_mask: Some(mask),
+            dscp_statemask: statemask,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an action copying the bits of the conntrack mark in `mask` to the packet mark,
+    /// like `tc ... action ctinfo cpmark [<mask>]`.
+    pub fn with_cpmark(mask: u32) -> Self {
+        Self {
+            parms: ActGen {
+                action: TC_ACT_PIPE,
+                ..Default::default()
+            },
+            cpmark_mask: Some(mask),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the conntrack zone.
+    pub fn zone(&mut self, zone: u16) -> &mut Self {
+        self.zone = Some(zone);
+        self
+    }
+
+    /// Encodes the action into `TCA_CTINFO_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_ctinfo(self)
+    }
+}
+
+fn unmarshal_ctinfo(opts: Vec<TcOption>) -> Ctinfo {
+    let mut ctinfo = Ctinfo::default();
+
+    for opt in opts {
+        let kind = TcaCtinfo::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaCtinfo::Tm => ctinfo.tm = bincode::deserialize(b).ok(),
+            TcaCtinfo::Act => ctinfo.parms = bincode::deserialize(b).unwrap_or_default(),
+            TcaCtinfo::Zone => ctinfo.zone = unmarshal_u16_value(b),
+            TcaCtinfo::ParmsDscpMask => ctinfo.dscp_mask = unmarshal_u32_value(b),
+            TcaCtinfo::ParmsDscpStatemask => ctinfo.dscp_statemask = unmarshal_u32_value(b),
+            TcaCtinfo::ParmsCpmarkMask => ctinfo.cpmark_mask = unmarshal_u32_value(b),
+            TcaCtinfo::StatsDscpSet => ctinfo.stats_dscp_set = unmarshal_u64_value(b),
+            TcaCtinfo::StatsDscpError => ctinfo.stats_dscp_error = unmarshal_u64_value(b),
+            TcaCtinfo::StatsCpmarkSet => ctinfo.stats_cpmark_set = unmarshal_u64_value(b),
+            _ => (),
+        }
+    }
+
+    ctinfo
+}
+
+fn marshal_ctinfo(ctinfo: &Ctinfo) -> Result<Vec<TcOption>, Error> {
+    let mut opts = vec![TcOption {
+        kind: TcaCtinfo::Act as u16,
+        bytes: bincode::serialize(&ctinfo.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    if let Some(zone) = ctinfo.zone {
+        opts.push(TcOption {
+            kind: TcaCtinfo::Zone as u16,
+            bytes: zone.to_ne_bytes().to_vec(),
+        });
+    }
+    for (kind, mask) in [
+        (TcaCtinfo::ParmsDscpMask, ctinfo.dscp_mask),
+        (TcaCtinfo::ParmsDscpStatemask, ctinfo.dscp_statemask),
+        (TcaCtinfo::ParmsCpmarkMask, ctinfo.cpmark_mask),
+    ] {
+        if let Some(mask) = mask {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: mask.to_ne_bytes().to_vec(),
+            });
+        }
+    }
+
+    Ok(opts)
+}
@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft};
+use crate::{errors::Error, types::*};
+
+/// Options of the `gact` action, defined in `net/sched/act_gact.c`.
+///
+/// The action returns the verdict `parms.action`, or `prob.paction` for the packets
+/// selected by the random or deterministic probability.
+#[derive(Default, Debug, PartialEq)]
+pub struct Gact {
+    /// Defined in `include/uapi/linux/tc_act/tc_gact.h` as `struct tc_gact`.
+    pub parms: ActGen,
+    pub tm: Option<Tcft>,
+    pub prob: Option<GactProb>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_gact.h` as `struct tc_gact_p`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GactProb {
+    /// `PGACT_*` type of probability.
+    pub ptype: u16,
+    /// With `PGACT_NETRAND`, one packet out of `pval` on average gets `paction`,
+    /// with `PGACT_DETERM`, every `pval`th packet.
+    pub pval: u16,
+    pub paction: i32,
+}
+
+pub const PGACT_NONE: u16 = 0;
+pub const PGACT_NETRAND: u16 = 1;
+pub const PGACT_DETERM: u16 = 2;
+
+pub enum TcaGact {
+    Unspec = 0,
+    Tm,
+    Parms,
+    Prob,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaGact {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaGact::Unspec,
+            1 => TcaGact::Tm,
+            2 => TcaGact::Parms,
+            3 => TcaGact::Prob,
+            4 => TcaGact::Pad,
+            _ => TcaGact::Max,
+        }
+    }
+}
+
+impl Gact {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_gact(opts)
+    }
+
+    /// Creates an action returning the verdict `action`, one of `TC_ACT_*`,
+    /// like `tc ... action drop`.
+    pub fn with_action(action: i32) -> Self {
+        Self {
+            parms: ActGen {
+                action,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Returns `paction` instead for the packets selected by `ptype` and `pval`,
+    /// like `tc ... action pass random netrand drop 10`.
+    pub fn random(&mut self, ptype: u16, pval: u16, paction: i32) -> &mut Self {
+        self.prob = Some(GactProb {
+            ptype,
+            pval,
+            paction,
+        });
+        self
+    }
+
+    /// Encodes the action into `TCA_GACT_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_gact(self)
+    }
+}
+
+fn unmarshal_gact(opts: Vec<TcOption>) -> Gact {
+    let mut gact = Gact::default();
+
+    for opt in opts {
+        let kind = TcaGact::from(opt.kind);
+        match kind {
+            TcaGact::Tm => gact.tm = bincode::deserialize(&opt.bytes).ok(),
+            TcaGact::Parms => gact.parms = bincode::deserialize(&opt.bytes).unwrap_or_default(),
+            TcaGact::Prob => gact.prob = bincode::deserialize(&opt.bytes).ok(),
+            _ => (),
+        }
+    }
+
+    gact
+}
+
+fn marshal_gact(gact: &Gact) -> Result<Vec<TcOption>, Error> {
+    let mut opts = vec![TcOption {
+        kind: TcaGact::Parms as u16,
+        bytes: bincode::serialize(&gact.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    if let Some(prob) = &gact.prob {
+        opts.push(TcOption {
+            kind: TcaGact::Prob as u16,
+            bytes: bincode::serialize(prob).map_err(|e| Error::Encode(e.to_string()))?,
+        });
+    }
+
+    Ok(opts)
+}
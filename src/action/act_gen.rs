@@ -0,0 +1,191 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::Error, types::*, ParseOptions};
+
+// Verdicts, defined in `include/uapi/linux/pkt_cls.h`
+pub const TC_ACT_UNSPEC: i32 = -1;
+pub const TC_ACT_OK: i32 = 0;
+pub const TC_ACT_RECLASSIFY: i32 = 1;
+pub const TC_ACT_SHOT: i32 = 2;
+pub const TC_ACT_PIPE: i32 = 3;
+pub const TC_ACT_STOLEN: i32 = 4;
+pub const TC_ACT_QUEUED: i32 = 5;
+pub const TC_ACT_REPEAT: i32 = 6;
+pub const TC_ACT_REDIRECT: i32 = 7;
+pub const TC_ACT_TRAP: i32 = 8;
+pub const TC_ACT_JUMP: i32 = 1 << 28;
+pub const TC_ACT_GOTO_CHAIN: i32 = 2 << 28;
+
+// `TCA_ACT_FLAGS` flags
+pub const TCA_ACT_FLAGS_NO_PERCPU_STATS: u32 = 1 << 0;
+pub const TCA_ACT_FLAGS_SKIP_HW: u32 = 1 << 1;
+pub const TCA_ACT_FLAGS_SKIP_SW: u32 = 1 << 2;
+
+// `TCA_ACT_HW_STATS` types
+pub const TCA_ACT_HW_STATS_IMMEDIATE: u32 = 1 << 0;
+pub const TCA_ACT_HW_STATS_DELAYED: u32 = 1 << 1;
+
+/// Parameters shared by all actions,
+/// defined in `include/uapi/linux/pkt_cls.h` as the `tc_gen` macro.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActGen {
+    pub index: u32,
+    pub capab: u32,
+    /// Verdict of the action, one of `TC_ACT_*`.
+    pub action: i32,
+    pub refcnt: i32,
+    pub bindcnt: i32,
+}
+
+/// Defined in `include/uapi/linux/pkt_cls.h` as `struct tcf_t`.
+/// Times are in jiffies.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tcft {
+    pub install: u64,
+    pub lastuse: u64,
+    pub expires: u64,
+    pub firstuse: u64,
+}
+
+pub enum TcaAct {
+    Unspec = 0,
+    Kind,
+    Options,
+    Index,
+    Stats,
+    Pad,
+    Cookie,
+    Flags,
+    HwStats,
+    UsedHwStats,
+    InHwCount,
+    Max,
+}
+
+impl From<u16> for TcaAct {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaAct::Unspec,
+            1 => TcaAct::Kind,
+            2 => TcaAct::Options,
+            3 => TcaAct::Index,
+            4 => TcaAct::Stats,
+            5 => TcaAct::Pad,
+            6 => TcaAct::Cookie,
+            7 => TcaAct::Flags,
+            8 => TcaAct::HwStats,
+            9 => TcaAct::UsedHwStats,
+            10 => TcaAct::InHwCount,
+            _ => TcaAct::Max,
+        }
+    }
+}
+
+/// Decodes the actions of a classifier, the value of its `TCA_*_ACT` option.
+/// Actions of unknown kinds are kept without their options.
+pub fn unmarshal_actions(bytes: &[u8]) -> Vec<Action> {
+    let mut opts = ParseOptions::new();
+    opts.fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false);
+    crate::tc::actions(bytes, &opts).unwrap_or_default()
+}
+
+/// Encodes `actions` as the value of a `TCA_*_ACT` option.
+pub fn marshal_actions(actions: &[Action]) -> Result<Vec<u8>, Error> {
+    let opts = actions
+        .iter()
+        .enumerate()
+        .map(|(i, action)| {
+            // the kernel runs the actions by increasing order, starting at 1
+            let order = if action.order == 0 {
+                i as u16 + 1
+            } else {
+                action.order
+            };
+            Ok(TcOption {
+                kind: order,
+                bytes: marshal_nested(&action.to_options()?),
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(marshal_nested(&opts))
+}
+
+pub(crate) fn marshal_action(action: &Action) -> Result<Vec<TcOption>, Error> {
+    let mut opts = Vec::new();
+
+    let mut kind = action.kind.as_bytes().to_vec();
+    kind.push(0);
+    opts.push(TcOption {
+        kind: TcaAct::Kind as u16,
+        bytes: kind,
+    });
+    if let Some(options) = &action.options {
+        let options = match options {
+            ActionOptions::Bpf(bpf) => bpf.to_options()?,
+            ActionOptions::Connmark(connmark) => connmark.to_options()?,
+            ActionOptions::Ct(ct) => ct.to_options()?,
+            ActionOptions::Ctinfo(ctinfo) => ctinfo.to_options()?,
+            ActionOptions::Gact(gact) => gact.to_options()?,
+            ActionOptions::Mirred(mirred) => mirred.to_options()?,
+            ActionOptions::Mpls(mpls) => mpls.to_options()?,
+            ActionOptions::Police(police) => police.to_options()?,
+            ActionOptions::TunnelKey(key) => key.to_options()?,
+            _ => {
+                return Err(Error::Encode(format!(
+                    "Creating {} actions is not supported",
+                    action.kind
+                )))
+            }
+        };
+        opts.push(TcOption {
+            kind: TcaAct::Options as u16,
+            bytes: marshal_nested(&options),
+        });
+    }
+    if let Some(index) = action.index {
+        opts.push(TcOption {
+            kind: TcaAct::Index as u16,
+            bytes: index.to_ne_bytes().to_vec(),
+        });
+    }
+    if let Some(cookie) = &action.cookie {
+        opts.push(TcOption {
+            kind: TcaAct::Cookie as u16,
+            bytes: cookie.clone(),
+        });
+    }
+    if let Some(flags) = action.flags {
+        opts.push(TcOption {
+            kind: TcaAct::Flags as u16,
+            bytes: marshal_bitfield32(flags),
+        });
+    }
+    if let Some(hw_stats) = action.hw_stats {
+        opts.push(TcOption {
+            kind: TcaAct::HwStats as u16,
+            bytes: marshal_bitfield32(hw_stats),
+        });
+    }
+
+    Ok(opts)
+}
+
+/// Encodes `struct nla_bitfield32`, selecting the bits set in `value`.
+pub(crate) fn marshal_bitfield32(value: u32) -> Vec<u8> {
+    let mut bytes = value.to_ne_bytes().to_vec();
+    bytes.extend(value.to_ne_bytes());
+    bytes
+}
+
+pub(crate) fn unmarshal_act_ipv4(bytes: &[u8]) -> Option<IpAddr> {
+    let octets: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+    Some(IpAddr::V4(Ipv4Addr::from(octets)))
+}
+
+pub(crate) fn unmarshal_act_ipv6(bytes: &[u8]) -> Option<IpAddr> {
+    let octets: [u8; 16] = bytes.get(..16)?.try_into().ok()?;
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
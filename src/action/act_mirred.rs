@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft, TC_ACT_PIPE, TC_ACT_STOLEN};
+use crate::{errors::Error, types::*};
+
+/// Options of the `mirred` action, defined in `net/sched/act_mirred.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Mirred {
+    pub parms: MirredParms,
+    pub tm: Option<Tcft>,
+    pub blockid: Option<u32>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_mirred.h` as `struct tc_mirred`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MirredParms {
+    pub gen: ActGen,
+    /// `TCA_*_REDIR` or `TCA_*_MIRROR` direction and kind of the action.
+    pub eaction: i32,
+    /// Index of the interface packets are sent to.
+    pub ifindex: u32,
+}
+
+pub const TCA_EGRESS_REDIR: i32 = 1;
+pub const TCA_EGRESS_MIRROR: i32 = 2;
+pub const TCA_INGRESS_REDIR: i32 = 3;
+pub const TCA_INGRESS_MIRROR: i32 = 4;
+
+pub enum TcaMirred {
+    Unspec = 0,
+    Tm,
+    Parms,
+    Pad,
+    Blockid,
+    Max,
+}
+
+impl From<u16> for TcaMirred {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaMirred::Unspec,
+            1 => TcaMirred::Tm,
+            2 => TcaMirred::Parms,
+            3 => TcaMirred::Pad,
+            4 => TcaMirred::Blockid,
+            _ => TcaMirred::Max,
+        }
+    }
+}
+
+impl Mirred {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_mirred(opts)
+    }
+
+    /// Creates an action sending packets to the interface `ifindex`,
+    /// like `tc ... action mirred egress redirect dev <dev>` with `TCA_EGRESS_REDIR`.
+    /// Redirected packets are stolen while mirrored packets continue down the action list,
+    /// as `tc` defaults to.
+    pub fn with_ifindex(eaction: i32, ifindex: u32) -> Self {
+        let action = match eaction {
+            TCA_EGRESS_REDIR | TCA_INGRESS_REDIR => TC_ACT_STOLEN,
+            _ => TC_ACT_PIPE,
+        };
+        Self {
+            parms: MirredParms {
+                gen: ActGen {
+                    action,
+                    ..Default::default()
+                },
+                eaction,
+                ifindex,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Whether packets are mirrored rather than redirected.
+    pub fn is_mirror(&self) -> bool {
+        matches!(self.parms.eaction, TCA_EGRESS_MIRROR | TCA_INGRESS_MIRROR)
+    }
+
+    /// Whether packets are sent to the ingress rather than the egress of the interface.
+    pub fn is_ingress(&self) -> bool {
+        matches!(self.parms.eaction, TCA_INGRESS_REDIR | TCA_INGRESS_MIRROR)
+    }
+
+    /// Encodes the action into `TCA_MIRRED_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_mirred(self)
+    }
+}
+
+fn unmarshal_mirred(opts: Vec<TcOption>) -> Mirred {
+    let mut mirred = Mirred::default();
+
+    for opt in opts {
+        let kind = TcaMirred::from(opt.kind);
+        match kind {
+            TcaMirred::Tm => mirred.tm = bincode::deserialize(&opt.bytes).ok(),
+            TcaMirred::Parms => mirred.parms = bincode::deserialize(&opt.bytes).unwrap_or_default(),
+            TcaMirred::Blockid => mirred.blockid = unmarshal_u32_value(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    mirred
+}
+
+fn marshal_mirred(mirred: &Mirred) -> Result<Vec<TcOption>, Error> {
+    let mut opts = vec![TcOption {
+        kind: TcaMirred::Parms as u16,
+        bytes: bincode::serialize(&mirred.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    if let Some(blockid) = mirred.blockid {
+        opts.push(TcOption {
+            kind: TcaMirred::Blockid as u16,
+            bytes: blockid.to_ne_bytes().to_vec(),
+        });
+    }
+
+    Ok(opts)
+}
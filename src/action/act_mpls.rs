@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft, TC_ACT_PIPE};
+use crate::{errors::Error, types::*};
+
+/// Options of the `mpls` action, defined in `net/sched/act_mpls.c`.
+///
+/// `proto` is converted to host byte order.
+#[derive(Default, Debug, PartialEq)]
+pub struct Mpls {
+    pub parms: MplsParms,
+    pub tm: Option<Tcft>,
+    /// Ethertype pushed, or restored on pop.
+    pub proto: Option<u16>,
+    pub label: Option<u32>,
+    pub tc: Option<u8>,
+    pub ttl: Option<u8>,
+    pub bos: Option<u8>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_mpls.h` as `struct tc_mpls`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MplsParms {
+    pub gen: ActGen,
+    /// `TCA_MPLS_ACT_*` operation.
+    pub m_action: i32,
+}
+
+pub const TCA_MPLS_ACT_POP: i32 = 1;
+pub const TCA_MPLS_ACT_PUSH: i32 = 2;
+pub const TCA_MPLS_ACT_MODIFY: i32 = 3;
+pub const TCA_MPLS_ACT_DEC_TTL: i32 = 4;
+pub const TCA_MPLS_ACT_MAC_PUSH: i32 = 5;
+
+/// Highest valid MPLS label.
+pub const MPLS_LABEL_MAX: u32 = 0xfffff;
+
+pub enum TcaMpls {
+    Unspec = 0,
+    Tm,
+    Parms,
+    Pad,
+    Proto,
+    Label,
+    Tc,
+    Ttl,
+    Bos,
+    Max,
+}
+
+impl From<u16> for TcaMpls {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaMpls::Unspec,
+            1 => TcaMpls::Tm,
+            2 => TcaMpls::Parms,
+            3 => TcaMpls::Pad,
+            4 => TcaMpls::Proto,
+            5 => TcaMpls::Label,
+            6 => TcaMpls::Tc,
+            7 => TcaMpls::Ttl,
+            8 => TcaMpls::Bos,
+            _ => TcaMpls::Max,
+        }
+    }
+}
+
+impl Mpls {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_mpls(opts)
+    }
+
+    /// Creates an action running the `TCA_MPLS_ACT_*` operation `m_action`.
+    pub fn with_action(m_action: i32) -> Self {
+        Self {
+            parms: MplsParms {
+                gen: ActGen {
+                    action: TC_ACT_PIPE,
+                    ..Default::default()
+                },
+                m_action,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Sets the ethertype, `ETH_P_MPLS_UC` or `ETH_P_MPLS_MC` on push
+    /// and the ethertype of the next header on pop.
+    pub fn proto(&mut self, proto: u16) -> &mut Self {
+        self.proto = Some(proto);
+        self
+    }
+
+    /// Sets the label, traffic class and TTL of the pushed or modified entry.
+    pub fn entry(&mut self, label: u32, tc: u8, ttl: u8) -> &mut Self {
+        self.label = Some(label);
+        self.tc = Some(tc);
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the bottom of stack bit.
+    pub fn bos(&mut self, bos: bool) -> &mut Self {
+        self.bos = Some(bos as u8);
+        self
+    }
+
+    /// Encodes the action into `TCA_MPLS_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_mpls(self)
+    }
+}
+
+fn unmarshal_mpls(opts: Vec<TcOption>) -> Mpls {
+    let mut mpls = Mpls::default();
+
+    for opt in opts {
+        let kind = TcaMpls::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaMpls::Tm => mpls.tm = bincode::deserialize(b).ok(),
+            TcaMpls::Parms => mpls.parms = bincode::deserialize(b).unwrap_or_default(),
+            TcaMpls::Proto => mpls.proto = unmarshal_u16_value(b).map(u16::from_be),
+            TcaMpls::Label => mpls.label = unmarshal_u32_value(b),
+            TcaMpls::Tc => mpls.tc = b.first().copied(),
+            TcaMpls::Ttl => mpls.ttl = b.first().copied(),
+            TcaMpls::Bos => mpls.bos = b.first().copied(),
+            _ => (),
+        }
+    }
+
+    mpls
+}
+
+fn marshal_mpls(mpls: &Mpls) -> Result<Vec<TcOption>, Error> {
+    if mpls.label.is_some_and(|label| label > MPLS_LABEL_MAX) {
+        return Err(Error::Encode(format!(
+            "MPLS label must not exceed {MPLS_LABEL_MAX}"
+        )));
+    }
+
+    let mut opts = vec![TcOption {
+        kind: TcaMpls::Parms as u16,
+        bytes: bincode::serialize(&mpls.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    if let Some(proto) = mpls.proto {
+        opts.push(TcOption {
+            kind: TcaMpls::Proto as u16,
+            bytes: proto.to_be_bytes().to_vec(),
+        });
+    }
+    if let Some(label) = mpls.label {
+        opts.push(TcOption {
+            kind: TcaMpls::Label as u16,
+            bytes: label.to_ne_bytes().to_vec(),
+        });
+    }
+    for (kind, value) in [
+        (TcaMpls::Tc, mpls.tc),
+        (TcaMpls::Ttl, mpls.ttl),
+        (TcaMpls::Bos, mpls.bos),
+    ] {
+        if let Some(value) = value {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: vec![value],
+            });
+        }
+    }
+
+    Ok(opts)
+}
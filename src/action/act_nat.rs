@@ -0,0 +1,90 @@
+use std::net::Ipv4Addr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft};
+use crate::types::*;
+
+/// Options of the `nat` action, defined in `net/sched/act_nat.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Nat {
+    pub parms: NatParms,
+    pub tm: Option<Tcft>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_nat.h` as `struct tc_nat`.
+///
+/// Addresses are kept in network byte order, see `old_addr`, `new_addr` and `mask`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NatParms {
+    pub gen: ActGen,
+    pub old_addr: [u8; 4],
+    pub new_addr: [u8; 4],
+    pub mask: [u8; 4],
+    /// `TCA_NAT_FLAG_*` flags.
+    pub flags: u32,
+}
+
+/// Translates source addresses on egress instead of destination addresses on ingress.
+pub const TCA_NAT_FLAG_EGRESS: u32 = 1;
+
+pub enum TcaNat {
+    Unspec = 0,
+    Parms,
+    Tm,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaNat {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaNat::Unspec,
+            1 => TcaNat::Parms,
+            2 => TcaNat::Tm,
+            3 => TcaNat::Pad,
+            _ => TcaNat::Max,
+        }
+    }
+}
+
+impl Nat {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_nat(opts)
+    }
+
+    /// Returns the address, or prefix with `mask`, being translated.
+    pub fn old_addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.parms.old_addr)
+    }
+
+    /// Returns the address the packets are translated to.
+    pub fn new_addr(&self) -> Ipv4Addr {
+        Ipv4Addr::from(self.parms.new_addr)
+    }
+
+    /// Returns the prefix length of `mask`.
+    pub fn prefix_len(&self) -> u32 {
+        u32::from_be_bytes(self.parms.mask).count_ones()
+    }
+
+    /// Whether source addresses are translated on egress.
+    pub fn is_egress(&self) -> bool {
+        self.parms.flags & TCA_NAT_FLAG_EGRESS != 0
+    }
+}
+
+fn unmarshal_nat(opts: Vec<TcOption>) -> Nat {
+    let mut nat = Nat::default();
+
+    for opt in opts {
+        let kind = TcaNat::from(opt.kind);
+        match kind {
+            TcaNat::Parms => nat.parms = bincode::deserialize(&opt.bytes).unwrap_or_default(),
+            TcaNat::Tm => nat.tm = bincode::deserialize(&opt.bytes).ok(),
+            _ => (),
+        }
+    }
+
+    nat
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft};
+use crate::types::*;
+
+/// Options of the `pedit` action, defined in `net/sched/act_pedit.c`.
+///
+/// Each key rewrites a 32-bit word of the packet as `(word & mask) ^ val`, or adds `val`
+/// with `TCA_PEDIT_KEY_EX_CMD_ADD`. With extended keys, `keys_ex` holds the header
+/// each key's offset is relative to and its command, in the same order as `sel.keys`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Pedit {
+    pub sel: Option<PeditSel>,
+    pub tm: Option<Tcft>,
+    pub keys_ex: Vec<PeditKeyEx>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_pedit.h` as `struct tc_pedit_sel`.
+#[derive(Default, Debug, PartialEq)]
+pub struct PeditSel {
+    pub parms: ActGen,
+    pub nkeys: u8,
+    pub flags: u8,
+    pub keys: Vec<PeditKey>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_pedit.h` as `struct tc_pedit_key`.
+///
+/// `mask` and `val` are converted from network byte order,
+/// e.g. a `val` of `0x0a000000` writes `10` to the first byte of the word.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeditKey {
+    /// Bits of the word to keep.
+    pub mask: u32,
+    pub val: u32,
+    pub off: u32,
+    /// Offset of the byte used for a variable offset, with `offmask` and `shift`.
+    pub at: u32,
+    pub offmask: u32,
+    pub shift: u32,
+}
+
+/// Nested in `TCA_PEDIT_KEYS_EX` for each key.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PeditKeyEx {
+    /// `TCA_PEDIT_KEY_EX_HDR_TYPE_*` header the offset of the key is relative to.
+    pub htype: u16,
+    /// `TCA_PEDIT_KEY_EX_CMD_*` operation of the key.
+    pub cmd: u16,
+}
+
+// Header types, defined in `include/uapi/linux/tc_act/tc_pedit.h` as `enum pedit_header_type`
+pub const TCA_PEDIT_KEY_EX_HDR_TYPE_NETWORK: u16 = 0;
+pub const TCA_PEDIT_KEY_EX_HDR_TYPE_ETH: u16 = 1;
+pub const TCA_PEDIT_KEY_EX_HDR_TYPE_IP4: u16 = 2;
+pub const TCA_PEDIT_KEY_EX_HDR_TYPE_IP6: u16 = 3;
+pub const TCA_PEDIT_KEY_EX_HDR_TYPE_TCP: u16 = 4;
+pub const TCA_PEDIT_KEY_EX_HDR_TYPE_UDP: u16 = 5;
+
+// Commands, defined in `include/uapi/linux/tc_act/tc_pedit.h` as `enum pedit_cmd`
+pub const TCA_PEDIT_KEY_EX_CMD_SET: u16 = 0;
+pub const TCA_PEDIT_KEY_EX_CMD_ADD: u16 = 1;
+
+/// Size of `struct tc_pedit_sel` without its keys.
+const PEDIT_SEL_LEN: usize = 24;
+const PEDIT_KEY_LEN: usize = 24;
+
+pub enum TcaPedit {
+    Unspec = 0,
+    Tm,
+    Parms,
+    Pad,
+    ParmsEx,
+    KeysEx,
+    KeyEx,
+    Max,
+}
+
+impl From<u16> for TcaPedit {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaPedit::Unspec,
+            1 => TcaPedit::Tm,
+            2 => TcaPedit::Parms,
+            3 => TcaPedit::Pad,
+            4 => TcaPedit::ParmsEx,
+            5 => TcaPedit::KeysEx,
+            6 => TcaPedit::KeyEx,
+            _ => TcaPedit::Max,
+        }
+    }
+}
+
+pub enum TcaPeditKeyEx {
+    Unspec = 0,
+    Htype,
+    Cmd,
+    Max,
+}
+
+impl From<u16> for TcaPeditKeyEx {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaPeditKeyEx::Unspec,
+            1 => TcaPeditKeyEx::Htype,
+            2 => TcaPeditKeyEx::Cmd,
+            _ => TcaPeditKeyEx::Max,
+        }
+    }
+}
+
+impl Pedit {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_pedit(opts)
+    }
+}
+
+impl PeditSel {
+    pub fn new(bytes: &[u8]) -> Option<Self> {
+        unmarshal_pedit_sel(bytes)
+    }
+}
+
+fn unmarshal_pedit(opts: Vec<TcOption>) -> Pedit {
+    let mut pedit = Pedit::default();
+
+    for opt in opts {
+        let kind = TcaPedit::from(opt.kind);
+        match kind {
+            TcaPedit::Tm => pedit.tm = bincode::deserialize(&opt.bytes).ok(),
+            TcaPedit::Parms | TcaPedit::ParmsEx => pedit.sel = PeditSel::new(&opt.bytes),
+            TcaPedit::KeysEx => pedit.keys_ex = unmarshal_pedit_keys_ex(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    pedit
+}
+
+fn unmarshal_pedit_sel(bytes: &[u8]) -> Option<PeditSel> {
+    let header = bytes.get(..PEDIT_SEL_LEN)?;
+    let keys = bytes[PEDIT_SEL_LEN..]
+        .chunks_exact(PEDIT_KEY_LEN)
+        .take(header[20] as usize)
+        .filter_map(|b| bincode::deserialize::<PeditKey>(b).ok())
+        .map(|key| PeditKey {
+            mask: u32::from_be(key.mask),
+            val: u32::from_be(key.val),
+            ..key
+        })
+        .collect();
+
+    Some(PeditSel {
+        parms: bincode::deserialize(&header[..20]).ok()?,
+        nkeys: header[20],
+        flags: header[21],
+        keys,
+    })
+}
+
+fn unmarshal_pedit_keys_ex(bytes: &[u8]) -> Vec<PeditKeyEx> {
+    unmarshal_nested(bytes)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|opt| matches!(TcaPedit::from(opt.kind), TcaPedit::KeyEx))
+        .map(|opt| {
+            let mut key = PeditKeyEx::default();
+            for attr in unmarshal_nested(&opt.bytes).unwrap_or_default() {
+                let value = unmarshal_u16_value(&attr.bytes).unwrap_or_default();
+                match TcaPeditKeyEx::from(attr.kind) {
+                    TcaPeditKeyEx::Htype => key.htype = value,
+                    TcaPeditKeyEx::Cmd => key.cmd = value,
+                    _ => (),
+                }
+            }
+            key
+        })
+        .collect()
+}
@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{Tcft, TC_ACT_OK, TC_ACT_RECLASSIFY};
+use crate::rate::{calc_rtable, calc_xmittime, LinkLayer};
+use crate::{errors::Error, types::*};
+
+/// Options of the `police` action, defined in `net/sched/act_police.c`.
+///
+/// Rates are in bytes per second, except `pktrate64` in packets per second.
+/// The rates above 32 bits are reported in `rate64` and `peakrate64`,
+/// `tbf.rate.rate` and `tbf.peakrate.rate` being capped to `u32::MAX`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Police {
+    pub tbf: Option<PoliceTbf>,
+    pub rtab: Vec<u8>,
+    pub ptab: Vec<u8>,
+    /// Average rate checked against the rate estimator of the action.
+    pub avrate: Option<u32>,
+    /// Verdict for conforming packets, `TC_ACT_*`.
+    pub result: Option<i32>,
+    pub tm: Option<Tcft>,
+    pub rate64: Option<u64>,
+    pub peakrate64: Option<u64>,
+    pub pktrate64: Option<u64>,
+    /// Packet burst, in psched ticks at `pktrate64`.
+    pub pktburst64: Option<u64>,
+}
+
+/// Defined in `include/uapi/linux/pkt_cls.h` as `struct tc_police`.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PoliceTbf {
+    pub index: u32,
+    /// Verdict for packets exceeding the rate, `TC_ACT_*`.
+    pub action: i32,
+    pub limit: u32,
+    /// Burst, in psched ticks at `rate`.
+    pub burst: u32,
+    pub mtu: u32,
+    pub rate: RateSpec,
+    pub peakrate: RateSpec,
+    pub refcnt: i32,
+    pub bindcnt: i32,
+    pub capab: u32,
+}
+
+pub enum TcaPolice {
+    Unspec = 0,
+    Tbf,
+    Rate,
+    PeakRate,
+    AvRate,
+    Result,
+    Tm,
+    Pad,
+    Rate64,
+    PeakRate64,
+    PktRate64,
+    PktBurst64,
+    Max,
+}
+
+impl From<u16> for TcaPolice {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaPolice::Unspec,
+            1 => TcaPolice::Tbf,
+            2 => TcaPolice::Rate,
+            3 => TcaPolice::PeakRate,
+            4 => TcaPolice::AvRate,
+            5 => TcaPolice::Result,
+            6 => TcaPolice::Tm,
+            7 => TcaPolice::Pad,
+            8 => TcaPolice::Rate64,
+            9 => TcaPolice::PeakRate64,
+            10 => TcaPolice::PktRate64,
+            11 => TcaPolice::PktBurst64,
+            _ => TcaPolice::Max,
+        }
+    }
+}
+
+impl Police {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_police(opts)
+    }
+
+    /// Encodes the action into `TCA_POLICE_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_police(self)
+    }
+
+    /// Returns the rate in bytes per second, including rates above 32 bits.
+    pub fn rate(&self) -> Option<u64> {
+        self.rate64
+            .or_else(|| self.tbf.as_ref().map(|tbf| tbf.rate.rate as u64))
+            .filter(|rate| *rate != 0)
+    }
+
+    /// Returns the peak rate in bytes per second, including rates above 32 bits.
+    pub fn peakrate(&self) -> Option<u64> {
+        self.peakrate64
+            .or_else(|| self.tbf.as_ref().map(|tbf| tbf.peakrate.rate as u64))
+            .filter(|rate| *rate != 0)
+    }
+}
+
+/// `PoliceBuilder` computes the options of a policer the way `tc ... action police` does,
+/// including the rate tables.
+///
+/// Rates are in bytes per second and bursts in bytes, or in packets for `pkt_rate`.
+///
+/// # Example
+/// ```
+/// use netlink_tc::action::{PoliceBuilder, TC_ACT_SHOT};
+///
+/// // police rate 1mbit burst 10k conform-exceed drop
+/// let police = PoliceBuilder::new(125_000, 10_240).exceed(TC_ACT_SHOT).build().unwrap();
+/// assert_eq!(police.tbf.unwrap().rate.rate, 125_000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PoliceBuilder {
+    rate: u64,
+    burst: u32,
+    peakrate: Option<u64>,
+    pkt_rate: Option<(u64, u64)>,
+    avrate: Option<u32>,
+    mtu: u32,
+    mpu: u16,
+    overhead: u16,
+    cell_log: Option<u8>,
+    linklayer: LinkLayer,
+    exceed: Option<i32>,
+    conform: Option<i32>,
+}
+
+impl PoliceBuilder {
+    /// Creates a builder for a policer limiting traffic to `rate` bytes per second,
+    /// with bursts of `burst` bytes.
+    pub fn new(rate: u64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a builder for a policer limiting traffic to `rate` packets per second,
+    /// with bursts of `burst` packets.
+    pub fn with_pkt_rate(rate: u64, burst: u64) -> Self {
+        Self {
+            pkt_rate: Some((rate, burst)),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the maximum rate in bytes per second of a burst, which requires the MTU.
+    pub fn peakrate(&mut self, peakrate: u64) -> &mut Self {
+        self.peakrate = Some(peakrate);
+        self
+    }
+
+    /// Sets the average rate in bytes per second checked against the rate estimator.
+    pub fn avrate(&mut self, avrate: u32) -> &mut Self {
+        self.avrate = Some(avrate);
+        self
+    }
+
+    /// Sets the largest packet allowed by the peak rate, defaults to 2047 bytes for the tables.
+    pub fn mtu(&mut self, mtu: u32) -> &mut Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Sets the minimum packet unit, packets smaller than `mpu` are accounted as `mpu` bytes.
+    pub fn mpu(&mut self, mpu: u16) -> &mut Self {
+        self.mpu = mpu;
+        self
+    }
+
+    /// Sets the per packet overhead added by the kernel when computing the rate.
+    pub fn overhead(&mut self, overhead: u16) -> &mut Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Sets the cell log of the rate tables, derived from the MTU by default.
+    pub fn cell_log(&mut self, cell_log: u8) -> &mut Self {
+        self.cell_log = Some(cell_log);
+        self
+    }
+
+    /// Sets the link layer used to compute the size of packets on the wire.
+    pub fn linklayer(&mut self, linklayer: LinkLayer) -> &mut Self {
+        self.linklayer = linklayer;
+        self
+    }
+
+    /// Sets the verdict for packets exceeding the rate, `TC_ACT_RECLASSIFY` by default.
+    pub fn exceed(&mut self, action: i32) -> &mut Self {
+        self.exceed = Some(action);
+        self
+    }
+
+    /// Sets the verdict for conforming packets, `TC_ACT_OK` by default.
+    pub fn conform(&mut self, action: i32) -> &mut Self {
+        self.conform = Some(action);
+        self
+    }
+
+    /// Builds the action options.
+    pub fn build(&self) -> Result<Police, Error> {
+        if self.rate != 0 && self.pkt_rate.is_some() {
+            return Err(Error::Encode(
+                "Policer can't limit both bytes and packets".to_string(),
+            ));
+        }
+        if self.rate != 0 && self.burst == 0 {
+            return Err(Error::Encode("Policer rate requires a burst".to_string()));
+        }
+        if self.peakrate.is_some() && self.rate == 0 {
+            return Err(Error::Encode(
+                "Policer peakrate requires a rate".to_string(),
+            ));
+        }
+        if self.peakrate.is_some() && self.mtu == 0 {
+            return Err(Error::Encode(
+                "Policer peakrate requires an MTU".to_string(),
+            ));
+        }
+        if self.peakrate == Some(0) {
+            return Err(Error::Encode(
+                "Policer peakrate must not be zero".to_string(),
+            ));
+        }
+        if let Some(cell_log) = self.cell_log.filter(|cell_log| *cell_log >= 32) {
+            return Err(Error::Encode(format!(
+                "Policer cell_log {cell_log} must be below 32"
+            )));
+        }
+
+        let mut tbf = PoliceTbf {
+            action: self.exceed.unwrap_or(TC_ACT_RECLASSIFY),
+            mtu: self.mtu,
+            ..Default::default()
+        };
+        let mut police = Police {
+            avrate: self.avrate,
+            result: self.conform.filter(|conform| *conform != TC_ACT_OK),
+            ..Default::default()
+        };
+
+        if self.rate != 0 {
+            tbf.rate = self.rate_spec(self.rate);
+            police.rtab = calc_rtable(
+                &mut tbf.rate,
+                self.rate,
+                self.cell_log,
+                self.mtu,
+                self.linklayer,
+            );
+            tbf.burst = calc_xmittime(self.rate, self.burst);
+            police.rate64 = (self.rate > u32::MAX as u64).then_some(self.rate);
+        }
+        if let Some(peakrate) = self.peakrate {
+            tbf.peakrate = self.rate_spec(peakrate);
+            police.ptab = calc_rtable(
+                &mut tbf.peakrate,
+                peakrate,
+                self.cell_log,
+                self.mtu,
+                self.linklayer,
+            );
+            police.peakrate64 = (peakrate > u32::MAX as u64).then_some(peakrate);
+        }
+        if let Some((rate, burst)) = self.pkt_rate {
+            if rate == 0 || burst == 0 {
+                return Err(Error::Encode(
+                    "Policer packet rate requires a rate and a burst".to_string(),
+                ));
+            }
+            police.pktrate64 = Some(rate);
+            // the kernel expects the time needed to send the burst, as for bytes
+            police.pktburst64 = Some(calc_xmittime(rate, burst.min(u32::MAX as u64) as u32) as u64);
+        }
+        police.tbf = Some(tbf);
+
+        Ok(police)
+    }
+
+    fn rate_spec(&self, rate: u64) -> RateSpec {
+        RateSpec {
+            rate: rate.min(u32::MAX as u64) as u32,
+            overhead: self.overhead,
+            mpu: self.mpu,
+            ..Default::default()
+        }
+    }
+}
+
+fn unmarshal_police(opts: Vec<TcOption>) -> Police {
+    let mut police = Police::default();
+
+    for opt in opts {
+        let kind = TcaPolice::from(opt.kind);
+        match kind {
+            TcaPolice::Tbf => police.tbf = bincode::deserialize(&opt.bytes).ok(),
+            TcaPolice::Rate => police.rtab = opt.bytes,
+            TcaPolice::PeakRate => police.ptab = opt.bytes,
+            TcaPolice::AvRate => police.avrate = unmarshal_u32_value(&opt.bytes),
+            TcaPolice::Result => police.result = unmarshal_u32_value(&opt.bytes).map(|v| v as i32),
+            TcaPolice::Tm => police.tm = bincode::deserialize(&opt.bytes).ok(),
+            TcaPolice::Rate64 => police.rate64 = unmarshal_u64_value(&opt.bytes),
+            TcaPolice::PeakRate64 => police.peakrate64 = unmarshal_u64_value(&opt.bytes),
+            TcaPolice::PktRate64 => police.pktrate64 = unmarshal_u64_value(&opt.bytes),
+            TcaPolice::PktBurst64 => police.pktburst64 = unmarshal_u64_value(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    police
+}
+
+fn marshal_police(police: &Police) -> Result<Vec<TcOption>, Error> {
+    let mut opts = Vec::new();
+
+    let tbf = police
+        .tbf
+        .as_ref()
+        .ok_or_else(|| Error::Encode("Policer requires tbf parameters".to_string()))?;
+    opts.push(TcOption {
+        kind: TcaPolice::Tbf as u16,
+        bytes: bincode::serialize(tbf).map_err(|e| Error::Encode(e.to_string()))?,
+    });
+    if !police.rtab.is_empty() {
+        opts.push(TcOption {
+            kind: TcaPolice::Rate as u16,
+            bytes: police.rtab.clone(),
+        });
+    }
+    if !police.ptab.is_empty() {
+        opts.push(TcOption {
+            kind: TcaPolice::PeakRate as u16,
+            bytes: police.ptab.clone(),
+        });
+    }
+    for (kind, value) in [
+        (TcaPolice::AvRate, police.avrate),
+        (TcaPolice::Result, police.result.map(|v| v as u32)),
+    ] {
+        if let Some(value) = value {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: value.to_ne_bytes().to_vec(),
+            });
+        }
+    }
+    for (kind, value) in [
+        (TcaPolice::Rate64, police.rate64),
+        (TcaPolice::PeakRate64, police.peakrate64),
+        (TcaPolice::PktRate64, police.pktrate64),
+        (TcaPolice::PktBurst64, police.pktburst64),
+    ] {
+        if let Some(value) = value {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: value.to_ne_bytes().to_vec(),
+            });
+        }
+    }
+
+    Ok(opts)
+}
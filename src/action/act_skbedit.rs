@@ -0,0 +1,92 @@
+use crate::action::{ActGen, Tcft};
+use crate::types::*;
+
+/// Options of the `skbedit` action, defined in `net/sched/act_skbedit.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Skbedit {
+    pub parms: ActGen,
+    pub tm: Option<Tcft>,
+    /// Priority, i.e. class id, set on the packet.
+    pub priority: Option<u32>,
+    pub queue_mapping: Option<u16>,
+    /// Upper bound of the queues picked from `queue_mapping` with `SKBEDIT_F_TXQ_SKBHASH`.
+    pub queue_mapping_max: Option<u16>,
+    pub mark: Option<u32>,
+    /// Bits of the mark set from `mark`.
+    pub mask: Option<u32>,
+    /// `PACKET_*` type set on the packet, e.g. `PACKET_HOST`.
+    pub ptype: Option<u16>,
+    /// `SKBEDIT_F_*` flags.
+    pub flags: Option<u64>,
+}
+
+pub const SKBEDIT_F_PRIORITY: u64 = 0x1;
+pub const SKBEDIT_F_QUEUE_MAPPING: u64 = 0x2;
+pub const SKBEDIT_F_MARK: u64 = 0x4;
+pub const SKBEDIT_F_PTYPE: u64 = 0x8;
+pub const SKBEDIT_F_MASK: u64 = 0x10;
+pub const SKBEDIT_F_INHERITDSFIELD: u64 = 0x20;
+pub const SKBEDIT_F_TXQ_SKBHASH: u64 = 0x40;
+
+pub enum TcaSkbedit {
+    Unspec = 0,
+    Tm,
+    Parms,
+    Priority,
+    QueueMapping,
+    Mark,
+    Pad,
+    Ptype,
+    Mask,
+    Flags,
+    QueueMappingMax,
+    Max,
+}
+
+impl From<u16> for TcaSkbedit {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaSkbedit::Unspec,
+            1 => TcaSkbedit::Tm,
+            2 => TcaSkbedit::Parms,
+            3 => TcaSkbedit::Priority,
+            4 => TcaSkbedit::QueueMapping,
+            5 => TcaSkbedit::Mark,
+            6 => TcaSkbedit::Pad,
+            7 => TcaSkbedit::Ptype,
+            8 => TcaSkbedit::Mask,
+            9 => TcaSkbedit::Flags,
+            10 => TcaSkbedit::QueueMappingMax,
+            _ => TcaSkbedit::Max,
+        }
+    }
+}
+
+impl Skbedit {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_skbedit(opts)
+    }
+}
+
+fn unmarshal_skbedit(opts: Vec<TcOption>) -> Skbedit {
+    let mut skbedit = Skbedit::default();
+
+    for opt in opts {
+        let kind = TcaSkbedit::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaSkbedit::Tm => skbedit.tm = bincode::deserialize(b).ok(),
+            TcaSkbedit::Parms => skbedit.parms = bincode::deserialize(b).unwrap_or_default(),
+            TcaSkbedit::Priority => skbedit.priority = unmarshal_u32_value(b),
+            TcaSkbedit::QueueMapping => skbedit.queue_mapping = unmarshal_u16_value(b),
+            TcaSkbedit::QueueMappingMax => skbedit.queue_mapping_max = unmarshal_u16_value(b),
+            TcaSkbedit::Mark => skbedit.mark = unmarshal_u32_value(b),
+            TcaSkbedit::Mask => skbedit.mask = unmarshal_u32_value(b),
+            TcaSkbedit::Ptype => skbedit.ptype = unmarshal_u16_value(b),
+            TcaSkbedit::Flags => skbedit.flags = unmarshal_u64_value(b),
+            _ => (),
+        }
+    }
+
+    skbedit
+}
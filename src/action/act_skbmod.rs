@@ -0,0 +1,81 @@
+use crate::action::{ActGen, Tcft};
+use crate::types::*;
+
+/// Options of the `skbmod` action, defined in `net/sched/act_skbmod.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Skbmod {
+    pub parms: ActGen,
+    /// `SKBMOD_F_*` flags, from `struct tc_skbmod`.
+    pub flags: u64,
+    pub tm: Option<Tcft>,
+    pub dmac: Option<[u8; 6]>,
+    pub smac: Option<[u8; 6]>,
+    /// Ethertype set on the packet, in host byte order.
+    pub etype: Option<u16>,
+}
+
+pub const SKBMOD_F_DMAC: u64 = 0x1;
+pub const SKBMOD_F_SMAC: u64 = 0x2;
+pub const SKBMOD_F_ETYPE: u64 = 0x4;
+pub const SKBMOD_F_SWAPMAC: u64 = 0x8;
+pub const SKBMOD_F_ECN: u64 = 0x10;
+
+/// Offset of `flags` in `struct tc_skbmod`, aligned after `tc_gen`.
+const SKBMOD_FLAGS_OFFSET: usize = 24;
+
+pub enum TcaSkbmod {
+    Unspec = 0,
+    Tm,
+    Parms,
+    Dmac,
+    Smac,
+    Etype,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaSkbmod {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaSkbmod::Unspec,
+            1 => TcaSkbmod::Tm,
+            2 => TcaSkbmod::Parms,
+            3 => TcaSkbmod::Dmac,
+            4 => TcaSkbmod::Smac,
+            5 => TcaSkbmod::Etype,
+            6 => TcaSkbmod::Pad,
+            _ => TcaSkbmod::Max,
+        }
+    }
+}
+
+impl Skbmod {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_skbmod(opts)
+    }
+}
+
+fn unmarshal_skbmod(opts: Vec<TcOption>) -> Skbmod {
+    let mut skbmod = Skbmod::default();
+
+    for opt in opts {
+        let kind = TcaSkbmod::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaSkbmod::Tm => skbmod.tm = bincode::deserialize(b).ok(),
+            TcaSkbmod::Parms => {
+                skbmod.parms = bincode::deserialize(b).unwrap_or_default();
+                skbmod.flags = b
+                    .get(SKBMOD_FLAGS_OFFSET..)
+                    .and_then(unmarshal_u64_value)
+                    .unwrap_or_default();
+            }
+            TcaSkbmod::Dmac => skbmod.dmac = b.get(..6).map(|b| b.try_into().unwrap()),
+            TcaSkbmod::Smac => skbmod.smac = b.get(..6).map(|b| b.try_into().unwrap()),
+            TcaSkbmod::Etype => skbmod.etype = unmarshal_u16_value(b),
+            _ => (),
+        }
+    }
+
+    skbmod
+}
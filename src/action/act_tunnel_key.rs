@@ -0,0 +1,387 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::action::{unmarshal_act_ipv4, unmarshal_act_ipv6, ActGen, Tcft, TC_ACT_PIPE};
+use crate::{errors::Error, types::*};
+
+/// Options of the `tunnel_key` action, defined in `net/sched/act_tunnel_key.c`.
+///
+/// Fields in network byte order on the wire (`key_id`, `dst_port`, geneve `class`)
+/// are converted to host byte order.
+#[derive(Default, Debug, PartialEq)]
+pub struct TunnelKey {
+    pub parms: TunnelKeyParms,
+    pub tm: Option<Tcft>,
+    pub enc_src: Option<IpAddr>,
+    pub enc_dst: Option<IpAddr>,
+    /// Tunnel id, e.g. the VNI of a VXLAN tunnel.
+    pub key_id: Option<u32>,
+    pub dst_port: Option<u16>,
+    pub no_csum: Option<bool>,
+    pub no_frag: bool,
+    pub tos: Option<u8>,
+    pub ttl: Option<u8>,
+    pub geneve_opts: Vec<GeneveOpt>,
+    /// Group policy of a VXLAN tunnel.
+    pub vxlan_gbp: Option<u32>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_tunnel_key.h` as `struct tc_tunnel_key`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TunnelKeyParms {
+    pub gen: ActGen,
+    /// `TCA_TUNNEL_KEY_ACT_*` operation.
+    pub t_action: i32,
+}
+
+/// A geneve option, nested in `TCA_TUNNEL_KEY_ENC_OPTS_GENEVE`.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct GeneveOpt {
+    pub class: u16,
+    pub opt_type: u8,
+    /// Option data, a multiple of 4 bytes.
+    pub data: Vec<u8>,
+}
+
+pub const TCA_TUNNEL_KEY_ACT_SET: i32 = 1;
+pub const TCA_TUNNEL_KEY_ACT_RELEASE: i32 = 2;
+
+pub enum TcaTunnelKey {
+    Unspec = 0,
+    Tm,
+    Parms,
+    EncIpv4Src,
+    EncIpv4Dst,
+    EncIpv6Src,
+    EncIpv6Dst,
+    EncKeyId,
+    Pad,
+    EncDstPort,
+    NoCsum,
+    EncOpts,
+    EncTos,
+    EncTtl,
+    NoFrag,
+    Max,
+}
+
+impl From<u16> for TcaTunnelKey {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaTunnelKey::Unspec,
+            1 => TcaTunnelKey::Tm,
+            2 => TcaTunnelKey::Parms,
+            3 => TcaTunnelKey::EncIpv4Src,
+            4 => TcaTunnelKey::EncIpv4Dst,
+            5 => TcaTunnelKey::EncIpv6Src,
+            6 => TcaTunnelKey::EncIpv6Dst,
+            7 => TcaTunnelKey::EncKeyId,
+            8 => TcaTunnelKey::Pad,
+            9 => TcaTunnelKey::EncDstPort,
+            10 => TcaTunnelKey::NoCsum,
+            11 => TcaTunnelKey::EncOpts,
+            12 => TcaTunnelKey::EncTos,
+            13 => TcaTunnelKey::EncTtl,
+            14 => TcaTunnelKey::NoFrag,
+            _ => TcaTunnelKey::Max,
+        }
+    }
+}
+
+pub enum TcaTunnelKeyEncOpts {
+    Unspec = 0,
+    Geneve,
+    Vxlan,
+    Erspan,
+    Max,
+}
+
+impl From<u16> for TcaTunnelKeyEncOpts {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaTunnelKeyEncOpts::Unspec,
+            1 => TcaTunnelKeyEncOpts::Geneve,
+            2 => TcaTunnelKeyEncOpts::Vxlan,
+            3 => TcaTunnelKeyEncOpts::Erspan,
+            _ => TcaTunnelKeyEncOpts::Max,
+        }
+    }
+}
+
+pub enum TcaTunnelKeyEncOptGeneve {
+    Unspec = 0,
+    Class,
+    Type,
+    Data,
+    Max,
+}
+
+impl From<u16> for TcaTunnelKeyEncOptGeneve {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaTunnelKeyEncOptGeneve::Unspec,
+            1 => TcaTunnelKeyEncOptGeneve::Class,
+            2 => TcaTunnelKeyEncOptGeneve::Type,
+            3 => TcaTunnelKeyEncOptGeneve::Data,
+            _ => TcaTunnelKeyEncOptGeneve::Max,
+        }
+    }
+}
+
+/// `TCA_TUNNEL_KEY_ENC_OPT_VXLAN_GBP`, nested in `TCA_TUNNEL_KEY_ENC_OPTS_VXLAN`.
+const TCA_TUNNEL_KEY_ENC_OPT_VXLAN_GBP: u16 = 1;
+
+impl TunnelKey {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_tunnel_key(opts)
+    }
+
+    /// Creates an action setting the tunnel metadata of packets,
+    /// like `tc ... action tunnel_key set src_ip <src> dst_ip <dst>`.
+    pub fn set(src: IpAddr, dst: IpAddr) -> Self {
+        Self {
+            parms: TunnelKeyParms {
+                gen: ActGen {
+                    action: TC_ACT_PIPE,
+                    ..Default::default()
+                },
+                t_action: TCA_TUNNEL_KEY_ACT_SET,
+            },
+            enc_src: Some(src),
+            enc_dst: Some(dst),
+            ..Default::default()
+        }
+    }
+
+    /// Creates an action removing the tunnel metadata of packets,
+    /// like `tc ... action tunnel_key unset`.
+    pub fn release() -> Self {
+        Self {
+            parms: TunnelKeyParms {
+                gen: ActGen {
+                    action: TC_ACT_PIPE,
+                    ..Default::default()
+                },
+                t_action: TCA_TUNNEL_KEY_ACT_RELEASE,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Sets the tunnel id.
+    pub fn key_id(&mut self, key_id: u32) -> &mut Self {
+        self.key_id = Some(key_id);
+        self
+    }
+
+    /// Sets the UDP destination port of the tunnel.
+    pub fn dst_port(&mut self, dst_port: u16) -> &mut Self {
+        self.dst_port = Some(dst_port);
+        self
+    }
+
+    /// Sets the TOS and TTL of the outer IP header.
+    pub fn tos_ttl(&mut self, tos: u8, ttl: u8) -> &mut Self {
+        self.tos = Some(tos);
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Adds a geneve option.
+    pub fn geneve_opt(&mut self, class: u16, opt_type: u8, data: &[u8]) -> &mut Self {
+        self.geneve_opts.push(GeneveOpt {
+            class,
+            opt_type,
+            data: data.to_vec(),
+        });
+        self
+    }
+
+    /// Encodes the action into `TCA_TUNNEL_KEY_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_tunnel_key(self)
+    }
+}
+
+fn unmarshal_tunnel_key(opts: Vec<TcOption>) -> TunnelKey {
+    let mut key = TunnelKey::default();
+
+    for opt in opts {
+        let kind = TcaTunnelKey::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaTunnelKey::Tm => key.tm = bincode::deserialize(b).ok(),
+            TcaTunnelKey::Parms => key.parms = bincode::deserialize(b).unwrap_or_default(),
+            TcaTunnelKey::EncIpv4Src => key.enc_src = unmarshal_act_ipv4(b),
+            TcaTunnelKey::EncIpv4Dst => key.enc_dst = unmarshal_act_ipv4(b),
+            TcaTunnelKey::EncIpv6Src => key.enc_src = unmarshal_act_ipv6(b),
+            TcaTunnelKey::EncIpv6Dst => key.enc_dst = unmarshal_act_ipv6(b),
+            TcaTunnelKey::EncKeyId => {
+                key.key_id = b
+                    .get(..4)
+                    .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+            }
+            TcaTunnelKey::EncDstPort => {
+                key.dst_port = b
+                    .get(..2)
+                    .map(|b| u16::from_be_bytes(b.try_into().unwrap()))
+            }
+            TcaTunnelKey::NoCsum => key.no_csum = b.first().map(|v| *v != 0),
+            TcaTunnelKey::NoFrag => key.no_frag = true,
+            TcaTunnelKey::EncTos => key.tos = b.first().copied(),
+            TcaTunnelKey::EncTtl => key.ttl = b.first().copied(),
+            TcaTunnelKey::EncOpts => unmarshal_enc_opts(&mut key, b),
+            _ => (),
+        }
+    }
+
+    key
+}
+
+fn unmarshal_enc_opts(key: &mut TunnelKey, bytes: &[u8]) {
+    for opt in unmarshal_nested(bytes).unwrap_or_default() {
+        let attrs = unmarshal_nested(&opt.bytes).unwrap_or_default();
+        match TcaTunnelKeyEncOpts::from(opt.kind) {
+            // the options follow each other, each starting with its class
+            TcaTunnelKeyEncOpts::Geneve => {
+                for attr in attrs {
+                    let b = attr.bytes.as_slice();
+                    match TcaTunnelKeyEncOptGeneve::from(attr.kind) {
+                        TcaTunnelKeyEncOptGeneve::Class => key.geneve_opts.push(GeneveOpt {
+                            class: b
+                                .get(..2)
+                                .map(|b| u16::from_be_bytes(b.try_into().unwrap()))
+                                .unwrap_or_default(),
+                            ..Default::default()
+                        }),
+                        TcaTunnelKeyEncOptGeneve::Type => {
+                            if let Some(opt) = key.geneve_opts.last_mut() {
+                                opt.opt_type = b.first().copied().unwrap_or_default();
+                            }
+                        }
+                        TcaTunnelKeyEncOptGeneve::Data => {
+                            if let Some(opt) = key.geneve_opts.last_mut() {
+                                opt.data = b.to_vec();
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            TcaTunnelKeyEncOpts::Vxlan => {
+                key.vxlan_gbp = attrs
+                    .iter()
+                    .find(|attr| attr.kind == TCA_TUNNEL_KEY_ENC_OPT_VXLAN_GBP)
+                    .and_then(|attr| unmarshal_u32_value(&attr.bytes))
+            }
+            _ => (),
+        }
+    }
+}
+
+fn marshal_tunnel_key(key: &TunnelKey) -> Result<Vec<TcOption>, Error> {
+    let mut opts = vec![TcOption {
+        kind: TcaTunnelKey::Parms as u16,
+        bytes: bincode::serialize(&key.parms).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+
+    for (addr, v4, v6) in [
+        (
+            key.enc_src,
+            TcaTunnelKey::EncIpv4Src,
+            TcaTunnelKey::EncIpv6Src,
+        ),
+        (
+            key.enc_dst,
+            TcaTunnelKey::EncIpv4Dst,
+            TcaTunnelKey::EncIpv6Dst,
+        ),
+    ] {
+        match addr {
+            Some(IpAddr::V4(addr)) => opts.push(TcOption {
+                kind: v4 as u16,
+                bytes: addr.octets().to_vec(),
+            }),
+            Some(IpAddr::V6(addr)) => opts.push(TcOption {
+                kind: v6 as u16,
+                bytes: addr.octets().to_vec(),
+            }),
+            None => (),
+        }
+    }
+    if let Some(key_id) = key.key_id {
+        opts.push(TcOption {
+            kind: TcaTunnelKey::EncKeyId as u16,
+            bytes: key_id.to_be_bytes().to_vec(),
+        });
+    }
+    if let Some(dst_port) = key.dst_port {
+        opts.push(TcOption {
+            kind: TcaTunnelKey::EncDstPort as u16,
+            bytes: dst_port.to_be_bytes().to_vec(),
+        });
+    }
+    if let Some(no_csum) = key.no_csum {
+        opts.push(TcOption {
+            kind: TcaTunnelKey::NoCsum as u16,
+            bytes: vec![no_csum as u8],
+        });
+    }
+    if key.no_frag {
+        opts.push(TcOption {
+            kind: TcaTunnelKey::NoFrag as u16,
+            bytes: vec![],
+        });
+    }
+    for (kind, value) in [
+        (TcaTunnelKey::EncTos, key.tos),
+        (TcaTunnelKey::EncTtl, key.ttl),
+    ] {
+        if let Some(value) = value {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: vec![value],
+            });
+        }
+    }
+
+    // the kernel parses a single option per `TCA_TUNNEL_KEY_ENC_OPTS_GENEVE` nest
+    let mut enc_opts = Vec::new();
+    for opt in &key.geneve_opts {
+        enc_opts.push(TcOption {
+            kind: TcaTunnelKeyEncOpts::Geneve as u16,
+            bytes: marshal_nested(&[
+                TcOption {
+                    kind: TcaTunnelKeyEncOptGeneve::Class as u16,
+                    bytes: opt.class.to_be_bytes().to_vec(),
+                },
+                TcOption {
+                    kind: TcaTunnelKeyEncOptGeneve::Type as u16,
+                    bytes: vec![opt.opt_type],
+                },
+                TcOption {
+                    kind: TcaTunnelKeyEncOptGeneve::Data as u16,
+                    bytes: opt.data.clone(),
+                },
+            ]),
+        });
+    }
+    if let Some(gbp) = key.vxlan_gbp {
+        enc_opts.push(TcOption {
+            kind: TcaTunnelKeyEncOpts::Vxlan as u16,
+            bytes: marshal_nested(&[TcOption {
+                kind: TCA_TUNNEL_KEY_ENC_OPT_VXLAN_GBP,
+                bytes: gbp.to_ne_bytes().to_vec(),
+            }]),
+        });
+    }
+    if !enc_opts.is_empty() {
+        opts.push(TcOption {
+            kind: TcaTunnelKey::EncOpts as u16,
+            bytes: marshal_nested(&enc_opts),
+        });
+    }
+
+    Ok(opts)
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::action::{ActGen, Tcft};
+use crate::types::*;
+
+/// Options of the `vlan` action, defined in `net/sched/act_vlan.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Vlan {
+    pub parms: VlanParms,
+    pub tm: Option<Tcft>,
+    pub push_vlan_id: Option<u16>,
+    /// Protocol of the pushed tag, e.g. `ETH_P_8021Q`, in host byte order.
+    pub push_vlan_protocol: Option<u16>,
+    pub push_vlan_priority: Option<u8>,
+    pub push_eth_dst: Option<[u8; 6]>,
+    pub push_eth_src: Option<[u8; 6]>,
+}
+
+/// Defined in `include/uapi/linux/tc_act/tc_vlan.h` as `struct tc_vlan`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VlanParms {
+    pub gen: ActGen,
+    /// `TCA_VLAN_ACT_*` operation on the tag.
+    pub v_action: i32,
+}
+
+pub const TCA_VLAN_ACT_POP: i32 = 1;
+pub const TCA_VLAN_ACT_PUSH: i32 = 2;
+pub const TCA_VLAN_ACT_MODIFY: i32 = 3;
+pub const TCA_VLAN_ACT_POP_ETH: i32 = 4;
+pub const TCA_VLAN_ACT_PUSH_ETH: i32 = 5;
+
+pub enum TcaVlan {
+    Unspec = 0,
+    Tm,
+    Parms,
+    PushVlanId,
+    PushVlanProtocol,
+    Pad,
+    PushVlanPriority,
+    PushEthDst,
+    PushEthSrc,
+    Max,
+}
+
+impl From<u16> for TcaVlan {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaVlan::Unspec,
+            1 => TcaVlan::Tm,
+            2 => TcaVlan::Parms,
+            3 => TcaVlan::PushVlanId,
+            4 => TcaVlan::PushVlanProtocol,
+            5 => TcaVlan::Pad,
+            6 => TcaVlan::PushVlanPriority,
+            7 => TcaVlan::PushEthDst,
+            8 => TcaVlan::PushEthSrc,
+            _ => TcaVlan::Max,
+        }
+    }
+}
+
+impl Vlan {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_vlan(opts)
+    }
+}
+
+fn unmarshal_vlan(opts: Vec<TcOption>) -> Vlan {
+    let mut vlan = Vlan::default();
+
+    for opt in opts {
+        let kind = TcaVlan::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaVlan::Tm => vlan.tm = bincode::deserialize(b).ok(),
+            TcaVlan::Parms => vlan.parms = bincode::deserialize(b).unwrap_or_default(),
+            TcaVlan::PushVlanId => vlan.push_vlan_id = unmarshal_u16_value(b),
+            TcaVlan::PushVlanProtocol => {
+                vlan.push_vlan_protocol = b
+                    .get(..2)
+                    .map(|b| u16::from_be_bytes(b.try_into().unwrap()))
+            }
+            TcaVlan::PushVlanPriority => vlan.push_vlan_priority = b.first().copied(),
+            TcaVlan::PushEthDst => vlan.push_eth_dst = b.get(..6).map(|b| b.try_into().unwrap()),
+            TcaVlan::PushEthSrc => vlan.push_eth_src = b.get(..6).map(|b| b.try_into().unwrap()),
+            _ => (),
+        }
+    }
+
+    vlan
+}
@@ -0,0 +1,145 @@
+use netlink_packet_core::{
+    NetlinkDeserializable, NetlinkHeader, NetlinkPayload, NetlinkSerializable,
+};
+use netlink_packet_utils::DecodeError;
+
+use crate::action::{marshal_bitfield32, TcaAct};
+use crate::types::{marshal_nested, unmarshal_nested, TcOption};
+
+pub const RTM_NEWACTION: u16 = 48;
+pub const RTM_DELACTION: u16 = 49;
+pub const RTM_GETACTION: u16 = 50;
+
+/// Size of `struct tcamsg`, the header of `RTM_*ACTION` messages.
+const TCA_MSG_LEN: usize = 4;
+
+// Flags of `TCA_ROOT_FLAGS`, defined in `include/uapi/linux/rtnetlink.h`
+pub const TCA_ACT_FLAG_LARGE_DUMP_ON: u32 = 1 << 0;
+pub const TCA_ACT_FLAG_TERSE_DUMP: u32 = 1 << 1;
+
+pub enum TcaRoot {
+    Unspec = 0,
+    Tab,
+    Flags,
+    Count,
+    TimeDelta,
+    ExtWarnMsg,
+    Max,
+}
+
+impl From<u16> for TcaRoot {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaRoot::Unspec,
+            1 => TcaRoot::Tab,
+            2 => TcaRoot::Flags,
+            3 => TcaRoot::Count,
+            4 => TcaRoot::TimeDelta,
+            5 => TcaRoot::ExtWarnMsg,
+            _ => TcaRoot::Max,
+        }
+    }
+}
+
+/// Body of `RTM_*ACTION` messages: `struct tcamsg` followed by `TCA_ROOT_*` attributes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TcaMessage {
+    pub family: u8,
+    pub attrs: Vec<TcOption>,
+}
+
+/// `RTM_*ACTION` messages, which `netlink-packet-route` doesn't model.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionMessage {
+    NewAction(TcaMessage),
+    DelAction(TcaMessage),
+    GetAction(TcaMessage),
+}
+
+impl ActionMessage {
+    /// Builds a request to dump the actions of `kind`, e.g. `mirred`.
+    pub fn dump(kind: &str) -> Self {
+        let mut name = kind.as_bytes().to_vec();
+        name.push(0);
+        let action = TcOption {
+            kind: 1, // order of the action in the list
+            bytes: marshal_nested(&[TcOption {
+                kind: TcaAct::Kind as u16,
+                bytes: name,
+            }]),
+        };
+        ActionMessage::GetAction(TcaMessage {
+            family: 0,
+            attrs: vec![
+                TcOption {
+                    kind: TcaRoot::Tab as u16,
+                    bytes: marshal_nested(&[action]),
+                },
+                TcOption {
+                    kind: TcaRoot::Flags as u16,
+                    bytes: marshal_bitfield32(TCA_ACT_FLAG_LARGE_DUMP_ON),
+                },
+            ],
+        })
+    }
+
+    pub fn message(&self) -> &TcaMessage {
+        match self {
+            ActionMessage::NewAction(message)
+            | ActionMessage::DelAction(message)
+            | ActionMessage::GetAction(message) => message,
+        }
+    }
+}
+
+impl NetlinkSerializable for ActionMessage {
+    fn message_type(&self) -> u16 {
+        match self {
+            ActionMessage::NewAction(_) => RTM_NEWACTION,
+            ActionMessage::DelAction(_) => RTM_DELACTION,
+            ActionMessage::GetAction(_) => RTM_GETACTION,
+        }
+    }
+
+    fn buffer_len(&self) -> usize {
+        TCA_MSG_LEN + marshal_nested(&self.message().attrs).len()
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        let message = self.message();
+        buffer[..TCA_MSG_LEN].fill(0);
+        buffer[0] = message.family;
+        let attrs = marshal_nested(&message.attrs);
+        buffer[TCA_MSG_LEN..TCA_MSG_LEN + attrs.len()].copy_from_slice(&attrs);
+    }
+}
+
+impl NetlinkDeserializable for ActionMessage {
+    type Error = DecodeError;
+
+    fn deserialize(header: &NetlinkHeader, payload: &[u8]) -> Result<Self, Self::Error> {
+        if payload.len() < TCA_MSG_LEN {
+            return Err(DecodeError::from("Action message is too short"));
+        }
+        let attrs = unmarshal_nested(&payload[TCA_MSG_LEN..])
+            .map_err(|e| DecodeError::from(e.to_string()))?;
+        let message = TcaMessage {
+            family: payload[0],
+            attrs,
+        };
+        match header.message_type {
+            RTM_NEWACTION => Ok(ActionMessage::NewAction(message)),
+            RTM_DELACTION => Ok(ActionMessage::DelAction(message)),
+            RTM_GETACTION => Ok(ActionMessage::GetAction(message)),
+            message_type => Err(DecodeError::from(format!(
+                "Unknown action message type: {message_type}"
+            ))),
+        }
+    }
+}
+
+impl From<ActionMessage> for NetlinkPayload<ActionMessage> {
+    fn from(message: ActionMessage) -> Self {
+        NetlinkPayload::InnerMessage(message)
+    }
+}
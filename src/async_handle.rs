@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use netlink_packet_core::NetlinkMessage;
+use netlink_packet_route::{RtnlMessage, TcMessage};
+use netlink_sys::{protocols::NETLINK_ROUTE, AsyncSocket, AsyncSocketExt, SocketAddr, TokioSocket};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::action::ActionMessage;
+use crate::errors::Error;
+use crate::handle::{
+    bpf_programs, dump_request, filter_message, message_sequence_number, serialize, split, Replies,
+    Reply, DUMP_RETRIES,
+};
+use crate::qdiscs::ClsactHook;
+use crate::types::{Action, Tc};
+use crate::ParseOptions;
+
+/// A netlink message routed to a pending request, decoded by the request.
+type RawReply = Result<Vec<u8>, Error>;
+/// The pending requests by sequence number, `None` once the receiver task has stopped.
+type Pending = Arc<Mutex<Option<HashMap<u32, mpsc::UnboundedSender<RawReply>>>>>;
+
+/// `AsyncTcHandle` is the asynchronous counterpart of `TcHandle`, built on `tokio`.
+///
+/// Requests can be issued concurrently over the same socket: a background task receives
+/// the replies and routes them to the pending request with the same sequence number.
+/// Since the kernel runs a single dump at a time per socket, concurrent dumps are queued
+/// while other requests are sent right away.
+///
+/// # Example
+/// ```no_run
+/// use netlink_tc::{AsyncTcHandle, ParseOptions};
+///
+/// # async fn run() {
+/// let mut opts = ParseOptions::new();
+/// opts.fail_on_unknown_attribute(false)
+///     .fail_on_unknown_option(false);
+/// let handle = AsyncTcHandle::with_options(opts).unwrap();
+/// let (qdiscs, classes) = tokio::join!(handle.qdiscs(), handle.classes(1));
+/// # }
+/// ```
+pub struct AsyncTcHandle {
+    socket: Arc<TokioSocket>,
+    sequence_number: AtomicU32,
+    pending: Pending,
+    dump_lock: tokio::sync::Mutex<()>,
+    opts: ParseOptions,
+    receiver: JoinHandle<()>,
+}
+
+impl AsyncTcHandle {
+    /// Opens a new handle, parsing replies with the default `ParseOptions`.
+    /// Must be called from within a `tokio` runtime.
+    pub fn new() -> Result<Self, Error> {
+        Self::with_options(ParseOptions::default())
+    }
+
+    /// Opens a new handle, parsing replies with `opts`.
+    /// Must be called from within a `tokio` runtime.
+    pub fn with_options(opts: ParseOptions) -> Result<Self, Error> {
+        let mut socket = TokioSocket::new(NETLINK_ROUTE)?;
+        socket.socket_mut().connect(&SocketAddr::new(0, 0))?;
+        let socket = Arc::new(socket);
+        let pending = Arc::new(Mutex::new(Some(HashMap::new())));
+        let receiver = tokio::spawn(receive(socket.clone(), pending.clone()));
+        Ok(Self {
+            socket,
+            sequence_number: AtomicU32::new(0),
+            pending,
+            dump_lock: tokio::sync::Mutex::new(()),
+            opts,
+            receiver,
+        })
+    }
+
+    /// Returns the queueing disciplines of all interfaces.
+    pub async fn qdiscs(&self) -> Result<Vec<Tc>, Error> {
+        let messages = self
+            .dump(RtnlMessage::GetQueueDiscipline(TcMessage::default()))
+            .await?;
+        self.opts.tc(messages)
+    }
+
+    /// Returns the queueing disciplines of the interface with index `index`.
+    pub async fn qdiscs_for(&self, index: i32) -> Result<Vec<Tc>, Error> {
+        let qdiscs = self.qdiscs().await?;
+        Ok(qdiscs
+            .into_iter()
+            .filter(|tc| tc.msg.index == index as u32)
+            .collect())
+    }
+
+    /// Returns the traffic classes of the interface with index `index`.
+    pub async fn classes(&self, index: i32) -> Result<Vec<Tc>, Error> {
+        let messages = self
+            .dump(RtnlMessage::GetTrafficClass(TcMessage::with_index(index)))
+            .await?;
+        self.opts.tc(messages)
+    }
+
+    /// Returns the filters attached to `parent` on the interface with index `index`.
+    pub async fn filters(&self, index: i32, parent: u32) -> Result<Vec<Tc>, Error> {
+        let messages = self
+            .dump(RtnlMessage::GetTrafficFilter(filter_message(index, parent)))
+            .await?;
+        self.opts.tc(messages)
+    }
+
+    /// Returns the BPF programs attached to `hook` of the `clsact` qdisc
+    /// on the interface with index `index`, ordered by chain and priority.
+    pub async fn bpf_programs(&self, index: i32, hook: ClsactHook) -> Result<Vec<Tc>, Error> {
+        self.filters(index, hook.parent()).await.map(bpf_programs)
+    }
+
+    /// Returns the actions of `kind`, e.g. `mirred`, from the kernel action tables.
+    pub async fn actions(&self, kind: &str) -> Result<Vec<Action>, Error> {
+        let messages = self.dump_messages(ActionMessage::dump(kind)).await?;
+        self.opts.actions(messages)
+    }
+
+    /// Sends a dump request for `message` and returns the replies.
+    /// The dump is retried if the kernel reports it was interrupted by a concurrent change.
+    pub async fn dump(
+        &self,
+        message: RtnlMessage,
+    ) -> Result<Vec<NetlinkMessage<RtnlMessage>>, Error> {
+        self.dump_messages(message).await
+    }
+
+    async fn dump_messages<T: Reply>(&self, message: T) -> Result<Vec<NetlinkMessage<T>>, Error> {
+        let _lock = self.dump_lock.lock().await;
+        for _ in 0..DUMP_RETRIES {
+            let replies = self.send(dump_request(message.clone())).await?;
+            if !replies.interrupted {
+                return Ok(replies.messages);
+            }
+        }
+        Err(Error::Netlink(format!(
+            "Dump interrupted {DUMP_RETRIES} times"
+        )))
+    }
+
+    /// Sends `message` and waits for the kernel to acknowledge it.
+    /// The message must have `NLM_F_ACK` set, see `QDiscRequest::message`.
+    pub async fn request(&self, message: NetlinkMessage<RtnlMessage>) -> Result<(), Error> {
+        self.send(message).await.map(|_| ())
+    }
+
+    async fn send<T: Reply>(&self, message: NetlinkMessage<T>) -> Result<Replies<T>, Error> {
+        let sequence_number = self
+            .sequence_number
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(sequence_number, tx),
+            None => return Err(Error::Netlink("Netlink receiver stopped".to_string())),
+        };
+        // unregisters the request even if the future is dropped before completion
+        let _guard = PendingGuard {
+            pending: &self.pending,
+            sequence_number,
+        };
+
+        self.exchange(message, sequence_number, &mut rx).await
+    }
+
+    async fn exchange<T: Reply>(
+        &self,
+        message: NetlinkMessage<T>,
+        sequence_number: u32,
+        rx: &mut mpsc::UnboundedReceiver<RawReply>,
+    ) -> Result<Replies<T>, Error> {
+        self.socket
+            .send(&serialize(message, sequence_number))
+            .await?;
+
+        let mut replies = Replies::default();
+        while let Some(reply) = rx.recv().await {
+            if replies.push(T::decode(&reply?, &self.opts)?)? {
+                return Ok(replies);
+            }
+        }
+        Err(Error::Netlink("Netlink receiver stopped".to_string()))
+    }
+}
+
+impl Drop for AsyncTcHandle {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    sequence_number: u32,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&self.sequence_number);
+        }
+    }
+}
+
+/// Receives datagrams from `socket` and routes each message to the pending request
+/// with the same sequence number.
+/// The task stops on a socket error other than an interruption, failing the pending requests.
+async fn receive(socket: Arc<TokioSocket>, pending: Pending) {
+    loop {
+        let buf = match socket.recv_from_full().await {
+            Ok((buf, _)) => buf,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                // later requests fail right away instead of waiting for replies
+                if let Some(pending) = pending.lock().unwrap().take() {
+                    fail_pending(&pending, &e.to_string());
+                }
+                return;
+            }
+        };
+
+        let pending = pending.lock().unwrap();
+        let Some(pending) = pending.as_ref() else {
+            return;
+        };
+        match split(&buf) {
+            Ok(messages) => {
+                for bytes in messages {
+                    if let Some(tx) = pending.get(&message_sequence_number(bytes)) {
+                        let _ = tx.send(Ok(bytes.to_vec()));
+                    }
+                }
+            }
+            // The datagram can't be attributed to a request, fail all of them.
+            Err(e) => fail_pending(pending, &e.to_string()),
+        }
+    }
+}
+
+fn fail_pending(pending: &HashMap<u32, mpsc::UnboundedSender<RawReply>>, error: &str) {
+    for tx in pending.values() {
+        let _ = tx.send(Err(Error::Netlink(error.to_string())));
+    }
+}
@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
 
+use crate::rate::{calc_rtable, calc_xmittime, LinkLayer};
 use crate::{errors::Error, types::*};
 
+/// Defined in `include/uapi/linux/pkt_sched.h`.
+pub const TC_HTB_PROTOVER: u32 = 3;
+
 /// Defined in `include/uapi/linux/pkt_sched.h`.
 #[derive(Default, Debug, PartialEq)]
 pub struct Htb {
@@ -37,7 +41,7 @@ pub struct HtbGlob {
 }
 
 /// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_htb_xstats`.
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
 pub struct HtbXstats {
     pub lends: u32,
     pub borrows: u32,
@@ -82,6 +86,189 @@ impl Htb {
     }
 }
 
+impl Htb {
+    /// Encodes the class into `TCA_HTB_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_htb(self)
+    }
+}
+
+/// `HtbClassBuilder` computes the options of an HTB class the way `tc class add ... htb` does,
+/// including the rate and ceil tables.
+///
+/// Rates are in bytes per second and bursts in bytes.
+///
+/// # Example
+/// ```
+/// use netlink_tc::class::HtbClassBuilder;
+///
+/// // 10mbit with a 20mbit ceil
+/// let htb = HtbClassBuilder::new(1_250_000).ceil(2_500_000).prio(1).build().unwrap();
+/// assert_eq!(htb.parms.unwrap().ceil.rate, 2_500_000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HtbClassBuilder {
+    rate: u64,
+    ceil: Option<u64>,
+    burst: Option<u32>,
+    cburst: Option<u32>,
+    quantum: u32,
+    prio: u32,
+    mtu: u32,
+    mpu: u16,
+    overhead: u16,
+    cell_log: Option<u8>,
+    ccell_log: Option<u8>,
+    linklayer: LinkLayer,
+}
+
+impl HtbClassBuilder {
+    /// Creates a builder for a class guaranteed `rate` bytes per second.
+    /// By default, the class can't borrow above its rate.
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the maximum rate in bytes per second the class can borrow up to.
+    pub fn ceil(&mut self, ceil: u64) -> &mut Self {
+        self.ceil = Some(ceil);
+        self
+    }
+
+    /// Sets the number of bytes that can be burst at ceil speed.
+    /// Defaults to the rate divided by the timer frequency plus the MTU.
+    pub fn burst(&mut self, burst: u32) -> &mut Self {
+        self.burst = Some(burst);
+        self
+    }
+
+    /// Sets the number of bytes that can be burst at infinite speed.
+    /// Defaults to the ceil divided by the timer frequency plus the MTU.
+    pub fn cburst(&mut self, cburst: u32) -> &mut Self {
+        self.cburst = Some(cburst);
+        self
+    }
+
+    /// Sets the number of bytes served from the class before moving to the next one.
+    /// The kernel computes it from `rate2quantum` when left at zero.
+    pub fn quantum(&mut self, quantum: u32) -> &mut Self {
+        self.quantum = quantum;
+        self
+    }
+
+    /// Sets the priority of the class, lower values are served first.
+    pub fn prio(&mut self, prio: u32) -> &mut Self {
+        self.prio = prio;
+        self
+    }
+
+    /// Sets the MTU used to compute the rate tables, defaults to 1600.
+    pub fn mtu(&mut self, mtu: u32) -> &mut Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Sets the minimum packet unit, packets smaller than `mpu` are accounted as `mpu` bytes.
+    pub fn mpu(&mut self, mpu: u16) -> &mut Self {
+        self.mpu = mpu;
+        self
+    }
+
+    /// Sets the per packet overhead added by the kernel when computing the rate.
+    pub fn overhead(&mut self, overhead: u16) -> &mut Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Sets the cell log of the rate table, derived from the MTU by default.
+    pub fn cell_log(&mut self, cell_log: u8) -> &mut Self {
+        self.cell_log = Some(cell_log);
+        self
+    }
+
+    /// Sets the cell log of the ceil table, derived from the MTU by default.
+    pub fn ccell_log(&mut self, ccell_log: u8) -> &mut Self {
+        self.ccell_log = Some(ccell_log);
+        self
+    }
+
+    /// Sets the link layer used to compute the size of packets on the wire.
+    pub fn linklayer(&mut self, linklayer: LinkLayer) -> &mut Self {
+        self.linklayer = linklayer;
+        self
+    }
+
+    /// Builds the class options.
+    ///
+    /// Returns an error if the rate or ceil is zero, or if a cell log doesn't fit the 32-bit rate table sizes.
+    pub fn build(&self) -> Result<Htb, Error> {
+        let mtu = if self.mtu == 0 { 1600 } else { self.mtu };
+        let rate64 = self.rate;
+        let ceil64 = self.ceil.unwrap_or(rate64);
+        if rate64 == 0 {
+            return Err(Error::Encode("HTB class requires a rate".to_string()));
+        }
+        if ceil64 == 0 {
+            return Err(Error::Encode("HTB class ceil must not be zero".to_string()));
+        }
+        for cell_log in [self.cell_log, self.ccell_log].into_iter().flatten() {
+            if cell_log >= 32 {
+                return Err(Error::Encode(format!(
+                    "HTB class cell_log {cell_log} must be below 32"
+                )));
+            }
+        }
+        // `tc` uses the timer frequency reported in `/proc/net/psched`, which is 1GHz with hrtimers.
+        let burst = self.burst.unwrap_or((rate64 / 1_000_000_000) as u32 + mtu);
+        let cburst = self.cburst.unwrap_or((ceil64 / 1_000_000_000) as u32 + mtu);
+
+        let mut rate = RateSpec {
+            rate: rate64.min(u32::MAX as u64) as u32,
+            overhead: self.overhead,
+            mpu: self.mpu,
+            ..Default::default()
+        };
+        let mut ceil = RateSpec {
+            rate: ceil64.min(u32::MAX as u64) as u32,
+            overhead: self.overhead,
+            mpu: self.mpu,
+            ..Default::default()
+        };
+        let rtab = calc_rtable(&mut rate, rate64, self.cell_log, mtu, self.linklayer);
+        let ctab = calc_rtable(&mut ceil, ceil64, self.ccell_log, mtu, self.linklayer);
+
+        Ok(Htb {
+            parms: Some(HtbOpt {
+                rate,
+                ceil,
+                buffer: calc_xmittime(rate64, burst),
+                cbuffer: calc_xmittime(ceil64, cburst),
+                quantum: self.quantum,
+                level: 0,
+                prio: self.prio,
+            }),
+            init: None,
+            ctab,
+            rtab,
+            direct_qlen: None,
+            rate64: (rate64 > u32::MAX as u64).then_some(rate64),
+            ceil64: (ceil64 > u32::MAX as u64).then_some(ceil64),
+        })
+    }
+}
+
+impl HtbGlob {
+    /// Encodes the qdisc into `TCA_HTB_INIT`.
+    /// The version is always sent as `TC_HTB_PROTOVER`, since the kernel reports
+    /// its full `HTB_VER` in dumps but only accepts the protocol version on input.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_htb_glob(self)
+    }
+}
+
 impl HtbXstats {
     pub fn new(bytes: &[u8]) -> Result<Self, Error> {
         unmarshal_htb_xstats(bytes)
@@ -135,6 +322,57 @@ fn unmarshal_htb(opts: Vec<TcOption>) -> Htb {
     htb
 }
 
+fn marshal_htb(htb: &Htb) -> Result<Vec<TcOption>, Error> {
+    let mut opts = Vec::new();
+
+    if let Some(rate64) = htb.rate64 {
+        opts.push(TcOption {
+            kind: TcaHtb::Rate64 as u16,
+            bytes: rate64.to_ne_bytes().to_vec(),
+        });
+    }
+    if let Some(ceil64) = htb.ceil64 {
+        opts.push(TcOption {
+            kind: TcaHtb::Ceil64 as u16,
+            bytes: ceil64.to_ne_bytes().to_vec(),
+        });
+    }
+    let parms = htb
+        .parms
+        .as_ref()
+        .ok_or_else(|| Error::Encode("HTB class requires parms".to_string()))?;
+    opts.push(TcOption {
+        kind: TcaHtb::Parms as u16,
+        bytes: bincode::serialize(parms).map_err(|e| Error::Encode(e.to_string()))?,
+    });
+    if !htb.rtab.is_empty() {
+        opts.push(TcOption {
+            kind: TcaHtb::Rtab as u16,
+            bytes: htb.rtab.clone(),
+        });
+    }
+    if !htb.ctab.is_empty() {
+        opts.push(TcOption {
+            kind: TcaHtb::Ctab as u16,
+            bytes: htb.ctab.clone(),
+        });
+    }
+
+    Ok(opts)
+}
+
+fn marshal_htb_glob(glob: &HtbGlob) -> Result<Vec<TcOption>, Error> {
+    let glob = HtbGlob {
+        version: TC_HTB_PROTOVER,
+        ..*glob
+    };
+    let bytes = bincode::serialize(&glob).map_err(|e| Error::Encode(e.to_string()))?;
+    Ok(vec![TcOption {
+        kind: TcaHtb::Init as u16,
+        bytes,
+    }])
+}
+
 fn unmarshal_htb_opt(bytes: &[u8]) -> Result<HtbOpt, Error> {
     bincode::deserialize(bytes).map_err(|e| Error::Parse(e.to_string()))
 }
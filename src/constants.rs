@@ -1,6 +1,54 @@
 // QDiscs
+pub const CAKE: &str = "cake";
 pub const CLSACT: &str = "clsact";
+pub const CODEL: &str = "codel";
+pub const FQ: &str = "fq";
 pub const FQ_CODEL: &str = "fq_codel";
+pub const FQ_PIE: &str = "fq_pie";
+pub const PIE: &str = "pie";
+pub const TBF: &str = "tbf";
 
 // Classes
 pub const HTB: &str = "htb";
+
+// Filters
+pub const BPF: &str = "bpf";
+pub const FLOWER: &str = "flower";
+pub const MATCHALL: &str = "matchall";
+pub const U32: &str = "u32";
+
+// Actions
+pub const CONNMARK: &str = "connmark";
+pub const CSUM: &str = "csum";
+pub const CT: &str = "ct";
+pub const CTINFO: &str = "ctinfo";
+pub const GACT: &str = "gact";
+pub const MIRRED: &str = "mirred";
+pub const MPLS: &str = "mpls";
+pub const NAT: &str = "nat";
+pub const PEDIT: &str = "pedit";
+pub const POLICE: &str = "police";
+pub const SKBEDIT: &str = "skbedit";
+pub const SKBMOD: &str = "skbmod";
+pub const TUNNEL_KEY: &str = "tunnel_key";
+pub const VLAN: &str = "vlan";
+
+// Protocols, defined in `include/uapi/linux/if_ether.h`
+pub const ETH_P_ALL: u16 = 0x0003;
+pub const ETH_P_IP: u16 = 0x0800;
+pub const ETH_P_IPV6: u16 = 0x86DD;
+
+// Handles, defined in `include/uapi/linux/pkt_sched.h`
+pub const TC_H_UNSPEC: u32 = 0;
+pub const TC_H_ROOT: u32 = 0xFFFF_FFFF;
+pub const TC_H_INGRESS: u32 = 0xFFFF_FFF1;
+pub const TC_H_CLSACT: u32 = TC_H_INGRESS;
+pub const TC_H_MIN_INGRESS: u32 = 0xFFF2;
+pub const TC_H_MIN_EGRESS: u32 = 0xFFF3;
+pub const TC_H_MAJ_MASK: u32 = 0xFFFF_0000;
+pub const TC_H_MIN_MASK: u32 = 0x0000_FFFF;
+
+/// Builds a `tc` handle from its major and minor numbers, like `TC_H_MAKE`.
+pub const fn tc_handle(major: u16, minor: u16) -> u32 {
+    ((major as u32) << 16) | minor as u32
+}
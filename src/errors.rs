@@ -4,4 +4,13 @@ use thiserror::Error;
 pub enum Error {
     #[error("Failed to parse: {0}")]
     Parse(String),
+    #[error("Failed to encode: {0}")]
+    Encode(String),
+    #[error("Netlink socket error: {0}")]
+    Socket(#[from] std::io::Error),
+    #[error("Netlink error: {0}")]
+    Netlink(String),
+    /// The kernel rejected the request with the given `errno`.
+    #[error("Kernel returned error: {}", std::io::Error::from_raw_os_error(*.0))]
+    Kernel(i32),
 }
@@ -0,0 +1,9 @@
+pub mod cls_bpf;
+pub mod cls_flower;
+pub mod cls_matchall;
+pub mod cls_u32;
+
+pub use cls_bpf::*;
+pub use cls_flower::*;
+pub use cls_matchall::*;
+pub use cls_u32::*;
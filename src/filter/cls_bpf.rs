@@ -0,0 +1,257 @@
+use std::ffi::CString;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::action::{marshal_actions, unmarshal_actions};
+use crate::{errors::Error, types::*};
+
+/// Options of the `bpf` classifier, defined in `net/sched/cls_bpf.c`.
+///
+/// The classifier runs either an eBPF program, identified by `id`, `name` and `tag`,
+/// or a classic BPF program whose instructions are reported in `ops`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Bpf {
+    pub classid: Option<u32>,
+    /// Instructions of a classic BPF program.
+    pub ops: Vec<BpfOp>,
+    pub name: Option<String>,
+    pub id: Option<u32>,
+    /// Hash of the eBPF program instructions, as reported by `bpftool prog`.
+    pub tag: Option<[u8; 8]>,
+    /// `TCA_BPF_FLAG_*` flags.
+    pub flags: Option<u32>,
+    pub flags_gen: ClsFlags,
+    /// File descriptor of the eBPF program to attach.
+    /// Only used when creating the filter, the kernel reports `id` instead.
+    pub fd: Option<RawFd>,
+    /// Actions run on the matched packets.
+    pub actions: Vec<Action>,
+}
+
+/// Defined in `include/uapi/linux/filter.h` as `struct sock_filter`.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct BpfOp {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+/// The program returns a `TC_ACT_*` verdict instead of a class id.
+pub const TCA_BPF_FLAG_ACT_DIRECT: u32 = 1 << 0;
+
+const BPF_OP_LEN: usize = 8;
+const BPF_OBJ_GET: libc::c_long = 7;
+
+pub enum TcaBpf {
+    Unspec = 0,
+    Act,
+    Police,
+    ClassId,
+    OpsLen,
+    Ops,
+    Fd,
+    Name,
+    Flags,
+    FlagsGen,
+    Tag,
+    Id,
+    Max,
+}
+
+impl From<u16> for TcaBpf {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaBpf::Unspec,
+            1 => TcaBpf::Act,
+            2 => TcaBpf::Police,
+            3 => TcaBpf::ClassId,
+            4 => TcaBpf::OpsLen,
+            5 => TcaBpf::Ops,
+            6 => TcaBpf::Fd,
+            7 => TcaBpf::Name,
+            8 => TcaBpf::Flags,
+            9 => TcaBpf::FlagsGen,
+            10 => TcaBpf::Tag,
+            11 => TcaBpf::Id,
+            _ => TcaBpf::Max,
+        }
+    }
+}
+
+impl Bpf {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_bpf(opts)
+    }
+
+    /// Creates a classifier running the eBPF program `fd` in direct-action mode,
+    /// like `tc filter add ... bpf da fd <fd> name <name>`.
+    /// `name` is only informative and reported back by the kernel.
+    pub fn with_fd(fd: RawFd, name: &str) -> Self {
+        Self {
+            fd: Some(fd),
+            name: Some(name.to_string()),
+            flags: Some(TCA_BPF_FLAG_ACT_DIRECT),
+            ..Default::default()
+        }
+    }
+
+    /// Encodes the classifier into `TCA_BPF_*` options.
+    /// Either an eBPF program `fd` or classic BPF `ops` is required.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_bpf(self)
+    }
+
+    /// Whether the program runs in direct-action mode.
+    pub fn direct_action(&self) -> bool {
+        self.flags.unwrap_or_default() & TCA_BPF_FLAG_ACT_DIRECT != 0
+    }
+}
+
+fn unmarshal_bpf(opts: Vec<TcOption>) -> Bpf {
+    let mut bpf = Bpf::default();
+
+    for opt in opts {
+        let kind = TcaBpf::from(opt.kind);
+        match kind {
+            TcaBpf::Act => bpf.actions = unmarshal_actions(&opt.bytes),
+            TcaBpf::ClassId => bpf.classid = unmarshal_u32_value(&opt.bytes),
+            TcaBpf::Ops => bpf.ops = unmarshal_bpf_ops(&opt.bytes).unwrap_or_default(),
+            TcaBpf::Name => {
+                let name = opt.bytes.split(|b| *b == 0).next().unwrap_or_default();
+                bpf.name = String::from_utf8(name.to_vec()).ok();
+            }
+            TcaBpf::Flags => bpf.flags = unmarshal_u32_value(&opt.bytes),
+            TcaBpf::FlagsGen => {
+                bpf.flags_gen = unmarshal_u32_value(&opt.bytes)
+                    .map(ClsFlags::from)
+                    .unwrap_or_default()
+            }
+            TcaBpf::Tag => bpf.tag = opt.bytes.get(..8).map(|b| b.try_into().unwrap()),
+            TcaBpf::Id => bpf.id = unmarshal_u32_value(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    bpf
+}
+
+fn marshal_bpf(bpf: &Bpf) -> Result<Vec<TcOption>, Error> {
+    let mut opts = Vec::new();
+
+    match (bpf.fd, bpf.ops.is_empty()) {
+        (Some(fd), _) => {
+            opts.push(TcOption {
+                kind: TcaBpf::Fd as u16,
+                bytes: fd.to_ne_bytes().to_vec(),
+            });
+            let name = bpf.name.as_deref().unwrap_or_default();
+            let mut bytes = name.as_bytes().to_vec();
+            bytes.push(0);
+            opts.push(TcOption {
+                kind: TcaBpf::Name as u16,
+                bytes,
+            });
+        }
+        (None, false) => {
+            opts.push(TcOption {
+                kind: TcaBpf::OpsLen as u16,
+                bytes: (bpf.ops.len() as u16).to_ne_bytes().to_vec(),
+            });
+            opts.push(TcOption {
+                kind: TcaBpf::Ops as u16,
+                bytes: marshal_bpf_ops(&bpf.ops),
+            });
+        }
+        (None, true) => {
+            return Err(Error::Encode(
+                "BPF filter requires a program fd or ops".to_string(),
+            ))
+        }
+    }
+
+    if let Some(classid) = bpf.classid {
+        opts.push(TcOption {
+            kind: TcaBpf::ClassId as u16,
+            bytes: classid.to_ne_bytes().to_vec(),
+        });
+    }
+    if let Some(flags) = bpf.flags {
+        opts.push(TcOption {
+            kind: TcaBpf::Flags as u16,
+            bytes: flags.to_ne_bytes().to_vec(),
+        });
+    }
+    let flags_gen = bpf.flags_gen.request_flags();
+    if flags_gen != 0 {
+        opts.push(TcOption {
+            kind: TcaBpf::FlagsGen as u16,
+            bytes: flags_gen.to_ne_bytes().to_vec(),
+        });
+    }
+    if !bpf.actions.is_empty() {
+        opts.push(TcOption {
+            kind: TcaBpf::Act as u16,
+            bytes: marshal_actions(&bpf.actions)?,
+        });
+    }
+
+    Ok(opts)
+}
+
+/// Opens the eBPF program pinned at `path` on a `bpf` filesystem, like `BPF_OBJ_GET`.
+/// The returned file descriptor can be attached with `Bpf::with_fd`,
+/// it must be kept open until the request is acknowledged.
+pub fn open_pinned(path: impl AsRef<Path>) -> Result<OwnedFd, Error> {
+    let pathname = CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|e| Error::Encode(e.to_string()))?;
+
+    // union bpf_attr for BPF_OBJ_GET: pathname, bpf_fd, file_flags
+    let mut attr = [0u8; 16];
+    attr[..8].copy_from_slice(&(pathname.as_ptr() as u64).to_ne_bytes());
+    // SAFETY: `attr` outlives the call and `pathname` is a valid C string.
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            BPF_OBJ_GET,
+            attr.as_ptr(),
+            attr.len() as libc::c_uint,
+        )
+    };
+    if fd < 0 {
+        let errno = std::io::Error::last_os_error().raw_os_error();
+        return Err(Error::Kernel(errno.unwrap_or_default()));
+    }
+    // SAFETY: the kernel returned a new file descriptor owned by nobody else.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+pub(crate) fn unmarshal_bpf_ops(bytes: &[u8]) -> Result<Vec<BpfOp>, Error> {
+    if !bytes.len().is_multiple_of(BPF_OP_LEN) {
+        return Err(Error::Parse(format!(
+            "BPF ops require a multiple of {BPF_OP_LEN} bytes"
+        )));
+    }
+
+    Ok(bytes
+        .chunks_exact(BPF_OP_LEN)
+        .map(|op| BpfOp {
+            code: u16::from_ne_bytes(op[0..2].try_into().unwrap()),
+            jt: op[2],
+            jf: op[3],
+            k: u32::from_ne_bytes(op[4..8].try_into().unwrap()),
+        })
+        .collect())
+}
+
+pub(crate) fn marshal_bpf_ops(ops: &[BpfOp]) -> Vec<u8> {
+    ops.iter()
+        .flat_map(|op| {
+            let mut bytes = op.code.to_ne_bytes().to_vec();
+            bytes.extend([op.jt, op.jf]);
+            bytes.extend(op.k.to_ne_bytes());
+            bytes
+        })
+        .collect()
+}
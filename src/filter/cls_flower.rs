@@ -0,0 +1,573 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::action::unmarshal_actions;
+use crate::{errors::Error, types::*};
+
+/// Options of the `flower` classifier, defined in `net/sched/cls_flower.c`.
+///
+/// Each key is reported along with its mask when the kernel dumps one.
+/// Values in network byte order on the wire are converted to host byte order.
+/// Ports are reported in `src_port` and `dst_port` whatever the `ip_proto`,
+/// the kernel dumps them from the `TCP`, `UDP` or `SCTP` attributes accordingly.
+#[derive(Default, Debug, PartialEq)]
+pub struct Flower {
+    pub classid: Option<u32>,
+    pub indev: Option<String>,
+    pub flags: ClsFlags,
+    /// Number of hardware devices the filter is offloaded to.
+    pub in_hw_count: Option<u32>,
+    /// Actions run on the matched packets.
+    pub actions: Vec<Action>,
+
+    // L2
+    pub eth_dst: Option<[u8; 6]>,
+    pub eth_dst_mask: Option<[u8; 6]>,
+    pub eth_src: Option<[u8; 6]>,
+    pub eth_src_mask: Option<[u8; 6]>,
+    pub eth_type: Option<u16>,
+    pub vlan_id: Option<u16>,
+    pub vlan_prio: Option<u8>,
+    pub vlan_eth_type: Option<u16>,
+    pub cvlan_id: Option<u16>,
+    pub cvlan_prio: Option<u8>,
+    pub cvlan_eth_type: Option<u16>,
+
+    // MPLS
+    pub mpls_ttl: Option<u8>,
+    pub mpls_bos: Option<u8>,
+    pub mpls_tc: Option<u8>,
+    pub mpls_label: Option<u32>,
+    /// Label stack entries matched at a given depth, from `TCA_FLOWER_KEY_MPLS_OPTS`.
+    pub mpls_lses: Vec<FlowerMplsLse>,
+
+    // L3
+    pub ip_proto: Option<u8>,
+    pub ipv4_src: Option<Ipv4Addr>,
+    pub ipv4_src_mask: Option<Ipv4Addr>,
+    pub ipv4_dst: Option<Ipv4Addr>,
+    pub ipv4_dst_mask: Option<Ipv4Addr>,
+    pub ipv6_src: Option<Ipv6Addr>,
+    pub ipv6_src_mask: Option<Ipv6Addr>,
+    pub ipv6_dst: Option<Ipv6Addr>,
+    pub ipv6_dst_mask: Option<Ipv6Addr>,
+    pub ip_tos: Option<u8>,
+    pub ip_tos_mask: Option<u8>,
+    pub ip_ttl: Option<u8>,
+    pub ip_ttl_mask: Option<u8>,
+    /// Fragmentation flags, `TCA_FLOWER_KEY_FLAGS_*`.
+    pub key_flags: Option<u32>,
+    pub key_flags_mask: Option<u32>,
+
+    // L4
+    pub src_port: Option<u16>,
+    pub src_port_mask: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub dst_port_mask: Option<u16>,
+    pub src_port_range: Option<FlowerPortRange>,
+    pub dst_port_range: Option<FlowerPortRange>,
+    pub tcp_flags: Option<u16>,
+    pub tcp_flags_mask: Option<u16>,
+    pub icmp_type: Option<u8>,
+    pub icmp_type_mask: Option<u8>,
+    pub icmp_code: Option<u8>,
+    pub icmp_code_mask: Option<u8>,
+
+    // Tunnel
+    pub enc_key_id: Option<u32>,
+    pub enc_ipv4_src: Option<Ipv4Addr>,
+    pub enc_ipv4_src_mask: Option<Ipv4Addr>,
+    pub enc_ipv4_dst: Option<Ipv4Addr>,
+    pub enc_ipv4_dst_mask: Option<Ipv4Addr>,
+    pub enc_ipv6_src: Option<Ipv6Addr>,
+    pub enc_ipv6_src_mask: Option<Ipv6Addr>,
+    pub enc_ipv6_dst: Option<Ipv6Addr>,
+    pub enc_ipv6_dst_mask: Option<Ipv6Addr>,
+    pub enc_src_port: Option<u16>,
+    pub enc_src_port_mask: Option<u16>,
+    pub enc_dst_port: Option<u16>,
+    pub enc_dst_port_mask: Option<u16>,
+    pub enc_ip_tos: Option<u8>,
+    pub enc_ip_tos_mask: Option<u8>,
+    pub enc_ip_ttl: Option<u8>,
+    pub enc_ip_ttl_mask: Option<u8>,
+    /// Raw `TCA_FLOWER_KEY_ENC_OPTS` attribute (geneve, vxlan, erspan or gtp options).
+    pub enc_opts: Option<Vec<u8>>,
+    pub enc_opts_mask: Option<Vec<u8>>,
+
+    // Conntrack
+    /// Connection state, `TCA_FLOWER_KEY_CT_FLAGS_*`.
+    pub ct_state: Option<u16>,
+    pub ct_state_mask: Option<u16>,
+    pub ct_zone: Option<u16>,
+    pub ct_zone_mask: Option<u16>,
+    pub ct_mark: Option<u32>,
+    pub ct_mark_mask: Option<u32>,
+    pub ct_labels: Option<[u8; 16]>,
+    pub ct_labels_mask: Option<[u8; 16]>,
+}
+
+/// A range of L4 ports, both ends included.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct FlowerPortRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+/// An MPLS label stack entry matched by `flower`.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct FlowerMplsLse {
+    /// Depth of the entry in the label stack, starting at 1.
+    pub depth: u8,
+    pub ttl: Option<u8>,
+    pub bos: Option<u8>,
+    pub tc: Option<u8>,
+    pub label: Option<u32>,
+}
+
+pub const TCA_FLOWER_KEY_CT_FLAGS_NEW: u16 = 1 << 0;
+pub const TCA_FLOWER_KEY_CT_FLAGS_ESTABLISHED: u16 = 1 << 1;
+pub const TCA_FLOWER_KEY_CT_FLAGS_RELATED: u16 = 1 << 2;
+pub const TCA_FLOWER_KEY_CT_FLAGS_TRACKED: u16 = 1 << 3;
+pub const TCA_FLOWER_KEY_CT_FLAGS_INVALID: u16 = 1 << 4;
+pub const TCA_FLOWER_KEY_CT_FLAGS_REPLY: u16 = 1 << 5;
+
+pub const TCA_FLOWER_KEY_FLAGS_IS_FRAGMENT: u32 = 1 << 0;
+pub const TCA_FLOWER_KEY_FLAGS_FRAG_IS_FIRST: u32 = 1 << 1;
+
+const TCA_FLOWER_KEY_MPLS_OPTS_LSE: u16 = 1;
+
+pub enum TcaFlowerMplsLse {
+    Unspec = 0,
+    Depth,
+    Ttl,
+    Bos,
+    Tc,
+    Label,
+    Max,
+}
+
+impl From<u16> for TcaFlowerMplsLse {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaFlowerMplsLse::Unspec,
+            1 => TcaFlowerMplsLse::Depth,
+            2 => TcaFlowerMplsLse::Ttl,
+            3 => TcaFlowerMplsLse::Bos,
+            4 => TcaFlowerMplsLse::Tc,
+            5 => TcaFlowerMplsLse::Label,
+            _ => TcaFlowerMplsLse::Max,
+        }
+    }
+}
+
+pub enum TcaFlower {
+    Unspec = 0,
+    ClassId,
+    Indev,
+    Act,
+    KeyEthDst,
+    KeyEthDstMask,
+    KeyEthSrc,
+    KeyEthSrcMask,
+    KeyEthType,
+    KeyIpProto,
+    KeyIpv4Src,
+    KeyIpv4SrcMask,
+    KeyIpv4Dst,
+    KeyIpv4DstMask,
+    KeyIpv6Src,
+    KeyIpv6SrcMask,
+    KeyIpv6Dst,
+    KeyIpv6DstMask,
+    KeyTcpSrc,
+    KeyTcpDst,
+    KeyUdpSrc,
+    KeyUdpDst,
+    Flags,
+    KeyVlanId,
+    KeyVlanPrio,
+    KeyVlanEthType,
+    KeyEncKeyId,
+    KeyEncIpv4Src,
+    KeyEncIpv4SrcMask,
+    KeyEncIpv4Dst,
+    KeyEncIpv4DstMask,
+    KeyEncIpv6Src,
+    KeyEncIpv6SrcMask,
+    KeyEncIpv6Dst,
+    KeyEncIpv6DstMask,
+    KeyTcpSrcMask,
+    KeyTcpDstMask,
+    KeyUdpSrcMask,
+    KeyUdpDstMask,
+    KeySctpSrcMask,
+    KeySctpDstMask,
+    KeySctpSrc,
+    KeySctpDst,
+    KeyEncUdpSrcPort,
+    KeyEncUdpSrcPortMask,
+    KeyEncUdpDstPort,
+    KeyEncUdpDstPortMask,
+    KeyFlags,
+    KeyFlagsMask,
+    KeyIcmpv4Code,
+    KeyIcmpv4CodeMask,
+    KeyIcmpv4Type,
+    KeyIcmpv4TypeMask,
+    KeyIcmpv6Code,
+    KeyIcmpv6CodeMask,
+    KeyIcmpv6Type,
+    KeyIcmpv6TypeMask,
+    KeyArpSip,
+    KeyArpSipMask,
+    KeyArpTip,
+    KeyArpTipMask,
+    KeyArpOp,
+    KeyArpOpMask,
+    KeyArpSha,
+    KeyArpShaMask,
+    KeyArpTha,
+    KeyArpThaMask,
+    KeyMplsTtl,
+    KeyMplsBos,
+    KeyMplsTc,
+    KeyMplsLabel,
+    KeyTcpFlags,
+    KeyTcpFlagsMask,
+    KeyIpTos,
+    KeyIpTosMask,
+    KeyIpTtl,
+    KeyIpTtlMask,
+    KeyCvlanId,
+    KeyCvlanPrio,
+    KeyCvlanEthType,
+    KeyEncIpTos,
+    KeyEncIpTosMask,
+    KeyEncIpTtl,
+    KeyEncIpTtlMask,
+    KeyEncOpts,
+    KeyEncOptsMask,
+    InHwCount,
+    KeyPortSrcMin,
+    KeyPortSrcMax,
+    KeyPortDstMin,
+    KeyPortDstMax,
+    KeyCtState,
+    KeyCtStateMask,
+    KeyCtZone,
+    KeyCtZoneMask,
+    KeyCtMark,
+    KeyCtMarkMask,
+    KeyCtLabels,
+    KeyCtLabelsMask,
+    KeyMplsOpts,
+    KeyHash,
+    KeyHashMask,
+    KeyNumOfVlans,
+    KeyPppoeSid,
+    KeyPppProto,
+    KeyL2tpv3Sid,
+    Max,
+}
+
+impl From<u16> for TcaFlower {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaFlower::Unspec,
+            1 => TcaFlower::ClassId,
+            2 => TcaFlower::Indev,
+            3 => TcaFlower::Act,
+            4 => TcaFlower::KeyEthDst,
+            5 => TcaFlower::KeyEthDstMask,
+            6 => TcaFlower::KeyEthSrc,
+            7 => TcaFlower::KeyEthSrcMask,
+            8 => TcaFlower::KeyEthType,
+            9 => TcaFlower::KeyIpProto,
+            10 => TcaFlower::KeyIpv4Src,
+            11 => TcaFlower::KeyIpv4SrcMask,
+            12 => TcaFlower::KeyIpv4Dst,
+            13 => TcaFlower::KeyIpv4DstMask,
+            14 => TcaFlower::KeyIpv6Src,
+            15 => TcaFlower::KeyIpv6SrcMask,
+            16 => TcaFlower::KeyIpv6Dst,
+            17 => TcaFlower::KeyIpv6DstMask,
+            18 => TcaFlower::KeyTcpSrc,
+            19 => TcaFlower::KeyTcpDst,
+            20 => TcaFlower::KeyUdpSrc,
+            21 => TcaFlower::KeyUdpDst,
+            22 => TcaFlower::Flags,
+            23 => TcaFlower::KeyVlanId,
+            24 => TcaFlower::KeyVlanPrio,
+            25 => TcaFlower::KeyVlanEthType,
+            26 => TcaFlower::KeyEncKeyId,
+            27 => TcaFlower::KeyEncIpv4Src,
+            28 => TcaFlower::KeyEncIpv4SrcMask,
+            29 => TcaFlower::KeyEncIpv4Dst,
+            30 => TcaFlower::KeyEncIpv4DstMask,
+            31 => TcaFlower::KeyEncIpv6Src,
+            32 => TcaFlower::KeyEncIpv6SrcMask,
+            33 => TcaFlower::KeyEncIpv6Dst,
+            34 => TcaFlower::KeyEncIpv6DstMask,
+            35 => TcaFlower::KeyTcpSrcMask,
+            36 => TcaFlower::KeyTcpDstMask,
+            37 => TcaFlower::KeyUdpSrcMask,
+            38 => TcaFlower::KeyUdpDstMask,
+            39 => TcaFlower::KeySctpSrcMask,
+            40 => TcaFlower::KeySctpDstMask,
+            41 => TcaFlower::KeySctpSrc,
+            42 => TcaFlower::KeySctpDst,
+            43 => TcaFlower::KeyEncUdpSrcPort,
+            44 => TcaFlower::KeyEncUdpSrcPortMask,
+            45 => TcaFlower::KeyEncUdpDstPort,
+            46 => TcaFlower::KeyEncUdpDstPortMask,
+            47 => TcaFlower::KeyFlags,
+            48 => TcaFlower::KeyFlagsMask,
+            49 => TcaFlower::KeyIcmpv4Code,
+            50 => TcaFlower::KeyIcmpv4CodeMask,
+            51 => TcaFlower::KeyIcmpv4Type,
+            52 => TcaFlower::KeyIcmpv4TypeMask,
+            53 => TcaFlower::KeyIcmpv6Code,
+            54 => TcaFlower::KeyIcmpv6CodeMask,
+            55 => TcaFlower::KeyIcmpv6Type,
+            56 => TcaFlower::KeyIcmpv6TypeMask,
+            57 => TcaFlower::KeyArpSip,
+            58 => TcaFlower::KeyArpSipMask,
+            59 => TcaFlower::KeyArpTip,
+            60 => TcaFlower::KeyArpTipMask,
+            61 => TcaFlower::KeyArpOp,
+            62 => TcaFlower::KeyArpOpMask,
+            63 => TcaFlower::KeyArpSha,
+            64 => TcaFlower::KeyArpShaMask,
+            65 => TcaFlower::KeyArpTha,
+            66 => TcaFlower::KeyArpThaMask,
+            67 => TcaFlower::KeyMplsTtl,
+            68 => TcaFlower::KeyMplsBos,
+            69 => TcaFlower::KeyMplsTc,
+            70 => TcaFlower::KeyMplsLabel,
+            71 => TcaFlower::KeyTcpFlags,
+            72 => TcaFlower::KeyTcpFlagsMask,
+            73 => TcaFlower::KeyIpTos,
+            74 => TcaFlower::KeyIpTosMask,
+            75 => TcaFlower::KeyIpTtl,
+            76 => TcaFlower::KeyIpTtlMask,
+            77 => TcaFlower::KeyCvlanId,
+            78 => TcaFlower::KeyCvlanPrio,
+            79 => TcaFlower::KeyCvlanEthType,
+            80 => TcaFlower::KeyEncIpTos,
+            81 => TcaFlower::KeyEncIpTosMask,
+            82 => TcaFlower::KeyEncIpTtl,
+            83 => TcaFlower::KeyEncIpTtlMask,
+            84 => TcaFlower::KeyEncOpts,
+            85 => TcaFlower::KeyEncOptsMask,
+            86 => TcaFlower::InHwCount,
+            87 => TcaFlower::KeyPortSrcMin,
+            88 => TcaFlower::KeyPortSrcMax,
+            89 => TcaFlower::KeyPortDstMin,
+            90 => TcaFlower::KeyPortDstMax,
+            91 => TcaFlower::KeyCtState,
+            92 => TcaFlower::KeyCtStateMask,
+            93 => TcaFlower::KeyCtZone,
+            94 => TcaFlower::KeyCtZoneMask,
+            95 => TcaFlower::KeyCtMark,
+            96 => TcaFlower::KeyCtMarkMask,
+            97 => TcaFlower::KeyCtLabels,
+            98 => TcaFlower::KeyCtLabelsMask,
+            99 => TcaFlower::KeyMplsOpts,
+            100 => TcaFlower::KeyHash,
+            101 => TcaFlower::KeyHashMask,
+            102 => TcaFlower::KeyNumOfVlans,
+            103 => TcaFlower::KeyPppoeSid,
+            104 => TcaFlower::KeyPppProto,
+            105 => TcaFlower::KeyL2tpv3Sid,
+            _ => TcaFlower::Max,
+        }
+    }
+}
+
+impl Flower {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_flower(opts)
+    }
+}
+
+fn unmarshal_flower(opts: Vec<TcOption>) -> Flower {
+    let mut fl = Flower::default();
+    let mut port_src_min = None;
+    let mut port_src_max = None;
+    let mut port_dst_min = None;
+    let mut port_dst_max = None;
+
+    for opt in opts {
+        let kind = TcaFlower::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        match kind {
+            TcaFlower::ClassId => fl.classid = ne_u32(b),
+            TcaFlower::Indev => fl.indev = string(b),
+            TcaFlower::Flags => fl.flags = ne_u32(b).map(ClsFlags::from).unwrap_or_default(),
+            TcaFlower::InHwCount => fl.in_hw_count = ne_u32(b),
+            TcaFlower::Act => fl.actions = unmarshal_actions(b),
+
+            TcaFlower::KeyEthDst => fl.eth_dst = array(b),
+            TcaFlower::KeyEthDstMask => fl.eth_dst_mask = array(b),
+            TcaFlower::KeyEthSrc => fl.eth_src = array(b),
+            TcaFlower::KeyEthSrcMask => fl.eth_src_mask = array(b),
+            TcaFlower::KeyEthType => fl.eth_type = be_u16(b),
+            TcaFlower::KeyVlanId => fl.vlan_id = ne_u16(b),
+            TcaFlower::KeyVlanPrio => fl.vlan_prio = byte(b),
+            TcaFlower::KeyVlanEthType => fl.vlan_eth_type = be_u16(b),
+            TcaFlower::KeyCvlanId => fl.cvlan_id = ne_u16(b),
+            TcaFlower::KeyCvlanPrio => fl.cvlan_prio = byte(b),
+            TcaFlower::KeyCvlanEthType => fl.cvlan_eth_type = be_u16(b),
+
+            TcaFlower::KeyMplsTtl => fl.mpls_ttl = byte(b),
+            TcaFlower::KeyMplsBos => fl.mpls_bos = byte(b),
+            TcaFlower::KeyMplsTc => fl.mpls_tc = byte(b),
+            TcaFlower::KeyMplsLabel => fl.mpls_label = ne_u32(b),
+            TcaFlower::KeyMplsOpts => fl.mpls_lses = unmarshal_mpls_opts(b).unwrap_or_default(),
+
+            TcaFlower::KeyIpProto => fl.ip_proto = byte(b),
+            TcaFlower::KeyIpv4Src => fl.ipv4_src = ipv4(b),
+            TcaFlower::KeyIpv4SrcMask => fl.ipv4_src_mask = ipv4(b),
+            TcaFlower::KeyIpv4Dst => fl.ipv4_dst = ipv4(b),
+            TcaFlower::KeyIpv4DstMask => fl.ipv4_dst_mask = ipv4(b),
+            TcaFlower::KeyIpv6Src => fl.ipv6_src = ipv6(b),
+            TcaFlower::KeyIpv6SrcMask => fl.ipv6_src_mask = ipv6(b),
+            TcaFlower::KeyIpv6Dst => fl.ipv6_dst = ipv6(b),
+            TcaFlower::KeyIpv6DstMask => fl.ipv6_dst_mask = ipv6(b),
+            TcaFlower::KeyIpTos => fl.ip_tos = byte(b),
+            TcaFlower::KeyIpTosMask => fl.ip_tos_mask = byte(b),
+            TcaFlower::KeyIpTtl => fl.ip_ttl = byte(b),
+            TcaFlower::KeyIpTtlMask => fl.ip_ttl_mask = byte(b),
+            TcaFlower::KeyFlags => fl.key_flags = be_u32(b),
+            TcaFlower::KeyFlagsMask => fl.key_flags_mask = be_u32(b),
+
+            TcaFlower::KeyTcpSrc | TcaFlower::KeyUdpSrc | TcaFlower::KeySctpSrc => {
+                fl.src_port = be_u16(b)
+            }
+            TcaFlower::KeyTcpSrcMask | TcaFlower::KeyUdpSrcMask | TcaFlower::KeySctpSrcMask => {
+                fl.src_port_mask = be_u16(b)
+            }
+            TcaFlower::KeyTcpDst | TcaFlower::KeyUdpDst | TcaFlower::KeySctpDst => {
+                fl.dst_port = be_u16(b)
+            }
+            TcaFlower::KeyTcpDstMask | TcaFlower::KeyUdpDstMask | TcaFlower::KeySctpDstMask => {
+                fl.dst_port_mask = be_u16(b)
+            }
+            TcaFlower::KeyPortSrcMin => port_src_min = be_u16(b),
+            TcaFlower::KeyPortSrcMax => port_src_max = be_u16(b),
+            TcaFlower::KeyPortDstMin => port_dst_min = be_u16(b),
+            TcaFlower::KeyPortDstMax => port_dst_max = be_u16(b),
+            TcaFlower::KeyTcpFlags => fl.tcp_flags = be_u16(b),
+            TcaFlower::KeyTcpFlagsMask => fl.tcp_flags_mask = be_u16(b),
+            TcaFlower::KeyIcmpv4Type | TcaFlower::KeyIcmpv6Type => fl.icmp_type = byte(b),
+            TcaFlower::KeyIcmpv4TypeMask | TcaFlower::KeyIcmpv6TypeMask => {
+                fl.icmp_type_mask = byte(b)
+            }
+            TcaFlower::KeyIcmpv4Code | TcaFlower::KeyIcmpv6Code => fl.icmp_code = byte(b),
+            TcaFlower::KeyIcmpv4CodeMask | TcaFlower::KeyIcmpv6CodeMask => {
+                fl.icmp_code_mask = byte(b)
+            }
+
+            TcaFlower::KeyEncKeyId => fl.enc_key_id = be_u32(b),
+            TcaFlower::KeyEncIpv4Src => fl.enc_ipv4_src = ipv4(b),
+            TcaFlower::KeyEncIpv4SrcMask => fl.enc_ipv4_src_mask = ipv4(b),
+            TcaFlower::KeyEncIpv4Dst => fl.enc_ipv4_dst = ipv4(b),
+            TcaFlower::KeyEncIpv4DstMask => fl.enc_ipv4_dst_mask = ipv4(b),
+            TcaFlower::KeyEncIpv6Src => fl.enc_ipv6_src = ipv6(b),
+            TcaFlower::KeyEncIpv6SrcMask => fl.enc_ipv6_src_mask = ipv6(b),
+            TcaFlower::KeyEncIpv6Dst => fl.enc_ipv6_dst = ipv6(b),
+            TcaFlower::KeyEncIpv6DstMask => fl.enc_ipv6_dst_mask = ipv6(b),
+            TcaFlower::KeyEncUdpSrcPort => fl.enc_src_port = be_u16(b),
+            TcaFlower::KeyEncUdpSrcPortMask => fl.enc_src_port_mask = be_u16(b),
+            TcaFlower::KeyEncUdpDstPort => fl.enc_dst_port = be_u16(b),
+            TcaFlower::KeyEncUdpDstPortMask => fl.enc_dst_port_mask = be_u16(b),
+            TcaFlower::KeyEncIpTos => fl.enc_ip_tos = byte(b),
+            TcaFlower::KeyEncIpTosMask => fl.enc_ip_tos_mask = byte(b),
+            TcaFlower::KeyEncIpTtl => fl.enc_ip_ttl = byte(b),
+            TcaFlower::KeyEncIpTtlMask => fl.enc_ip_ttl_mask = byte(b),
+            TcaFlower::KeyEncOpts => fl.enc_opts = Some(opt.bytes),
+            TcaFlower::KeyEncOptsMask => fl.enc_opts_mask = Some(opt.bytes),
+
+            TcaFlower::KeyCtState => fl.ct_state = ne_u16(b),
+            TcaFlower::KeyCtStateMask => fl.ct_state_mask = ne_u16(b),
+            TcaFlower::KeyCtZone => fl.ct_zone = ne_u16(b),
+            TcaFlower::KeyCtZoneMask => fl.ct_zone_mask = ne_u16(b),
+            TcaFlower::KeyCtMark => fl.ct_mark = ne_u32(b),
+            TcaFlower::KeyCtMarkMask => fl.ct_mark_mask = ne_u32(b),
+            TcaFlower::KeyCtLabels => fl.ct_labels = array(b),
+            TcaFlower::KeyCtLabelsMask => fl.ct_labels_mask = array(b),
+            _ => (),
+        }
+    }
+
+    fl.src_port_range = port_range(port_src_min, port_src_max);
+    fl.dst_port_range = port_range(port_dst_min, port_dst_max);
+    fl
+}
+
+fn unmarshal_mpls_opts(bytes: &[u8]) -> Result<Vec<FlowerMplsLse>, Error> {
+    let mut lses = Vec::new();
+    for opt in unmarshal_nested(bytes)? {
+        if opt.kind != TCA_FLOWER_KEY_MPLS_OPTS_LSE {
+            continue;
+        }
+        let mut lse = FlowerMplsLse::default();
+        for attr in unmarshal_nested(&opt.bytes)? {
+            let b = attr.bytes.as_slice();
+            match TcaFlowerMplsLse::from(attr.kind) {
+                TcaFlowerMplsLse::Depth => lse.depth = byte(b).unwrap_or_default(),
+                TcaFlowerMplsLse::Ttl => lse.ttl = byte(b),
+                TcaFlowerMplsLse::Bos => lse.bos = byte(b),
+                TcaFlowerMplsLse::Tc => lse.tc = byte(b),
+                TcaFlowerMplsLse::Label => lse.label = ne_u32(b),
+                _ => (),
+            }
+        }
+        lses.push(lse);
+    }
+    Ok(lses)
+}
+
+fn port_range(min: Option<u16>, max: Option<u16>) -> Option<FlowerPortRange> {
+    Some(FlowerPortRange {
+        min: min?,
+        max: max?,
+    })
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> Option<[u8; N]> {
+    bytes.get(..N).map(|b| b.try_into().unwrap())
+}
+
+fn byte(bytes: &[u8]) -> Option<u8> {
+    bytes.first().copied()
+}
+
+fn ne_u16(bytes: &[u8]) -> Option<u16> {
+    array(bytes).map(u16::from_ne_bytes)
+}
+
+fn be_u16(bytes: &[u8]) -> Option<u16> {
+    array(bytes).map(u16::from_be_bytes)
+}
+
+fn ne_u32(bytes: &[u8]) -> Option<u32> {
+    array(bytes).map(u32::from_ne_bytes)
+}
+
+fn be_u32(bytes: &[u8]) -> Option<u32> {
+    array(bytes).map(u32::from_be_bytes)
+}
+
+fn ipv4(bytes: &[u8]) -> Option<Ipv4Addr> {
+    array::<4>(bytes).map(Ipv4Addr::from)
+}
+
+fn ipv6(bytes: &[u8]) -> Option<Ipv6Addr> {
+    array::<16>(bytes).map(Ipv6Addr::from)
+}
+
+fn string(bytes: &[u8]) -> Option<String> {
+    let bytes = bytes.split(|b| *b == 0).next()?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
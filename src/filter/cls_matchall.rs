@@ -0,0 +1,96 @@
+use crate::action::{marshal_actions, unmarshal_actions};
+use crate::{errors::Error, types::*};
+
+/// Options of the `matchall` classifier, defined in `net/sched/cls_matchall.c`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Matchall {
+    pub classid: Option<u32>,
+    pub flags: ClsFlags,
+    /// Number of packets that hit the filter.
+    pub pcnt: Option<u64>,
+    /// Actions run on the matched packets.
+    pub actions: Vec<Action>,
+}
+
+pub enum TcaMatchall {
+    Unspec = 0,
+    ClassId,
+    Act,
+    Flags,
+    Pcnt,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaMatchall {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaMatchall::Unspec,
+            1 => TcaMatchall::ClassId,
+            2 => TcaMatchall::Act,
+            3 => TcaMatchall::Flags,
+            4 => TcaMatchall::Pcnt,
+            5 => TcaMatchall::Pad,
+            _ => TcaMatchall::Max,
+        }
+    }
+}
+
+impl Matchall {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_matchall(opts)
+    }
+
+    /// Encodes the classifier into `TCA_MATCHALL_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_matchall(self)
+    }
+}
+
+fn unmarshal_matchall(opts: Vec<TcOption>) -> Matchall {
+    let mut matchall = Matchall::default();
+
+    for opt in opts {
+        let kind = TcaMatchall::from(opt.kind);
+        match kind {
+            TcaMatchall::ClassId => matchall.classid = unmarshal_u32_value(&opt.bytes),
+            TcaMatchall::Act => matchall.actions = unmarshal_actions(&opt.bytes),
+            TcaMatchall::Flags => {
+                matchall.flags = unmarshal_u32_value(&opt.bytes)
+                    .map(ClsFlags::from)
+                    .unwrap_or_default()
+            }
+            // struct tc_matchall_pcnt
+            TcaMatchall::Pcnt => matchall.pcnt = unmarshal_u64_value(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    matchall
+}
+
+fn marshal_matchall(matchall: &Matchall) -> Result<Vec<TcOption>, Error> {
+    let mut opts = Vec::new();
+
+    if let Some(classid) = matchall.classid {
+        opts.push(TcOption {
+            kind: TcaMatchall::ClassId as u16,
+            bytes: classid.to_ne_bytes().to_vec(),
+        });
+    }
+    if !matchall.actions.is_empty() {
+        opts.push(TcOption {
+            kind: TcaMatchall::Act as u16,
+            bytes: marshal_actions(&matchall.actions)?,
+        });
+    }
+    let flags = matchall.flags.request_flags();
+    if flags != 0 {
+        opts.push(TcOption {
+            kind: TcaMatchall::Flags as u16,
+            bytes: flags.to_ne_bytes().to_vec(),
+        });
+    }
+
+    Ok(opts)
+}
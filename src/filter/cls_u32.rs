@@ -0,0 +1,197 @@
+use crate::action::unmarshal_actions;
+use crate::{errors::Error, types::*};
+
+/// Options of the `u32` classifier, defined in `net/sched/cls_u32.c`.
+///
+/// A `u32` filter is either a hash table, identified by `divisor`,
+/// or a key node matching `sel` and classifying to `classid` or jumping to the table `link`.
+#[derive(Default, Debug, PartialEq)]
+pub struct U32 {
+    pub classid: Option<u32>,
+    pub hash: Option<u32>,
+    pub link: Option<u32>,
+    pub divisor: Option<u32>,
+    pub sel: Option<U32Selector>,
+    pub flags: Option<u32>,
+    pub pcnt: Option<U32Pcnt>,
+    /// Actions run on the matched packets.
+    pub actions: Vec<Action>,
+}
+
+/// Defined in `include/uapi/linux/pkt_cls.h` as `struct tc_u32_sel`.
+///
+/// Fields in network byte order in the kernel struct (`offmask`, `hmask`)
+/// are converted to host byte order.
+#[derive(Default, Debug, PartialEq)]
+pub struct U32Selector {
+    pub flags: u8,
+    pub offshift: u8,
+    pub nkeys: u8,
+    pub offmask: u16,
+    pub off: u16,
+    pub offoff: i16,
+    pub hoff: i16,
+    pub hmask: u32,
+    pub keys: Vec<U32Key>,
+}
+
+/// Defined in `include/uapi/linux/pkt_cls.h` as `struct tc_u32_key`.
+///
+/// `val` and `mask` are converted to host byte order,
+/// so that `10.0.0.1` matches as `0x0a000001` like `tc filter show` prints it.
+#[derive(Default, Debug, PartialEq)]
+pub struct U32Key {
+    pub mask: u32,
+    pub val: u32,
+    pub off: i32,
+    pub offmask: i32,
+}
+
+/// Defined in `include/uapi/linux/pkt_cls.h` as `struct tc_u32_pcnt`.
+/// Only reported by kernels built with `CONFIG_CLS_U32_PERF`.
+#[derive(Default, Debug, PartialEq)]
+pub struct U32Pcnt {
+    /// Number of packets the selector was evaluated on.
+    pub rcnt: u64,
+    /// Number of packets matching all the keys.
+    pub rhit: u64,
+    /// Number of hits of each key, in the order of `U32Selector::keys`.
+    pub kcnts: Vec<u64>,
+}
+
+const U32_SEL_LEN: usize = 16;
+const U32_KEY_LEN: usize = 16;
+const U32_PCNT_LEN: usize = 16;
+
+pub enum TcaU32 {
+    Unspec = 0,
+    ClassId,
+    Hash,
+    Link,
+    Divisor,
+    Sel,
+    Police,
+    Act,
+    Indev,
+    Pcnt,
+    Mark,
+    Flags,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaU32 {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaU32::Unspec,
+            1 => TcaU32::ClassId,
+            2 => TcaU32::Hash,
+            3 => TcaU32::Link,
+            4 => TcaU32::Divisor,
+            5 => TcaU32::Sel,
+            6 => TcaU32::Police,
+            7 => TcaU32::Act,
+            8 => TcaU32::Indev,
+            9 => TcaU32::Pcnt,
+            10 => TcaU32::Mark,
+            11 => TcaU32::Flags,
+            12 => TcaU32::Pad,
+            _ => TcaU32::Max,
+        }
+    }
+}
+
+impl U32 {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_u32(opts)
+    }
+}
+
+impl U32Selector {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_u32_sel(bytes)
+    }
+}
+
+impl U32Pcnt {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_u32_pcnt(bytes)
+    }
+}
+
+fn unmarshal_u32(opts: Vec<TcOption>) -> U32 {
+    let mut u32 = U32::default();
+
+    for opt in opts {
+        let kind = TcaU32::from(opt.kind);
+        match kind {
+            TcaU32::ClassId => u32.classid = unmarshal_u32_value(&opt.bytes),
+            TcaU32::Hash => u32.hash = unmarshal_u32_value(&opt.bytes),
+            TcaU32::Link => u32.link = unmarshal_u32_value(&opt.bytes),
+            TcaU32::Divisor => u32.divisor = unmarshal_u32_value(&opt.bytes),
+            TcaU32::Sel => u32.sel = U32Selector::new(&opt.bytes).ok(),
+            TcaU32::Flags => u32.flags = unmarshal_u32_value(&opt.bytes),
+            TcaU32::Pcnt => u32.pcnt = U32Pcnt::new(&opt.bytes).ok(),
+            TcaU32::Act => u32.actions = unmarshal_actions(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    u32
+}
+
+fn unmarshal_u32_sel(bytes: &[u8]) -> Result<U32Selector, Error> {
+    if bytes.len() < U32_SEL_LEN {
+        return Err(Error::Parse(format!(
+            "U32 selector requires {U32_SEL_LEN} bytes"
+        )));
+    }
+
+    let nkeys = bytes[2];
+    let keys_len = nkeys as usize * U32_KEY_LEN;
+    if bytes.len() < U32_SEL_LEN + keys_len {
+        return Err(Error::Parse(format!(
+            "U32 selector with {nkeys} keys requires {} bytes",
+            U32_SEL_LEN + keys_len
+        )));
+    }
+
+    let keys = bytes[U32_SEL_LEN..U32_SEL_LEN + keys_len]
+        .chunks_exact(U32_KEY_LEN)
+        .map(|key| U32Key {
+            mask: u32::from_be_bytes(key[0..4].try_into().unwrap()),
+            val: u32::from_be_bytes(key[4..8].try_into().unwrap()),
+            off: i32::from_ne_bytes(key[8..12].try_into().unwrap()),
+            offmask: i32::from_ne_bytes(key[12..16].try_into().unwrap()),
+        })
+        .collect();
+
+    Ok(U32Selector {
+        flags: bytes[0],
+        offshift: bytes[1],
+        nkeys,
+        offmask: u16::from_be_bytes(bytes[4..6].try_into().unwrap()),
+        off: u16::from_ne_bytes(bytes[6..8].try_into().unwrap()),
+        offoff: i16::from_ne_bytes(bytes[8..10].try_into().unwrap()),
+        hoff: i16::from_ne_bytes(bytes[10..12].try_into().unwrap()),
+        hmask: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        keys,
+    })
+}
+
+fn unmarshal_u32_pcnt(bytes: &[u8]) -> Result<U32Pcnt, Error> {
+    if bytes.len() < U32_PCNT_LEN {
+        return Err(Error::Parse(format!(
+            "U32 counters require {U32_PCNT_LEN} bytes"
+        )));
+    }
+
+    Ok(U32Pcnt {
+        rcnt: u64::from_ne_bytes(bytes[0..8].try_into().unwrap()),
+        rhit: u64::from_ne_bytes(bytes[8..16].try_into().unwrap()),
+        kcnts: bytes[U32_PCNT_LEN..]
+            .chunks_exact(8)
+            .map(|kcnt| u64::from_ne_bytes(kcnt.try_into().unwrap()))
+            .collect(),
+    })
+}
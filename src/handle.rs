@@ -0,0 +1,332 @@
+use std::fmt::Debug;
+
+use netlink_packet_core::{
+    NetlinkDeserializable, NetlinkHeader, NetlinkMessage, NetlinkPayload, NetlinkSerializable,
+    NLM_F_DUMP, NLM_F_DUMP_INTR, NLM_F_REQUEST,
+};
+use netlink_packet_route::{RtnlMessage, TcMessage, TC_HEADER_LEN};
+use netlink_sys::{protocols::NETLINK_ROUTE, Socket, SocketAddr};
+
+use crate::action::ActionMessage;
+use crate::errors::Error;
+use crate::qdiscs::ClsactHook;
+use crate::types::{Action, Classifier, Filter, Tc};
+use crate::ParseOptions;
+
+const NETLINK_HEADER_LEN: usize = 16;
+const NLA_HEADER_LEN: usize = 4;
+const TCA_OPTIONS: u16 = 2;
+const RTM_NEWQDISC: u16 = 36;
+const RTM_GETTFILTER: u16 = 46;
+
+/// Number of times a dump is retried when the kernel reports it was interrupted.
+pub(crate) const DUMP_RETRIES: usize = 5;
+
+/// `TcHandle` owns a `NETLINK_ROUTE` socket and uses it to dump and change `tc` objects.
+///
+/// # Example
+/// ```no_run
+/// use netlink_tc::{ParseOptions, TcHandle};
+///
+/// let mut opts = ParseOptions::new();
+/// opts.fail_on_unknown_attribute(false)
+///     .fail_on_unknown_option(false);
+/// let mut handle = TcHandle::with_options(opts).unwrap();
+/// let qdiscs = handle.qdiscs().unwrap();
+/// let classes = handle.classes(1).unwrap();
+/// ```
+pub struct TcHandle {
+    socket: Socket,
+    sequence_number: u32,
+    opts: ParseOptions,
+}
+
+impl TcHandle {
+    /// Opens a new handle, parsing replies with the default `ParseOptions`.
+    pub fn new() -> Result<Self, Error> {
+        Self::with_options(ParseOptions::default())
+    }
+
+    /// Opens a new handle, parsing replies with `opts`.
+    pub fn with_options(opts: ParseOptions) -> Result<Self, Error> {
+        let socket = Socket::new(NETLINK_ROUTE)?;
+        socket.connect(&SocketAddr::new(0, 0))?;
+        Ok(Self {
+            socket,
+            sequence_number: 0,
+            opts,
+        })
+    }
+
+    /// Returns the queueing disciplines of all interfaces.
+    pub fn qdiscs(&mut self) -> Result<Vec<Tc>, Error> {
+        let messages = self.dump(RtnlMessage::GetQueueDiscipline(TcMessage::default()))?;
+        self.opts.tc(messages)
+    }
+
+    /// Returns the queueing disciplines of the interface with index `index`.
+    pub fn qdiscs_for(&mut self, index: i32) -> Result<Vec<Tc>, Error> {
+        let qdiscs = self.qdiscs()?;
+        Ok(qdiscs
+            .into_iter()
+            .filter(|tc| tc.msg.index == index as u32)
+            .collect())
+    }
+
+    /// Returns the traffic classes of the interface with index `index`.
+    pub fn classes(&mut self, index: i32) -> Result<Vec<Tc>, Error> {
+        let messages = self.dump(RtnlMessage::GetTrafficClass(TcMessage::with_index(index)))?;
+        self.opts.tc(messages)
+    }
+
+    /// Returns the filters attached to `parent` on the interface with index `index`,
+    /// e.g. `ClsactHook::Ingress.parent()` for the ingress hook of `clsact`.
+    pub fn filters(&mut self, index: i32, parent: u32) -> Result<Vec<Tc>, Error> {
+        let messages = self.dump(RtnlMessage::GetTrafficFilter(filter_message(index, parent)))?;
+        self.opts.tc(messages)
+    }
+
+    /// Returns the BPF programs attached to `hook` of the `clsact` qdisc
+    /// on the interface with index `index`, ordered by chain and priority.
+    pub fn bpf_programs(&mut self, index: i32, hook: ClsactHook) -> Result<Vec<Tc>, Error> {
+        self.filters(index, hook.parent()).map(bpf_programs)
+    }
+
+    /// Returns the actions of `kind`, e.g. `mirred`, from the kernel action tables.
+    /// Actions bound to a filter are only listed there if they were created with an index.
+    pub fn actions(&mut self, kind: &str) -> Result<Vec<Action>, Error> {
+        let messages = self.dump_messages(ActionMessage::dump(kind))?;
+        self.opts.actions(messages)
+    }
+
+    /// Sends a dump request for `message` and returns the replies.
+    /// The dump is retried if the kernel reports it was interrupted by a concurrent change.
+    pub fn dump(
+        &mut self,
+        message: RtnlMessage,
+    ) -> Result<Vec<NetlinkMessage<RtnlMessage>>, Error> {
+        self.dump_messages(message)
+    }
+
+    fn dump_messages<T: Reply>(&mut self, message: T) -> Result<Vec<NetlinkMessage<T>>, Error> {
+        for _ in 0..DUMP_RETRIES {
+            let sequence_number = self.send(dump_request(message.clone()))?;
+            let replies = self.receive(sequence_number)?;
+            if !replies.interrupted {
+                return Ok(replies.messages);
+            }
+        }
+        Err(Error::Netlink(format!(
+            "Dump interrupted {DUMP_RETRIES} times"
+        )))
+    }
+
+    /// Sends `message` and waits for the kernel to acknowledge it.
+    /// The message must have `NLM_F_ACK` set, see `QDiscRequest::message`.
+    pub fn request(&mut self, message: NetlinkMessage<RtnlMessage>) -> Result<(), Error> {
+        let sequence_number = self.send(message)?;
+        self.receive::<RtnlMessage>(sequence_number).map(|_| ())
+    }
+
+    fn send<T: Reply>(&mut self, message: NetlinkMessage<T>) -> Result<u32, Error> {
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        self.socket
+            .send(&serialize(message, self.sequence_number), 0)?;
+        Ok(self.sequence_number)
+    }
+
+    /// Receives replies to the request with `sequence_number` until the end of a dump or an ACK.
+    fn receive<T: Reply>(&mut self, sequence_number: u32) -> Result<Replies<T>, Error> {
+        let mut replies = Replies::default();
+        loop {
+            let (buf, _) = self.socket.recv_from_full()?;
+            for bytes in split(&buf)? {
+                if message_sequence_number(bytes) == sequence_number
+                    && replies.push(T::decode(bytes, &self.opts)?)?
+                {
+                    return Ok(replies);
+                }
+            }
+        }
+    }
+}
+
+/// Netlink messages exchanged by the handles: `RtnlMessage` for qdiscs, classes and filters,
+/// `ActionMessage` for actions.
+pub(crate) trait Reply: NetlinkSerializable + NetlinkDeserializable + Clone + Debug {
+    /// Deserializes a netlink message.
+    fn decode(bytes: &[u8], opts: &ParseOptions) -> Result<NetlinkMessage<Self>, Error>;
+}
+
+impl Reply for RtnlMessage {
+    fn decode(bytes: &[u8], opts: &ParseOptions) -> Result<NetlinkMessage<Self>, Error> {
+        deserialize_message(bytes, opts)
+    }
+}
+
+impl Reply for ActionMessage {
+    fn decode(bytes: &[u8], _opts: &ParseOptions) -> Result<NetlinkMessage<Self>, Error> {
+        <NetlinkMessage<ActionMessage>>::deserialize(bytes)
+            .map_err(|e| Error::Netlink(e.to_string()))
+    }
+}
+
+/// Replies received for a request.
+#[derive(Debug)]
+pub(crate) struct Replies<T> {
+    pub(crate) messages: Vec<NetlinkMessage<T>>,
+    /// Whether the kernel flagged the dump with `NLM_F_DUMP_INTR`.
+    pub(crate) interrupted: bool,
+}
+
+impl<T> Default for Replies<T> {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+            interrupted: false,
+        }
+    }
+}
+
+impl<T> Replies<T> {
+    /// Adds a reply, returning true once the request is complete.
+    pub(crate) fn push(&mut self, message: NetlinkMessage<T>) -> Result<bool, Error> {
+        if message.header.flags & NLM_F_DUMP_INTR != 0 {
+            self.interrupted = true;
+        }
+        match message.payload {
+            NetlinkPayload::Done(_) => Ok(true),
+            NetlinkPayload::Error(err) => match err.code {
+                Some(code) => Err(Error::Kernel(code.get().abs())),
+                None => Ok(true),
+            },
+            NetlinkPayload::Overrun(_) => Err(Error::Netlink("Netlink buffer overrun".to_string())),
+            NetlinkPayload::InnerMessage(_) => {
+                self.messages.push(message);
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Builds the `tc` message selecting the filters attached to `parent`.
+pub(crate) fn filter_message(index: i32, parent: u32) -> TcMessage {
+    let mut message = TcMessage::with_index(index);
+    message.header.parent = parent;
+    message
+}
+
+/// Keeps the `bpf` filters running a program, ordered by chain and priority.
+/// The kernel also reports a filter without options for each priority, which is skipped.
+pub(crate) fn bpf_programs(filters: Vec<Tc>) -> Vec<Tc> {
+    let mut programs: Vec<Tc> = filters
+        .into_iter()
+        .filter(|tc| {
+            matches!(
+                tc.attr.filter,
+                Some(Filter {
+                    classifier: Some(Classifier::Bpf(_)),
+                    handle,
+                    ..
+                }) if handle != 0
+            )
+        })
+        .collect();
+    programs.sort_by_key(|tc| {
+        let filter = tc.attr.filter.as_ref().unwrap();
+        (filter.chain, filter.priority, filter.handle)
+    });
+    programs
+}
+
+/// Builds a dump request for `message`.
+pub(crate) fn dump_request<T: Reply>(message: T) -> NetlinkMessage<T> {
+    let mut header = NetlinkHeader::default();
+    header.flags = NLM_F_REQUEST | NLM_F_DUMP;
+    NetlinkMessage::new(header, NetlinkPayload::InnerMessage(message))
+}
+
+/// Serializes `message` with the given `sequence_number`.
+pub(crate) fn serialize<T: Reply>(mut message: NetlinkMessage<T>, sequence_number: u32) -> Vec<u8> {
+    message.header.sequence_number = sequence_number;
+    message.finalize();
+    let mut buf = vec![0; message.header.length as usize];
+    message.serialize(&mut buf[..]);
+    buf
+}
+
+/// Splits a datagram into its netlink messages.
+pub(crate) fn split(buf: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while offset + NETLINK_HEADER_LEN <= buf.len() {
+        let length = u32::from_ne_bytes(buf[offset..offset + 4].try_into().unwrap()) as usize;
+        if length < NETLINK_HEADER_LEN || offset + length > buf.len() {
+            return Err(Error::Netlink(format!(
+                "Invalid netlink message length: {length}"
+            )));
+        }
+        messages.push(&buf[offset..offset + length]);
+        offset += align(length);
+    }
+    Ok(messages)
+}
+
+/// Returns the sequence number from the header of the netlink message in `bytes`.
+pub(crate) fn message_sequence_number(bytes: &[u8]) -> u32 {
+    u32::from_ne_bytes(bytes[8..12].try_into().unwrap())
+}
+
+/// Deserializes a netlink message.
+/// `tc` messages whose options can't be decoded by `netlink-packet-route`
+/// (e.g. `pfifo_fast` whose options are a plain struct) are decoded without them,
+/// unless `fail_on_unknown_option` is set.
+fn deserialize_message(
+    bytes: &[u8],
+    opts: &ParseOptions,
+) -> Result<NetlinkMessage<RtnlMessage>, Error> {
+    match <NetlinkMessage<RtnlMessage>>::deserialize(bytes) {
+        Ok(message) => Ok(message),
+        Err(e) => {
+            let message_type = u16::from_ne_bytes(bytes[4..6].try_into().unwrap());
+            let is_tc = (RTM_NEWQDISC..=RTM_GETTFILTER).contains(&message_type);
+            if !is_tc || opts.fail_on_unknown_option {
+                return Err(Error::Netlink(e.to_string()));
+            }
+            <NetlinkMessage<RtnlMessage>>::deserialize(&strip_options(bytes))
+                .map_err(|e| Error::Netlink(e.to_string()))
+        }
+    }
+}
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+/// Returns a copy of the `tc` message in `bytes` without its `TCA_OPTIONS` attribute.
+pub(crate) fn strip_options(bytes: &[u8]) -> Vec<u8> {
+    let start = NETLINK_HEADER_LEN + TC_HEADER_LEN;
+    if bytes.len() < start {
+        return bytes.to_vec();
+    }
+
+    let mut buf = bytes[..start].to_vec();
+    let mut offset = start;
+    while offset + NLA_HEADER_LEN <= bytes.len() {
+        let length = u16::from_ne_bytes(bytes[offset..offset + 2].try_into().unwrap()) as usize;
+        let kind = u16::from_ne_bytes(bytes[offset + 2..offset + 4].try_into().unwrap());
+        if length < NLA_HEADER_LEN {
+            break;
+        }
+        let end = (offset + align(length)).min(bytes.len());
+        if kind & !(1 << 15 | 1 << 14) != TCA_OPTIONS {
+            buf.extend_from_slice(&bytes[offset..end]);
+        }
+        offset = end;
+    }
+
+    let length = buf.len() as u32;
+    buf[..4].copy_from_slice(&length.to_ne_bytes());
+    buf
+}
@@ -2,7 +2,8 @@
 //!
 //! `netlink-tc` provides a pure Rust API for interacting with the [netlink](https://www.kernel.org/doc/html/latest/userspace-api/netlink/intro.html) based Linux Traffic Control ([`tc`](http://man7.org/linux/man-pages/man8/tc.8.html)) subsystem of [`rtnetlink`](http://man7.org/linux/man-pages/man7/rtnetlink.7.html).
 //!
-//! This library is very much in progress. It only supports a small subset of `classless` and `classful` [qdiscs](https://tldp.org/HOWTO/Traffic-Control-HOWTO/components.html#c-qdisc). Also, the library only supports read at the moment.
+//! This library is very much in progress. It only supports a small subset of `classless` and `classful` [qdiscs](https://tldp.org/HOWTO/Traffic-Control-HOWTO/components.html#c-qdisc).
+//! Qdiscs and classes can be created, replaced and deleted with `QDiscRequest` and `ClassRequest`.
 //!
 //! ## Example
 //!
@@ -22,36 +23,64 @@
 //!     .tc(messages)
 //!     .unwrap();
 //! ```
+//!
+//! Alternatively, `TcHandle` retrieves and parses the messages over its own netlink socket.
+//!
+//! ```no_run
+//! use netlink_tc::{ParseOptions, TcHandle};
+//!
+//! let mut opts = ParseOptions::new();
+//! opts.fail_on_unknown_attribute(false)
+//!     .fail_on_unknown_option(false);
+//! let qdiscs = TcHandle::with_options(opts).unwrap().qdiscs().unwrap();
+//! ```
 use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
 use netlink_packet_route::{tc as netlink_tc, RtnlMessage, TcMessage as NlTcMessage};
 use netlink_packet_utils::{nla::Nla, Emitable};
 
+use action::{ActionMessage, TcaRoot};
 use errors::Error;
-use types::{Tc, TcAttr, TcHeader, TcMsg, TcOption, TcStats2};
+use types::{Action, Tc, TcAttr, TcHeader, TcMsg, TcOption, TcStats2, TcaStats};
+
+#[cfg(feature = "tokio")]
+pub use async_handle::AsyncTcHandle;
+pub use handle::TcHandle;
+pub use request::{ClassRequest, FilterRequest, Operation, QDiscRequest};
 
+pub mod action;
+pub mod class;
+pub mod constants;
 pub mod errors;
+pub mod filter;
+pub mod qdiscs;
+pub mod stab;
 pub mod types;
 
-mod class;
-mod constants;
-mod qdiscs;
+pub mod rate;
+
+#[cfg(feature = "tokio")]
+mod async_handle;
+mod handle;
+mod request;
 mod tc;
 
 #[cfg(test)]
 mod test_data;
 #[cfg(test)]
+#[allow(clippy::get_first)]
 mod tests;
 
 /// Possible message types for `tc` messages.
 /// A subset of `rtnl::RtnlMessage` enum.
 pub enum RtNetlinkMessage {
-    GetQdisc(TcMsg), /* RTM_GETQDISC */
-    GetClass(TcMsg), /* RTM_GETCLASS */
+    GetQdisc(TcMsg),  /* RTM_GETQDISC */
+    GetClass(TcMsg),  /* RTM_GETCLASS */
+    GetFilter(TcMsg), /* RTM_GETTFILTER */
 }
 
 /// `OpenOptions` provides options for controlling how `netlink-tc` parses netlink messages.
 /// By default, unknown attributes and options are ignored.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ParseOptions {
     fail_on_unknown_netlink_message: bool,
     fail_on_unknown_attribute: bool,
@@ -100,7 +129,7 @@ impl ParseOptions {
         self
     }
 
-    /// Parses `tc` queueing disciplines and classes for the corresponding Netlink messages
+    /// Parses `tc` queueing disciplines, classes and filters for the corresponding Netlink messages
     /// with the options specified by `self`.
     ///
     /// # Example
@@ -116,6 +145,24 @@ impl ParseOptions {
     pub fn tc(&self, messages: Vec<NetlinkMessage<RtnlMessage>>) -> Result<Vec<Tc>, Error> {
         tc_stats(messages, self)
     }
+
+    /// Parses the `tc` actions reported in `RTM_NEWACTION` or `RTM_GETACTION` messages,
+    /// which `netlink-packet-route` doesn't decode, see `ActionMessage`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use netlink_tc::ParseOptions;
+    ///
+    /// let actions = ParseOptions::new()
+    ///     .fail_on_unknown_option(false)
+    ///     .actions(vec![]); // init with netlink messages
+    /// ```
+    pub fn actions(
+        &self,
+        messages: Vec<NetlinkMessage<ActionMessage>>,
+    ) -> Result<Vec<Action>, Error> {
+        parse_actions(messages, self)
+    }
 }
 
 fn to_tc(tc_message: NlTcMessage, opts: &ParseOptions) -> Result<TcMsg, Error> {
@@ -128,6 +175,7 @@ fn to_tc(tc_message: NlTcMessage, opts: &ParseOptions) -> Result<TcMsg, Error> {
         index: tc_header.index,
         handle: tc_header.handle,
         parent: tc_header.parent,
+        info: tc_header.info,
     };
     let mut attrs = Vec::new();
 
@@ -147,6 +195,16 @@ fn to_tc(tc_message: NlTcMessage, opts: &ParseOptions) -> Result<TcMsg, Error> {
                             };
                             options.push(option);
                         }
+                        // `netlink-packet-route` decodes the options of a few classifiers,
+                        // turn them back into raw options for our own parsers.
+                        netlink_tc::TcOpt::U32(_) | netlink_tc::TcOpt::Matchall(_) => {
+                            let mut buf = vec![0u8; opt.value_len()];
+                            opt.emit_value(buf.as_mut_slice());
+                            options.push(TcOption {
+                                kind: opt.kind(),
+                                bytes: buf,
+                            });
+                        }
                         _ => {
                             if opts.fail_on_unknown_option {
                                 return Err(Error::Parse(format!(
@@ -167,16 +225,28 @@ fn to_tc(tc_message: NlTcMessage, opts: &ParseOptions) -> Result<TcMsg, Error> {
             netlink_tc::Nla::Stats2(tc_stats) => {
                 let mut stats2 = Vec::new();
                 for stat in tc_stats {
-                    match stat {
-                        netlink_tc::Stats2::StatsBasic(bytes) => {
-                            stats2.push(TcStats2::StatsBasic(bytes))
-                        }
-                        netlink_tc::Stats2::StatsQueue(bytes) => {
-                            stats2.push(TcStats2::StatsQueue(bytes))
-                        }
-                        netlink_tc::Stats2::StatsApp(bytes) => {
-                            stats2.push(TcStats2::StatsApp(bytes))
+                    // the attributes `netlink-packet-route` does not know are turned into
+                    // raw attributes by their `TCA_STATS_*` type
+                    let (kind, bytes) = match &stat {
+                        netlink_tc::Stats2::StatsBasic(bytes) => (TcaStats::Basic, bytes.clone()),
+                        netlink_tc::Stats2::StatsQueue(bytes) => (TcaStats::Queue, bytes.clone()),
+                        netlink_tc::Stats2::StatsApp(bytes) => (TcaStats::App, bytes.clone()),
+                        netlink_tc::Stats2::Other(nla) => {
+                            let mut buf = vec![0u8; nla.value_len()];
+                            nla.emit_value(buf.as_mut_slice());
+                            (TcaStats::from(nla.kind()), buf)
                         }
+                        _ => (TcaStats::Max, Vec::new()),
+                    };
+                    match kind {
+                        TcaStats::Basic => stats2.push(TcStats2::StatsBasic(bytes)),
+                        TcaStats::Queue => stats2.push(TcStats2::StatsQueue(bytes)),
+                        TcaStats::App => stats2.push(TcStats2::StatsApp(bytes)),
+                        TcaStats::RateEst => stats2.push(TcStats2::RateEst(bytes)),
+                        TcaStats::RateEst64 => stats2.push(TcStats2::RateEst64(bytes)),
+                        TcaStats::BasicHw => stats2.push(TcStats2::BasicHw(bytes)),
+                        TcaStats::Pkt64 => stats2.push(TcStats2::Pkt64(bytes)),
+                        TcaStats::Pad => (),
                         _ => {
                             if opts.fail_on_unknown_attribute {
                                 return Err(Error::Parse(format!(
@@ -219,6 +289,9 @@ fn parse(
             NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficClass(message)) => {
                 tc_messages.push(RtNetlinkMessage::GetClass(to_tc(message.clone(), opts)?))
             }
+            NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficFilter(message)) => {
+                tc_messages.push(RtNetlinkMessage::GetFilter(to_tc(message.clone(), opts)?))
+            }
             payload => {
                 if opts.fail_on_unknown_netlink_message {
                     return Err(Error::Parse(format!(
@@ -232,6 +305,45 @@ fn parse(
     Ok(tc_messages)
 }
 
+fn parse_actions(
+    messages: Vec<NetlinkMessage<ActionMessage>>,
+    opts: &ParseOptions,
+) -> Result<Vec<Action>, Error> {
+    let mut actions = Vec::new();
+    for message in messages {
+        match message.payload {
+            // the kernel answers dumps with the type of the request, `RTM_GETACTION`
+            NetlinkPayload::InnerMessage(
+                ActionMessage::NewAction(message) | ActionMessage::GetAction(message),
+            ) => {
+                for attr in message.attrs {
+                    match TcaRoot::from(attr.kind) {
+                        TcaRoot::Tab => actions.extend(tc::actions(&attr.bytes, opts)?),
+                        TcaRoot::Flags | TcaRoot::Count | TcaRoot::TimeDelta => (),
+                        _ => {
+                            if opts.fail_on_unknown_attribute {
+                                return Err(Error::Parse(format!(
+                                    "Attribute {} not implemented",
+                                    attr.kind
+                                )));
+                            }
+                        }
+                    }
+                }
+            }
+            payload => {
+                if opts.fail_on_unknown_netlink_message {
+                    return Err(Error::Parse(format!(
+                        "Unknown netlink message type: {}",
+                        payload.message_type()
+                    )));
+                }
+            }
+        }
+    }
+    Ok(actions)
+}
+
 /// Parse `tc` queueing disciplines and classes for the corresponding Netlink messages.
 fn tc_stats(
     messages: Vec<NetlinkMessage<RtnlMessage>>,
@@ -1,5 +1,17 @@
+pub mod cake;
 pub mod clsact;
+pub mod codel;
+pub mod fq;
 pub mod fq_codel;
+pub mod fq_pie;
+pub mod pie;
+pub mod tbf;
 
+pub use cake::*;
 pub use clsact::*;
+pub use codel::*;
+pub use fq::*;
 pub use fq_codel::*;
+pub use fq_pie::*;
+pub use pie::*;
+pub use tbf::*;
@@ -0,0 +1,443 @@
+use crate::types::{unmarshal_nested, unmarshal_u32_value, unmarshal_u64_value};
+use crate::{errors::Error, TcOption};
+
+/// Options of the `cake` qdisc, defined in `net/sched/sch_cake.c`.
+///
+/// Fields are `None` when the kernel did not report them, and are left out when encoding
+/// so that the kernel defaults, or the current values when replacing, apply.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cake {
+    /// Shaped rate in bytes per second, zero for unlimited.
+    pub bandwidth: Option<u64>,
+    /// One of the `CAKE_DIFFSERV_*` modes.
+    pub diffserv_mode: Option<u32>,
+    /// One of the `CAKE_ATM_*` link layer compensations.
+    pub atm: Option<u32>,
+    /// One of the `CAKE_FLOW_*` modes.
+    pub flow_mode: Option<u32>,
+    /// Bytes added to, or removed from, each packet when computing its size on the wire.
+    pub overhead: Option<i32>,
+    /// Expected round trip time, in microseconds.
+    pub rtt: Option<u32>,
+    /// Target queueing delay, in microseconds.
+    pub target: Option<u32>,
+    /// Whether the bandwidth is adjusted to the estimated capacity of the link.
+    pub autorate: Option<u32>,
+    /// Memory limit, in bytes.
+    pub memory: Option<u32>,
+    /// Whether flows are looked up through the NAT table.
+    pub nat: Option<u32>,
+    /// Reported as zero when no overhead compensation is set, only the presence is meaningful.
+    pub raw: Option<u32>,
+    /// Whether the DSCP is cleared on egress.
+    pub wash: Option<u32>,
+    /// Minimum packet unit, in bytes.
+    pub mpu: Option<u32>,
+    /// Whether dropped packets are accounted as sent, for shaping ingress traffic.
+    pub ingress: Option<u32>,
+    /// One of the `CAKE_ACK_*` modes.
+    pub ack_filter: Option<u32>,
+    /// Whether GSO packets are split into segments.
+    pub split_gso: Option<u32>,
+    /// Mask applied to the firewall mark to select the tin.
+    pub fwmark: Option<u32>,
+}
+
+/// Values of `TCA_CAKE_FLOW_MODE`, defined in `include/uapi/linux/pkt_sched.h`.
+pub const CAKE_FLOW_NONE: u32 = 0;
+pub const CAKE_FLOW_SRC_IP: u32 = 1;
+pub const CAKE_FLOW_DST_IP: u32 = 2;
+pub const CAKE_FLOW_HOSTS: u32 = 3;
+pub const CAKE_FLOW_FLOWS: u32 = 4;
+pub const CAKE_FLOW_DUAL_SRC: u32 = 5;
+pub const CAKE_FLOW_DUAL_DST: u32 = 6;
+pub const CAKE_FLOW_TRIPLE: u32 = 7;
+
+/// Values of `TCA_CAKE_DIFFSERV_MODE`.
+pub const CAKE_DIFFSERV_DIFFSERV3: u32 = 0;
+pub const CAKE_DIFFSERV_DIFFSERV4: u32 = 1;
+pub const CAKE_DIFFSERV_DIFFSERV8: u32 = 2;
+pub const CAKE_DIFFSERV_BESTEFFORT: u32 = 3;
+pub const CAKE_DIFFSERV_PRECEDENCE: u32 = 4;
+
+/// Values of `TCA_CAKE_ACK_FILTER`.
+pub const CAKE_ACK_NONE: u32 = 0;
+pub const CAKE_ACK_FILTER: u32 = 1;
+pub const CAKE_ACK_AGGRESSIVE: u32 = 2;
+
+/// Values of `TCA_CAKE_ATM`.
+pub const CAKE_ATM_NONE: u32 = 0;
+pub const CAKE_ATM_ATM: u32 = 1;
+pub const CAKE_ATM_PTM: u32 = 2;
+
+impl Cake {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_cake(opts)
+    }
+
+    /// Encodes the qdisc into `TCA_CAKE_*` options.
+    pub fn to_options(&self) -> Vec<TcOption> {
+        marshal_cake(self)
+    }
+}
+
+/// Statistics of a `cake` qdisc, nested in `TCA_STATS_APP` as `TCA_CAKE_STATS_*` attributes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CakeXStats {
+    /// Estimated capacity of the link in bytes per second, when `autorate` is enabled.
+    pub capacity_estimate: u64,
+    pub memory_limit: u32,
+    pub memory_used: u32,
+    /// Average offset of the network header, in bytes.
+    pub avg_netoff: u32,
+    pub min_netlen: u32,
+    pub max_netlen: u32,
+    /// Smallest packet size after overhead compensation.
+    pub min_adjlen: u32,
+    /// Largest packet size after overhead compensation.
+    pub max_adjlen: u32,
+    /// Statistics of each tin, in the order reported by the kernel.
+    pub tins: Vec<CakeTinStats>,
+}
+
+impl CakeXStats {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_cake_xstats(bytes)
+    }
+}
+
+/// Statistics of a `cake` tin, nested in `TCA_CAKE_STATS_TIN_STATS`
+/// as `TCA_CAKE_TIN_STATS_*` attributes. Delays are in microseconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CakeTinStats {
+    pub sent_packets: u32,
+    pub sent_bytes: u64,
+    pub dropped_packets: u32,
+    pub dropped_bytes: u64,
+    pub acks_dropped_packets: u32,
+    pub acks_dropped_bytes: u64,
+    pub ecn_marked_packets: u32,
+    pub ecn_marked_bytes: u64,
+    pub backlog_packets: u32,
+    pub backlog_bytes: u32,
+    /// Rate of the tin in bytes per second.
+    pub threshold_rate: u64,
+    pub target_us: u32,
+    pub interval_us: u32,
+    pub way_indirect_hits: u32,
+    pub way_misses: u32,
+    pub way_collisions: u32,
+    pub peak_delay_us: u32,
+    pub avg_delay_us: u32,
+    pub base_delay_us: u32,
+    pub sparse_flows: u32,
+    pub bulk_flows: u32,
+    pub unresponsive_flows: u32,
+    pub max_skblen: u32,
+    pub flow_quantum: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaCake {
+    #[default]
+    Unspec = 0,
+    Pad,
+    BaseRate64,
+    DiffservMode,
+    Atm,
+    FlowMode,
+    Overhead,
+    Rtt,
+    Target,
+    Autorate,
+    Memory,
+    Nat,
+    Raw,
+    Wash,
+    Mpu,
+    Ingress,
+    AckFilter,
+    SplitGso,
+    Fwmark,
+    Max,
+}
+
+impl From<u16> for TcaCake {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCake::Unspec,
+            1 => TcaCake::Pad,
+            2 => TcaCake::BaseRate64,
+            3 => TcaCake::DiffservMode,
+            4 => TcaCake::Atm,
+            5 => TcaCake::FlowMode,
+            6 => TcaCake::Overhead,
+            7 => TcaCake::Rtt,
+            8 => TcaCake::Target,
+            9 => TcaCake::Autorate,
+            10 => TcaCake::Memory,
+            11 => TcaCake::Nat,
+            12 => TcaCake::Raw,
+            13 => TcaCake::Wash,
+            14 => TcaCake::Mpu,
+            15 => TcaCake::Ingress,
+            16 => TcaCake::AckFilter,
+            17 => TcaCake::SplitGso,
+            18 => TcaCake::Fwmark,
+            _ => TcaCake::Max,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaCakeStats {
+    #[default]
+    Invalid = 0,
+    Pad,
+    CapacityEstimate64,
+    MemoryLimit,
+    MemoryUsed,
+    AvgNetoff,
+    MinNetlen,
+    MaxNetlen,
+    MinAdjlen,
+    MaxAdjlen,
+    TinStats,
+    Deficit,
+    CobaltCount,
+    Dropping,
+    DropNextUs,
+    PDrop,
+    BlueTimerUs,
+    Max,
+}
+
+impl From<u16> for TcaCakeStats {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCakeStats::Invalid,
+            1 => TcaCakeStats::Pad,
+            2 => TcaCakeStats::CapacityEstimate64,
+            3 => TcaCakeStats::MemoryLimit,
+            4 => TcaCakeStats::MemoryUsed,
+            5 => TcaCakeStats::AvgNetoff,
+            6 => TcaCakeStats::MinNetlen,
+            7 => TcaCakeStats::MaxNetlen,
+            8 => TcaCakeStats::MinAdjlen,
+            9 => TcaCakeStats::MaxAdjlen,
+            10 => TcaCakeStats::TinStats,
+            11 => TcaCakeStats::Deficit,
+            12 => TcaCakeStats::CobaltCount,
+            13 => TcaCakeStats::Dropping,
+            14 => TcaCakeStats::DropNextUs,
+            15 => TcaCakeStats::PDrop,
+            16 => TcaCakeStats::BlueTimerUs,
+            _ => TcaCakeStats::Max,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaCakeTinStats {
+    #[default]
+    Invalid = 0,
+    Pad,
+    SentPackets,
+    SentBytes64,
+    DroppedPackets,
+    DroppedBytes64,
+    AcksDroppedPackets,
+    AcksDroppedBytes64,
+    EcnMarkedPackets,
+    EcnMarkedBytes64,
+    BacklogPackets,
+    BacklogBytes,
+    ThresholdRate64,
+    TargetUs,
+    IntervalUs,
+    WayIndirectHits,
+    WayMisses,
+    WayCollisions,
+    PeakDelayUs,
+    AvgDelayUs,
+    BaseDelayUs,
+    SparseFlows,
+    BulkFlows,
+    UnresponsiveFlows,
+    MaxSkblen,
+    FlowQuantum,
+    Max,
+}
+
+impl From<u16> for TcaCakeTinStats {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCakeTinStats::Invalid,
+            1 => TcaCakeTinStats::Pad,
+            2 => TcaCakeTinStats::SentPackets,
+            3 => TcaCakeTinStats::SentBytes64,
+            4 => TcaCakeTinStats::DroppedPackets,
+            5 => TcaCakeTinStats::DroppedBytes64,
+            6 => TcaCakeTinStats::AcksDroppedPackets,
+            7 => TcaCakeTinStats::AcksDroppedBytes64,
+            8 => TcaCakeTinStats::EcnMarkedPackets,
+            9 => TcaCakeTinStats::EcnMarkedBytes64,
+            10 => TcaCakeTinStats::BacklogPackets,
+            11 => TcaCakeTinStats::BacklogBytes,
+            12 => TcaCakeTinStats::ThresholdRate64,
+            13 => TcaCakeTinStats::TargetUs,
+            14 => TcaCakeTinStats::IntervalUs,
+            15 => TcaCakeTinStats::WayIndirectHits,
+            16 => TcaCakeTinStats::WayMisses,
+            17 => TcaCakeTinStats::WayCollisions,
+            18 => TcaCakeTinStats::PeakDelayUs,
+            19 => TcaCakeTinStats::AvgDelayUs,
+            20 => TcaCakeTinStats::BaseDelayUs,
+            21 => TcaCakeTinStats::SparseFlows,
+            22 => TcaCakeTinStats::BulkFlows,
+            23 => TcaCakeTinStats::UnresponsiveFlows,
+            24 => TcaCakeTinStats::MaxSkblen,
+            25 => TcaCakeTinStats::FlowQuantum,
+            _ => TcaCakeTinStats::Max,
+        }
+    }
+}
+
+fn unmarshal_cake(opts: Vec<TcOption>) -> Cake {
+    let mut cake = Cake::default();
+
+    for opt in opts {
+        let kind = TcaCake::from(opt.kind);
+        let value = unmarshal_u32_value(&opt.bytes);
+        match kind {
+            TcaCake::BaseRate64 => cake.bandwidth = unmarshal_u64_value(&opt.bytes),
+            TcaCake::DiffservMode => cake.diffserv_mode = value,
+            TcaCake::Atm => cake.atm = value,
+            TcaCake::FlowMode => cake.flow_mode = value,
+            TcaCake::Overhead => cake.overhead = value.map(|v| v as i32),
+            TcaCake::Rtt => cake.rtt = value,
+            TcaCake::Target => cake.target = value,
+            TcaCake::Autorate => cake.autorate = value,
+            TcaCake::Memory => cake.memory = value,
+            TcaCake::Nat => cake.nat = value,
+            TcaCake::Raw => cake.raw = value,
+            TcaCake::Wash => cake.wash = value,
+            TcaCake::Mpu => cake.mpu = value,
+            TcaCake::Ingress => cake.ingress = value,
+            TcaCake::AckFilter => cake.ack_filter = value,
+            TcaCake::SplitGso => cake.split_gso = value,
+            TcaCake::Fwmark => cake.fwmark = value,
+            _ => (),
+        }
+    }
+
+    cake
+}
+
+fn marshal_cake(cake: &Cake) -> Vec<TcOption> {
+    let values = [
+        (TcaCake::DiffservMode, cake.diffserv_mode),
+        (TcaCake::Atm, cake.atm),
+        (TcaCake::FlowMode, cake.flow_mode),
+        (TcaCake::Overhead, cake.overhead.map(|v| v as u32)),
+        (TcaCake::Rtt, cake.rtt),
+        (TcaCake::Target, cake.target),
+        (TcaCake::Autorate, cake.autorate),
+        (TcaCake::Memory, cake.memory),
+        (TcaCake::Nat, cake.nat),
+        (TcaCake::Raw, cake.raw),
+        (TcaCake::Wash, cake.wash),
+        (TcaCake::Mpu, cake.mpu),
+        (TcaCake::Ingress, cake.ingress),
+        (TcaCake::AckFilter, cake.ack_filter),
+        (TcaCake::SplitGso, cake.split_gso),
+        (TcaCake::Fwmark, cake.fwmark),
+    ];
+
+    let mut opts = Vec::new();
+    if let Some(bandwidth) = cake.bandwidth {
+        opts.push(TcOption {
+            kind: TcaCake::BaseRate64 as u16,
+            bytes: bandwidth.to_ne_bytes().to_vec(),
+        });
+    }
+    opts.extend(values.into_iter().filter_map(|(kind, value)| {
+        value.map(|value| TcOption {
+            kind: kind as u16,
+            bytes: value.to_ne_bytes().to_vec(),
+        })
+    }));
+
+    opts
+}
+
+fn unmarshal_cake_xstats(bytes: &[u8]) -> Result<CakeXStats, Error> {
+    let mut stats = CakeXStats::default();
+
+    for opt in unmarshal_nested(bytes)? {
+        let b = opt.bytes.as_slice();
+        let value = unmarshal_u32_value(b).unwrap_or_default();
+        match TcaCakeStats::from(opt.kind) {
+            TcaCakeStats::CapacityEstimate64 => {
+                stats.capacity_estimate = unmarshal_u64_value(b).unwrap_or_default()
+            }
+            TcaCakeStats::MemoryLimit => stats.memory_limit = value,
+            TcaCakeStats::MemoryUsed => stats.memory_used = value,
+            TcaCakeStats::AvgNetoff => stats.avg_netoff = value,
+            TcaCakeStats::MinNetlen => stats.min_netlen = value,
+            TcaCakeStats::MaxNetlen => stats.max_netlen = value,
+            TcaCakeStats::MinAdjlen => stats.min_adjlen = value,
+            TcaCakeStats::MaxAdjlen => stats.max_adjlen = value,
+            TcaCakeStats::TinStats => {
+                // each tin is nested under its index plus one
+                stats.tins = unmarshal_nested(b)?
+                    .iter()
+                    .map(|tin| unmarshal_cake_tin_stats(&tin.bytes))
+                    .collect::<Result<_, _>>()?
+            }
+            // the remaining attributes describe the flows dumped as classes
+            _ => (),
+        }
+    }
+
+    Ok(stats)
+}
+
+fn unmarshal_cake_tin_stats(bytes: &[u8]) -> Result<CakeTinStats, Error> {
+    let mut tin = CakeTinStats::default();
+
+    for opt in unmarshal_nested(bytes)? {
+        let b = opt.bytes.as_slice();
+        let value = unmarshal_u32_value(b).unwrap_or_default();
+        let value64 = unmarshal_u64_value(b).unwrap_or_default();
+        match TcaCakeTinStats::from(opt.kind) {
+            TcaCakeTinStats::SentPackets => tin.sent_packets = value,
+            TcaCakeTinStats::SentBytes64 => tin.sent_bytes = value64,
+            TcaCakeTinStats::DroppedPackets => tin.dropped_packets = value,
+            TcaCakeTinStats::DroppedBytes64 => tin.dropped_bytes = value64,
+            TcaCakeTinStats::AcksDroppedPackets => tin.acks_dropped_packets = value,
+            TcaCakeTinStats::AcksDroppedBytes64 => tin.acks_dropped_bytes = value64,
+            TcaCakeTinStats::EcnMarkedPackets => tin.ecn_marked_packets = value,
+            TcaCakeTinStats::EcnMarkedBytes64 => tin.ecn_marked_bytes = value64,
+            TcaCakeTinStats::BacklogPackets => tin.backlog_packets = value,
+            TcaCakeTinStats::BacklogBytes => tin.backlog_bytes = value,
+            TcaCakeTinStats::ThresholdRate64 => tin.threshold_rate = value64,
+            TcaCakeTinStats::TargetUs => tin.target_us = value,
+            TcaCakeTinStats::IntervalUs => tin.interval_us = value,
+            TcaCakeTinStats::WayIndirectHits => tin.way_indirect_hits = value,
+            TcaCakeTinStats::WayMisses => tin.way_misses = value,
+            TcaCakeTinStats::WayCollisions => tin.way_collisions = value,
+            TcaCakeTinStats::PeakDelayUs => tin.peak_delay_us = value,
+            TcaCakeTinStats::AvgDelayUs => tin.avg_delay_us = value,
+            TcaCakeTinStats::BaseDelayUs => tin.base_delay_us = value,
+            TcaCakeTinStats::SparseFlows => tin.sparse_flows = value,
+            TcaCakeTinStats::BulkFlows => tin.bulk_flows = value,
+            TcaCakeTinStats::UnresponsiveFlows => tin.unresponsive_flows = value,
+            TcaCakeTinStats::MaxSkblen => tin.max_skblen = value,
+            TcaCakeTinStats::FlowQuantum => tin.flow_quantum = value,
+            _ => (),
+        }
+    }
+
+    Ok(tin)
+}
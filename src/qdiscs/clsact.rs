@@ -1,2 +1,21 @@
+use crate::constants::{TC_H_CLSACT, TC_H_MAJ_MASK, TC_H_MIN_EGRESS, TC_H_MIN_INGRESS};
+
 #[derive(Debug, PartialEq)]
 pub struct Clsact {}
+
+/// The hooks of a `clsact` qdisc filters are attached to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClsactHook {
+    Ingress,
+    Egress,
+}
+
+impl ClsactHook {
+    /// Returns the parent of the filters attached to the hook.
+    pub fn parent(&self) -> u32 {
+        match self {
+            ClsactHook::Ingress => (TC_H_CLSACT & TC_H_MAJ_MASK) | TC_H_MIN_INGRESS,
+            ClsactHook::Egress => (TC_H_CLSACT & TC_H_MAJ_MASK) | TC_H_MIN_EGRESS,
+        }
+    }
+}
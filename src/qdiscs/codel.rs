@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rate::time_to_usecs;
+use crate::{errors::Error, TcOption};
+
+/// Options of the `codel` qdisc, defined in `net/sched/sch_codel.c`.
+/// Times are in microseconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Codel {
+    pub target: u32,
+    pub limit: u32,
+    pub interval: u32,
+    pub ecn: u32,
+    pub ce_threshold: u32,
+}
+
+impl Codel {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_codel(opts)
+    }
+
+    /// Encodes the qdisc into `TCA_CODEL_*` options.
+    /// Fields left at zero are omitted so that the kernel defaults apply,
+    /// except for `ecn` which is always sent.
+    pub fn to_options(&self) -> Vec<TcOption> {
+        marshal_codel(self)
+    }
+}
+
+/// `CodelBuilder` computes the options of a `codel` qdisc from human units,
+/// like `tc qdisc add ... codel` does.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use netlink_tc::qdiscs::CodelBuilder;
+///
+/// // codel target 5ms interval 100ms ecn
+/// let codel = CodelBuilder::new()
+///     .target(Duration::from_millis(5))
+///     .interval(Duration::from_millis(100))
+///     .ecn(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(codel.target, 5000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CodelBuilder {
+    target: Option<Duration>,
+    limit: u32,
+    interval: Option<Duration>,
+    ecn: bool,
+    ce_threshold: Option<Duration>,
+}
+
+impl CodelBuilder {
+    /// Creates a builder leaving every option to the kernel defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the acceptable minimum queueing delay.
+    pub fn target(&mut self, target: Duration) -> &mut Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets the maximum number of packets queued.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the window over which the minimum delay is measured, typically the worst RTT.
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Sets whether packets are ECN marked instead of dropped.
+    pub fn ecn(&mut self, ecn: bool) -> &mut Self {
+        self.ecn = ecn;
+        self
+    }
+
+    /// Sets the queueing delay above which packets are CE marked.
+    pub fn ce_threshold(&mut self, ce_threshold: Duration) -> &mut Self {
+        self.ce_threshold = Some(ce_threshold);
+        self
+    }
+
+    /// Builds the qdisc options, failing if a time does not fit in 32-bit microseconds.
+    pub fn build(&self) -> Result<Codel, Error> {
+        let usecs = |time: Option<Duration>| time.map_or(Ok(0), time_to_usecs);
+        Ok(Codel {
+            target: usecs(self.target)?,
+            limit: self.limit,
+            interval: usecs(self.interval)?,
+            ecn: self.ecn as u32,
+            ce_threshold: usecs(self.ce_threshold)?,
+        })
+    }
+}
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_codel_xstats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodelXStats {
+    /// Largest packet seen so far.
+    pub maxpacket: u32,
+    /// Number of drops since entering the dropping state.
+    pub count: u32,
+    pub lastcount: u32,
+    /// Sojourn time of the last dequeued packet, in microseconds.
+    pub ldelay: u32,
+    /// Time to the next drop in microseconds, negative when overdue.
+    pub drop_next: i32,
+    pub drop_overlimit: u32,
+    pub ecn_mark: u32,
+    pub dropping: u32,
+    pub ce_mark: u32,
+}
+
+impl CodelXStats {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_codel_xstats(bytes)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaCodel {
+    #[default]
+    Unspec = 0,
+    Target,
+    Limit,
+    Interval,
+    Ecn,
+    CeThreshold,
+    Max,
+}
+
+impl From<u16> for TcaCodel {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaCodel::Unspec,
+            1 => TcaCodel::Target,
+            2 => TcaCodel::Limit,
+            3 => TcaCodel::Interval,
+            4 => TcaCodel::Ecn,
+            5 => TcaCodel::CeThreshold,
+            _ => TcaCodel::Max,
+        }
+    }
+}
+
+fn unmarshal_codel(opts: Vec<TcOption>) -> Codel {
+    let mut codel = Codel::default();
+
+    for opt in opts {
+        let kind = TcaCodel::from(opt.kind);
+        if opt.bytes.len() < 4 {
+            // TODO: log error
+            continue;
+        }
+        let value = u32::from_ne_bytes(opt.bytes[..4].try_into().unwrap());
+        match kind {
+            TcaCodel::Target => codel.target = value,
+            TcaCodel::Limit => codel.limit = value,
+            TcaCodel::Interval => codel.interval = value,
+            TcaCodel::Ecn => codel.ecn = value,
+            TcaCodel::CeThreshold => codel.ce_threshold = value,
+            _ => (),
+        }
+    }
+
+    codel
+}
+
+fn marshal_codel(codel: &Codel) -> Vec<TcOption> {
+    let values = [
+        (TcaCodel::Target, codel.target),
+        (TcaCodel::Limit, codel.limit),
+        (TcaCodel::Interval, codel.interval),
+        (TcaCodel::Ecn, codel.ecn),
+        (TcaCodel::CeThreshold, codel.ce_threshold),
+    ];
+
+    values
+        .into_iter()
+        .filter(|(kind, value)| *value != 0 || *kind == TcaCodel::Ecn)
+        .map(|(kind, value)| TcOption {
+            kind: kind as u16,
+            bytes: value.to_ne_bytes().to_vec(),
+        })
+        .collect()
+}
+
+fn unmarshal_codel_xstats(bytes: &[u8]) -> Result<CodelXStats, Error> {
+    if bytes.len() < 36 {
+        return Err(Error::Parse("Codel XStats requires 36 bytes".to_string()));
+    }
+    bincode::deserialize(bytes).map_err(|e| Error::Parse(e.to_string()))
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::unmarshal_u32_value;
+use crate::{errors::Error, TcOption};
+
+/// Options of the `fq` qdisc, defined in `net/sched/sch_fq.c`.
+///
+/// Fields are `None` when the kernel did not report them, and are left out when encoding
+/// so that the kernel defaults apply.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fq {
+    /// Maximum number of packets queued.
+    pub plimit: Option<u32>,
+    /// Maximum number of packets queued per flow.
+    pub flow_plimit: Option<u32>,
+    /// Credit per dequeue round, in bytes.
+    pub quantum: Option<u32>,
+    /// Credit of new flows, in bytes.
+    pub initial_quantum: Option<u32>,
+    /// Whether the pacing rate of sockets is enforced.
+    pub rate_enable: Option<u32>,
+    /// Maximum rate per flow in bytes per second, `u32::MAX` for unlimited.
+    pub flow_max_rate: Option<u32>,
+    /// Log2 of the number of flow hash buckets.
+    pub buckets_log: Option<u32>,
+    /// Delay in microseconds before a throttled flow gets credit again.
+    pub flow_refill_delay: Option<u32>,
+    /// Mask used to hash packets without a socket into flows.
+    pub orphan_mask: Option<u32>,
+    /// Flows below this rate in bytes per second are served first.
+    pub low_rate_threshold: Option<u32>,
+    /// Sojourn time in microseconds above which packets are CE marked.
+    pub ce_threshold: Option<u32>,
+    /// Timer slack in nanoseconds.
+    pub timer_slack: Option<u32>,
+    /// Packets with a departure time further than this in microseconds are dropped or capped.
+    pub horizon: Option<u32>,
+    /// Whether packets beyond the horizon are dropped rather than capped.
+    pub horizon_drop: Option<u8>,
+    pub priomap: Option<FqPrioMap>,
+    /// Weights of the bands, in bytes per round.
+    pub weights: Option<Vec<i32>>,
+}
+
+/// Priority to band mapping of `fq`, sent as `struct tc_prio_qopt`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FqPrioMap {
+    pub bands: i32,
+    pub priomap: [u8; TC_PRIO_MAX + 1],
+}
+
+/// Highest packet priority mapped to a band, defined in `include/uapi/linux/pkt_sched.h`.
+pub const TC_PRIO_MAX: usize = 15;
+
+/// Number of priority bands of `fq`.
+pub const FQ_BANDS: usize = 3;
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_fq_qd_stats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FqQdStats {
+    pub gc_flows: u64,
+    /// Obsolete.
+    pub highprio_packets: u64,
+    /// Obsolete.
+    pub tcp_retrans: u64,
+    pub throttled: u64,
+    pub flows_plimit: u64,
+    pub pkts_too_long: u64,
+    pub allocation_errors: u64,
+    pub time_next_delayed_flow: i64,
+    pub flows: u32,
+    pub inactive_flows: u32,
+    pub throttled_flows: u32,
+    pub unthrottle_latency_ns: u32,
+    pub ce_mark: u64,
+    pub horizon_drops: u64,
+    pub horizon_caps: u64,
+    pub fastpath_packets: u64,
+    pub band_drops: [u64; FQ_BANDS],
+    pub band_pkt_count: [u32; FQ_BANDS],
+    pub pad: u32,
+}
+
+/// Size of `struct tc_fq_qd_stats` in the latest kernels,
+/// older kernels send a prefix of it.
+const FQ_QD_STATS_LEN: usize = 152;
+
+/// Size of the first version of `struct tc_fq_qd_stats`, up to `throttled_flows`.
+const FQ_QD_STATS_MIN_LEN: usize = 80;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaFq {
+    #[default]
+    Unspec = 0,
+    Plimit,
+    FlowPlimit,
+    Quantum,
+    InitialQuantum,
+    RateEnable,
+    FlowDefaultRate,
+    FlowMaxRate,
+    BucketsLog,
+    FlowRefillDelay,
+    OrphanMask,
+    LowRateThreshold,
+    CeThreshold,
+    TimerSlack,
+    Horizon,
+    HorizonDrop,
+    Priomap,
+    Weights,
+    Max,
+}
+
+impl From<u16> for TcaFq {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaFq::Unspec,
+            1 => TcaFq::Plimit,
+            2 => TcaFq::FlowPlimit,
+            3 => TcaFq::Quantum,
+            4 => TcaFq::InitialQuantum,
+            5 => TcaFq::RateEnable,
+            6 => TcaFq::FlowDefaultRate,
+            7 => TcaFq::FlowMaxRate,
+            8 => TcaFq::BucketsLog,
+            9 => TcaFq::FlowRefillDelay,
+            10 => TcaFq::OrphanMask,
+            11 => TcaFq::LowRateThreshold,
+            12 => TcaFq::CeThreshold,
+            13 => TcaFq::TimerSlack,
+            14 => TcaFq::Horizon,
+            15 => TcaFq::HorizonDrop,
+            16 => TcaFq::Priomap,
+            17 => TcaFq::Weights,
+            _ => TcaFq::Max,
+        }
+    }
+}
+
+impl Fq {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_fq(opts)
+    }
+
+    /// Encodes the qdisc into `TCA_FQ_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_fq(self)
+    }
+}
+
+impl FqQdStats {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_fq_qd_stats(bytes)
+    }
+}
+
+fn unmarshal_fq(opts: Vec<TcOption>) -> Fq {
+    let mut fq = Fq::default();
+
+    for opt in opts {
+        let kind = TcaFq::from(opt.kind);
+        let b = opt.bytes.as_slice();
+        let value = unmarshal_u32_value(b);
+        match kind {
+            TcaFq::Plimit => fq.plimit = value,
+            TcaFq::FlowPlimit => fq.flow_plimit = value,
+            TcaFq::Quantum => fq.quantum = value,
+            TcaFq::InitialQuantum => fq.initial_quantum = value,
+            TcaFq::RateEnable => fq.rate_enable = value,
+            TcaFq::FlowMaxRate => fq.flow_max_rate = value,
+            TcaFq::BucketsLog => fq.buckets_log = value,
+            TcaFq::FlowRefillDelay => fq.flow_refill_delay = value,
+            TcaFq::OrphanMask => fq.orphan_mask = value,
+            TcaFq::LowRateThreshold => fq.low_rate_threshold = value,
+            TcaFq::CeThreshold => fq.ce_threshold = value,
+            TcaFq::TimerSlack => fq.timer_slack = value,
+            TcaFq::Horizon => fq.horizon = value,
+            TcaFq::HorizonDrop => fq.horizon_drop = b.first().copied(),
+            TcaFq::Priomap => fq.priomap = bincode::deserialize(b).ok(),
+            TcaFq::Weights => {
+                fq.weights = Some(
+                    b.chunks_exact(4)
+                        .map(|b| i32::from_ne_bytes(b.try_into().unwrap()))
+                        .collect(),
+                )
+            }
+            _ => (),
+        }
+    }
+
+    fq
+}
+
+fn marshal_fq(fq: &Fq) -> Result<Vec<TcOption>, Error> {
+    let values = [
+        (TcaFq::Plimit, fq.plimit),
+        (TcaFq::FlowPlimit, fq.flow_plimit),
+        (TcaFq::Quantum, fq.quantum),
+        (TcaFq::InitialQuantum, fq.initial_quantum),
+        (TcaFq::RateEnable, fq.rate_enable),
+        (TcaFq::FlowMaxRate, fq.flow_max_rate),
+        (TcaFq::BucketsLog, fq.buckets_log),
+        (TcaFq::FlowRefillDelay, fq.flow_refill_delay),
+        (TcaFq::OrphanMask, fq.orphan_mask),
+        (TcaFq::LowRateThreshold, fq.low_rate_threshold),
+        (TcaFq::CeThreshold, fq.ce_threshold),
+        (TcaFq::TimerSlack, fq.timer_slack),
+        (TcaFq::Horizon, fq.horizon),
+    ];
+
+    let mut opts: Vec<TcOption> = values
+        .into_iter()
+        .filter_map(|(kind, value)| {
+            value.map(|value| TcOption {
+                kind: kind as u16,
+                bytes: value.to_ne_bytes().to_vec(),
+            })
+        })
+        .collect();
+    if let Some(horizon_drop) = fq.horizon_drop {
+        opts.push(TcOption {
+            kind: TcaFq::HorizonDrop as u16,
+            bytes: vec![horizon_drop],
+        });
+    }
+    if let Some(priomap) = &fq.priomap {
+        opts.push(TcOption {
+            kind: TcaFq::Priomap as u16,
+            bytes: bincode::serialize(priomap).map_err(|e| Error::Encode(e.to_string()))?,
+        });
+    }
+    if let Some(weights) = &fq.weights {
+        if weights.len() != FQ_BANDS {
+            return Err(Error::Encode(format!(
+                "fq requires {FQ_BANDS} weights, got {}",
+                weights.len()
+            )));
+        }
+        opts.push(TcOption {
+            kind: TcaFq::Weights as u16,
+            bytes: weights.iter().flat_map(|w| w.to_ne_bytes()).collect(),
+        });
+    }
+
+    Ok(opts)
+}
+
+fn unmarshal_fq_qd_stats(bytes: &[u8]) -> Result<FqQdStats, Error> {
+    if bytes.len() < FQ_QD_STATS_MIN_LEN {
+        return Err(Error::Parse(format!(
+            "Fq XStats requires at least {FQ_QD_STATS_MIN_LEN} bytes"
+        )));
+    }
+    // the structure has grown over time, the fields older kernels do not send are left at zero
+    let mut buf = bytes.to_vec();
+    if buf.len() < FQ_QD_STATS_LEN {
+        buf.resize(FQ_QD_STATS_LEN, 0);
+    }
+    bincode::deserialize(&buf).map_err(|e| Error::Parse(e.to_string()))
+}
@@ -8,7 +8,8 @@ pub struct FqCodel {
     pub target: u32,
     pub limit: u32,
     pub interval: u32,
-    pub ecn: u32,
+    /// Whether packets are ECN marked instead of dropped, `None` to keep the kernel default.
+    pub ecn: Option<u32>,
     pub flows: u32,
     pub quantum: u32,
     pub ce_threshold: u32,
@@ -20,6 +21,12 @@ impl FqCodel {
     pub fn new(opts: Vec<TcOption>) -> Self {
         unmarshal_fq_codel(opts)
     }
+
+    /// Encodes the qdisc into `TCA_FQ_CODEL_*` options.
+    /// Fields left at zero are omitted so that the kernel defaults apply.
+    pub fn to_options(&self) -> Vec<TcOption> {
+        marshal_fq_codel(self)
+    }
 }
 
 /// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_fq_codel_xstats`.
@@ -42,6 +49,35 @@ impl FqCodelXStats {
     }
 }
 
+/// Statistics of a single flow, reported as the xstats of the `fq_codel` pseudo-classes.
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_fq_codel_cl_stats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FqCodelClassXStats {
+    pub deficit: i32,
+    /// Sojourn time of the last dequeued packet, in microseconds.
+    pub ldelay: u32,
+    pub count: u32,
+    pub lastcount: u32,
+    pub dropping: u32,
+    /// Time to the next drop in microseconds, negative when overdue.
+    pub drop_next: i32,
+}
+
+impl FqCodelClassXStats {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_fq_codel_class_xstats(bytes)
+    }
+}
+
+/// A flow of a `fq_codel` qdisc, dumped as a class whose minor number is the flow index plus one.
+/// Flows have no options, their statistics are in `Attribute::xstats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FqCodelClass {}
+
+/// Types of `struct tc_fq_codel_xstats`.
+pub const TCA_FQ_CODEL_XSTATS_QDISC: u32 = 0;
+pub const TCA_FQ_CODEL_XSTATS_CLASS: u32 = 1;
+
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub enum TcaFqCodel {
     #[default]
@@ -90,7 +126,7 @@ fn unmarshal_fq_codel(opts: Vec<TcOption>) -> FqCodel {
             TcaFqCodel::Target => fq.target = value,
             TcaFqCodel::Limit => fq.limit = value,
             TcaFqCodel::Interval => fq.interval = value,
-            TcaFqCodel::Ecn => fq.ecn = value,
+            TcaFqCodel::Ecn => fq.ecn = Some(value),
             TcaFqCodel::Flows => fq.flows = value,
             TcaFqCodel::Quantum => fq.quantum = value,
             TcaFqCodel::CeThreshold => fq.ce_threshold = value,
@@ -103,6 +139,35 @@ fn unmarshal_fq_codel(opts: Vec<TcOption>) -> FqCodel {
     fq
 }
 
+fn marshal_fq_codel(fq: &FqCodel) -> Vec<TcOption> {
+    let values = [
+        (TcaFqCodel::Target, fq.target),
+        (TcaFqCodel::Limit, fq.limit),
+        (TcaFqCodel::Interval, fq.interval),
+        (TcaFqCodel::Flows, fq.flows),
+        (TcaFqCodel::Quantum, fq.quantum),
+        (TcaFqCodel::CeThreshold, fq.ce_threshold),
+        (TcaFqCodel::DropBatchSize, fq.drop_batch_size),
+        (TcaFqCodel::MemoryLimit, fq.memory_limit),
+    ];
+
+    let mut opts: Vec<TcOption> = values
+        .into_iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(kind, value)| TcOption {
+            kind: kind as u16,
+            bytes: value.to_ne_bytes().to_vec(),
+        })
+        .collect();
+    if let Some(ecn) = fq.ecn {
+        opts.push(TcOption {
+            kind: TcaFqCodel::Ecn as u16,
+            bytes: ecn.to_ne_bytes().to_vec(),
+        });
+    }
+    opts
+}
+
 fn unmarshal_fq_codel_xstats(bytes: &[u8]) -> Result<FqCodelXStats, Error> {
     if bytes.len() < 40 {
         return Err(Error::Parse("FqCodel XStats requires 40 bytes".to_string()));
@@ -111,7 +176,7 @@ fn unmarshal_fq_codel_xstats(bytes: &[u8]) -> Result<FqCodelXStats, Error> {
         .try_into()
         .map_err(|_| Error::Parse("Failed to extract FqCodel XStats kind".to_string()))?;
     let kind = u32::from_ne_bytes(buf);
-    if kind == 0 {
+    if kind == TCA_FQ_CODEL_XSTATS_QDISC {
         bincode::deserialize(&bytes[4..]).map_err(|e| Error::Parse(e.to_string()))
     } else {
         Err(Error::Parse(format!(
@@ -119,3 +184,19 @@ fn unmarshal_fq_codel_xstats(bytes: &[u8]) -> Result<FqCodelXStats, Error> {
         )))
     }
 }
+
+fn unmarshal_fq_codel_class_xstats(bytes: &[u8]) -> Result<FqCodelClassXStats, Error> {
+    if bytes.len() < 28 {
+        return Err(Error::Parse(
+            "FqCodel class XStats requires 28 bytes".to_string(),
+        ));
+    }
+    let kind = u32::from_ne_bytes(bytes[..4].try_into().unwrap());
+    if kind == TCA_FQ_CODEL_XSTATS_CLASS {
+        bincode::deserialize(&bytes[4..]).map_err(|e| Error::Parse(e.to_string()))
+    } else {
+        Err(Error::Parse(format!(
+            "FqCodel class XStats has unidentified kind: {kind}"
+        )))
+    }
+}
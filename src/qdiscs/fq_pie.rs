@@ -0,0 +1,313 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::qdiscs::pie::check_alpha_beta;
+use crate::rate::time_to_usecs;
+use crate::{errors::Error, TcOption};
+
+/// Options of the `fq_pie` qdisc, defined in `net/sched/sch_fq_pie.c`.
+/// Times are in microseconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FqPie {
+    pub limit: u32,
+    pub flows: u32,
+    pub target: u32,
+    pub tupdate: u32,
+    pub alpha: u32,
+    pub beta: u32,
+    pub quantum: u32,
+    pub memory_limit: u32,
+    pub ecn_prob: u32,
+    pub ecn: u32,
+    pub bytemode: u32,
+    pub dq_rate_estimator: u32,
+}
+
+impl FqPie {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_fq_pie(opts)
+    }
+
+    /// Encodes the qdisc into `TCA_FQ_PIE_*` options.
+    /// Fields left at zero are omitted so that the kernel defaults apply,
+    /// except for the `ecn`, `bytemode` and `dq_rate_estimator` flags which are always sent.
+    pub fn to_options(&self) -> Vec<TcOption> {
+        marshal_fq_pie(self)
+    }
+}
+
+/// Largest number of flows of `fq_pie`.
+pub const FQ_PIE_MAX_FLOWS: u32 = 65536;
+
+/// `FqPieBuilder` computes the options of a `fq_pie` qdisc from human units,
+/// like `tc qdisc add ... fq_pie` does.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use netlink_tc::qdiscs::FqPieBuilder;
+///
+/// // fq_pie flows 512 target 10ms ecn_prob 20
+/// let fq_pie = FqPieBuilder::new()
+///     .flows(512)
+///     .target(Duration::from_millis(10))
+///     .ecn_prob(20)
+///     .build()
+///     .unwrap();
+/// assert_eq!(fq_pie.target, 10_000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FqPieBuilder {
+    limit: u32,
+    flows: u32,
+    target: Option<Duration>,
+    tupdate: Option<Duration>,
+    alpha: u32,
+    beta: u32,
+    quantum: u32,
+    memory_limit: u32,
+    ecn_prob: u32,
+    ecn: bool,
+    bytemode: bool,
+    dq_rate_estimator: bool,
+}
+
+impl FqPieBuilder {
+    /// Creates a builder leaving every option to the kernel defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of packets queued.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the number of flow queues, which can't be changed once the qdisc is created.
+    pub fn flows(&mut self, flows: u32) -> &mut Self {
+        self.flows = flows;
+        self
+    }
+
+    /// Sets the target queueing delay.
+    pub fn target(&mut self, target: Duration) -> &mut Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets how often the drop probability is updated.
+    pub fn tupdate(&mut self, tupdate: Duration) -> &mut Self {
+        self.tupdate = Some(tupdate);
+        self
+    }
+
+    /// Sets the weight of the deviation from the target delay, between 0 and 32.
+    pub fn alpha(&mut self, alpha: u32) -> &mut Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the weight of the delay trend, between 0 and 32.
+    pub fn beta(&mut self, beta: u32) -> &mut Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Sets the number of bytes dequeued from a flow before moving to the next one.
+    pub fn quantum(&mut self, quantum: u32) -> &mut Self {
+        self.quantum = quantum;
+        self
+    }
+
+    /// Sets the maximum number of bytes queued.
+    pub fn memory_limit(&mut self, memory_limit: u32) -> &mut Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    /// Sets the drop probability in percent up to which packets are ECN marked.
+    pub fn ecn_prob(&mut self, ecn_prob: u32) -> &mut Self {
+        self.ecn_prob = ecn_prob;
+        self
+    }
+
+    /// Sets whether packets are ECN marked instead of dropped.
+    pub fn ecn(&mut self, ecn: bool) -> &mut Self {
+        self.ecn = ecn;
+        self
+    }
+
+    /// Sets whether the drop probability is scaled by the packet size.
+    pub fn bytemode(&mut self, bytemode: bool) -> &mut Self {
+        self.bytemode = bytemode;
+        self
+    }
+
+    /// Sets whether the queueing delay is estimated from the dequeue rate
+    /// rather than from timestamps.
+    pub fn dq_rate_estimator(&mut self, dq_rate_estimator: bool) -> &mut Self {
+        self.dq_rate_estimator = dq_rate_estimator;
+        self
+    }
+
+    /// Builds the qdisc options.
+    pub fn build(&self) -> Result<FqPie, Error> {
+        check_alpha_beta(self.alpha, self.beta)?;
+        if self.flows > FQ_PIE_MAX_FLOWS {
+            return Err(Error::Encode(format!(
+                "fq_pie supports at most {FQ_PIE_MAX_FLOWS} flows"
+            )));
+        }
+        if self.ecn_prob > 100 {
+            return Err(Error::Encode(
+                "fq_pie ecn_prob must be between 0 and 100".to_string(),
+            ));
+        }
+        let usecs = |time: Option<Duration>| time.map_or(Ok(0), time_to_usecs);
+        Ok(FqPie {
+            limit: self.limit,
+            flows: self.flows,
+            target: usecs(self.target)?,
+            tupdate: usecs(self.tupdate)?,
+            alpha: self.alpha,
+            beta: self.beta,
+            quantum: self.quantum,
+            memory_limit: self.memory_limit,
+            ecn_prob: self.ecn_prob,
+            ecn: self.ecn as u32,
+            bytemode: self.bytemode as u32,
+            dq_rate_estimator: self.dq_rate_estimator as u32,
+        })
+    }
+}
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_fq_pie_xstats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FqPieXStats {
+    pub packets_in: u32,
+    pub dropped: u32,
+    pub overlimit: u32,
+    pub overmemory: u32,
+    pub ecn_mark: u32,
+    pub new_flow_count: u32,
+    pub new_flows_len: u32,
+    pub old_flows_len: u32,
+    pub memory_usage: u32,
+}
+
+impl FqPieXStats {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_fq_pie_xstats(bytes)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaFqPie {
+    #[default]
+    Unspec = 0,
+    Limit,
+    Flows,
+    Target,
+    Tupdate,
+    Alpha,
+    Beta,
+    Quantum,
+    MemoryLimit,
+    EcnProb,
+    Ecn,
+    Bytemode,
+    DqRateEstimator,
+    Max,
+}
+
+impl From<u16> for TcaFqPie {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaFqPie::Unspec,
+            1 => TcaFqPie::Limit,
+            2 => TcaFqPie::Flows,
+            3 => TcaFqPie::Target,
+            4 => TcaFqPie::Tupdate,
+            5 => TcaFqPie::Alpha,
+            6 => TcaFqPie::Beta,
+            7 => TcaFqPie::Quantum,
+            8 => TcaFqPie::MemoryLimit,
+            9 => TcaFqPie::EcnProb,
+            10 => TcaFqPie::Ecn,
+            11 => TcaFqPie::Bytemode,
+            12 => TcaFqPie::DqRateEstimator,
+            _ => TcaFqPie::Max,
+        }
+    }
+}
+
+fn unmarshal_fq_pie(opts: Vec<TcOption>) -> FqPie {
+    let mut fq_pie = FqPie::default();
+
+    for opt in opts {
+        let kind = TcaFqPie::from(opt.kind);
+        if opt.bytes.len() < 4 {
+            // TODO: log error
+            continue;
+        }
+        let value = u32::from_ne_bytes(opt.bytes[..4].try_into().unwrap());
+        match kind {
+            TcaFqPie::Limit => fq_pie.limit = value,
+            TcaFqPie::Flows => fq_pie.flows = value,
+            TcaFqPie::Target => fq_pie.target = value,
+            TcaFqPie::Tupdate => fq_pie.tupdate = value,
+            TcaFqPie::Alpha => fq_pie.alpha = value,
+            TcaFqPie::Beta => fq_pie.beta = value,
+            TcaFqPie::Quantum => fq_pie.quantum = value,
+            TcaFqPie::MemoryLimit => fq_pie.memory_limit = value,
+            TcaFqPie::EcnProb => fq_pie.ecn_prob = value,
+            TcaFqPie::Ecn => fq_pie.ecn = value,
+            TcaFqPie::Bytemode => fq_pie.bytemode = value,
+            TcaFqPie::DqRateEstimator => fq_pie.dq_rate_estimator = value,
+            _ => (),
+        }
+    }
+
+    fq_pie
+}
+
+fn marshal_fq_pie(fq_pie: &FqPie) -> Vec<TcOption> {
+    let values = [
+        (TcaFqPie::Limit, fq_pie.limit),
+        (TcaFqPie::Flows, fq_pie.flows),
+        (TcaFqPie::Target, fq_pie.target),
+        (TcaFqPie::Tupdate, fq_pie.tupdate),
+        (TcaFqPie::Alpha, fq_pie.alpha),
+        (TcaFqPie::Beta, fq_pie.beta),
+        (TcaFqPie::Quantum, fq_pie.quantum),
+        (TcaFqPie::MemoryLimit, fq_pie.memory_limit),
+        (TcaFqPie::EcnProb, fq_pie.ecn_prob),
+        (TcaFqPie::Ecn, fq_pie.ecn),
+        (TcaFqPie::Bytemode, fq_pie.bytemode),
+        (TcaFqPie::DqRateEstimator, fq_pie.dq_rate_estimator),
+    ];
+
+    values
+        .into_iter()
+        .filter(|(kind, value)| {
+            *value != 0
+                || matches!(
+                    kind,
+                    TcaFqPie::Ecn | TcaFqPie::Bytemode | TcaFqPie::DqRateEstimator
+                )
+        })
+        .map(|(kind, value)| TcOption {
+            kind: kind as u16,
+            bytes: value.to_ne_bytes().to_vec(),
+        })
+        .collect()
+}
+
+fn unmarshal_fq_pie_xstats(bytes: &[u8]) -> Result<FqPieXStats, Error> {
+    if bytes.len() < 36 {
+        return Err(Error::Parse("FqPie XStats requires 36 bytes".to_string()));
+    }
+    bincode::deserialize(bytes).map_err(|e| Error::Parse(e.to_string()))
+}
@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rate::time_to_usecs;
+use crate::{errors::Error, TcOption};
+
+/// Options of the `pie` qdisc, defined in `net/sched/sch_pie.c`.
+/// Times are in microseconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pie {
+    pub target: u32,
+    pub limit: u32,
+    pub tupdate: u32,
+    pub alpha: u32,
+    pub beta: u32,
+    pub ecn: u32,
+    pub bytemode: u32,
+    pub dq_rate_estimator: u32,
+}
+
+impl Pie {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_pie(opts)
+    }
+
+    /// Encodes the qdisc into `TCA_PIE_*` options.
+    /// Fields left at zero are omitted so that the kernel defaults apply,
+    /// except for the `ecn`, `bytemode` and `dq_rate_estimator` flags which are always sent.
+    pub fn to_options(&self) -> Vec<TcOption> {
+        marshal_pie(self)
+    }
+}
+
+/// Largest `alpha` and `beta` accepted by `tc`.
+pub const PIE_MAX_ALPHA_BETA: u32 = 32;
+
+/// `PieBuilder` computes the options of a `pie` qdisc from human units,
+/// like `tc qdisc add ... pie` does.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use netlink_tc::qdiscs::PieBuilder;
+///
+/// // pie target 20ms limit 1000 ecn
+/// let pie = PieBuilder::new()
+///     .target(Duration::from_millis(20))
+///     .limit(1000)
+///     .ecn(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(pie.target, 20_000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PieBuilder {
+    target: Option<Duration>,
+    limit: u32,
+    tupdate: Option<Duration>,
+    alpha: u32,
+    beta: u32,
+    ecn: bool,
+    bytemode: bool,
+    dq_rate_estimator: bool,
+}
+
+impl PieBuilder {
+    /// Creates a builder leaving every option to the kernel defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the target queueing delay.
+    pub fn target(&mut self, target: Duration) -> &mut Self {
+        self.target = Some(target);
+        self
+    }
+
+    /// Sets the maximum number of packets queued.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets how often the drop probability is updated.
+    pub fn tupdate(&mut self, tupdate: Duration) -> &mut Self {
+        self.tupdate = Some(tupdate);
+        self
+    }
+
+    /// Sets the weight of the deviation from the target delay, between 0 and 32.
+    pub fn alpha(&mut self, alpha: u32) -> &mut Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the weight of the delay trend, between 0 and 32.
+    pub fn beta(&mut self, beta: u32) -> &mut Self {
+        self.beta = beta;
+        self
+    }
+
+    /// Sets whether packets are ECN marked instead of dropped.
+    pub fn ecn(&mut self, ecn: bool) -> &mut Self {
+        self.ecn = ecn;
+        self
+    }
+
+    /// Sets whether the drop probability is scaled by the packet size.
+    pub fn bytemode(&mut self, bytemode: bool) -> &mut Self {
+        self.bytemode = bytemode;
+        self
+    }
+
+    /// Sets whether the queueing delay is estimated from the dequeue rate
+    /// rather than from timestamps.
+    pub fn dq_rate_estimator(&mut self, dq_rate_estimator: bool) -> &mut Self {
+        self.dq_rate_estimator = dq_rate_estimator;
+        self
+    }
+
+    /// Builds the qdisc options.
+    pub fn build(&self) -> Result<Pie, Error> {
+        check_alpha_beta(self.alpha, self.beta)?;
+        let usecs = |time: Option<Duration>| time.map_or(Ok(0), time_to_usecs);
+        Ok(Pie {
+            target: usecs(self.target)?,
+            limit: self.limit,
+            tupdate: usecs(self.tupdate)?,
+            alpha: self.alpha,
+            beta: self.beta,
+            ecn: self.ecn as u32,
+            bytemode: self.bytemode as u32,
+            dq_rate_estimator: self.dq_rate_estimator as u32,
+        })
+    }
+}
+
+pub(crate) fn check_alpha_beta(alpha: u32, beta: u32) -> Result<(), Error> {
+    if alpha > PIE_MAX_ALPHA_BETA || beta > PIE_MAX_ALPHA_BETA {
+        return Err(Error::Encode(format!(
+            "alpha and beta must be between 0 and {PIE_MAX_ALPHA_BETA}"
+        )));
+    }
+    Ok(())
+}
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_pie_xstats`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PieXStats {
+    /// Current drop probability, scaled to `u64::MAX >> 8`.
+    pub prob: u64,
+    /// Current queueing delay, in microseconds.
+    pub delay: u32,
+    /// Average dequeue rate, in bytes per second.
+    pub avg_dq_rate: u32,
+    /// Whether the dequeue rate is used to estimate the delay.
+    pub dq_rate_estimating: u32,
+    pub packets_in: u32,
+    pub dropped: u32,
+    pub overlimit: u32,
+    pub maxq: u32,
+    pub ecn_mark: u32,
+}
+
+impl PieXStats {
+    pub fn new(bytes: &[u8]) -> Result<Self, Error> {
+        unmarshal_pie_xstats(bytes)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaPie {
+    #[default]
+    Unspec = 0,
+    Target,
+    Limit,
+    Tupdate,
+    Alpha,
+    Beta,
+    Ecn,
+    Bytemode,
+    DqRateEstimator,
+    Max,
+}
+
+impl From<u16> for TcaPie {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaPie::Unspec,
+            1 => TcaPie::Target,
+            2 => TcaPie::Limit,
+            3 => TcaPie::Tupdate,
+            4 => TcaPie::Alpha,
+            5 => TcaPie::Beta,
+            6 => TcaPie::Ecn,
+            7 => TcaPie::Bytemode,
+            8 => TcaPie::DqRateEstimator,
+            _ => TcaPie::Max,
+        }
+    }
+}
+
+fn unmarshal_pie(opts: Vec<TcOption>) -> Pie {
+    let mut pie = Pie::default();
+
+    for opt in opts {
+        let kind = TcaPie::from(opt.kind);
+        if opt.bytes.len() < 4 {
+            // TODO: log error
+            continue;
+        }
+        let value = u32::from_ne_bytes(opt.bytes[..4].try_into().unwrap());
+        match kind {
+            TcaPie::Target => pie.target = value,
+            TcaPie::Limit => pie.limit = value,
+            TcaPie::Tupdate => pie.tupdate = value,
+            TcaPie::Alpha => pie.alpha = value,
+            TcaPie::Beta => pie.beta = value,
+            TcaPie::Ecn => pie.ecn = value,
+            TcaPie::Bytemode => pie.bytemode = value,
+            TcaPie::DqRateEstimator => pie.dq_rate_estimator = value,
+            _ => (),
+        }
+    }
+
+    pie
+}
+
+fn marshal_pie(pie: &Pie) -> Vec<TcOption> {
+    let values = [
+        (TcaPie::Target, pie.target),
+        (TcaPie::Limit, pie.limit),
+        (TcaPie::Tupdate, pie.tupdate),
+        (TcaPie::Alpha, pie.alpha),
+        (TcaPie::Beta, pie.beta),
+        (TcaPie::Ecn, pie.ecn),
+        (TcaPie::Bytemode, pie.bytemode),
+        (TcaPie::DqRateEstimator, pie.dq_rate_estimator),
+    ];
+
+    values
+        .into_iter()
+        .filter(|(kind, value)| {
+            *value != 0
+                || matches!(
+                    kind,
+                    TcaPie::Ecn | TcaPie::Bytemode | TcaPie::DqRateEstimator
+                )
+        })
+        .map(|(kind, value)| TcOption {
+            kind: kind as u16,
+            bytes: value.to_ne_bytes().to_vec(),
+        })
+        .collect()
+}
+
+fn unmarshal_pie_xstats(bytes: &[u8]) -> Result<PieXStats, Error> {
+    if bytes.len() < 40 {
+        return Err(Error::Parse("Pie XStats requires 40 bytes".to_string()));
+    }
+    bincode::deserialize(bytes).map_err(|e| Error::Parse(e.to_string()))
+}
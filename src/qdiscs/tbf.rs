@@ -0,0 +1,373 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rate::{calc_rtable, calc_xmittime, LinkLayer, TICKS_PER_USEC, TIME_UNITS_PER_SEC};
+use crate::{errors::Error, types::*};
+
+/// Options of the `tbf` qdisc, defined in `net/sched/sch_tbf.c`.
+///
+/// Rates are in bytes per second. The rates above 32 bits are reported in `rate64`
+/// and `prate64`, `parms.rate.rate` and `parms.peakrate.rate` being capped to `u32::MAX`.
+/// The kernel only reports `parms`, `rate64` and `prate64`.
+#[derive(Default, Debug, PartialEq)]
+pub struct Tbf {
+    pub parms: Option<TbfQopt>,
+    pub rtab: Vec<u8>,
+    pub ptab: Vec<u8>,
+    pub rate64: Option<u64>,
+    pub prate64: Option<u64>,
+    /// Size of the bucket in bytes, preferred by the kernel over `parms.buffer`.
+    pub burst: Option<u32>,
+    /// Size of the peak rate bucket in bytes, preferred by the kernel over `parms.mtu`.
+    pub pburst: Option<u32>,
+}
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_tbf_qopt`.
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TbfQopt {
+    pub rate: RateSpec,
+    pub peakrate: RateSpec,
+    /// Maximum number of bytes queued.
+    pub limit: u32,
+    /// Size of the bucket, in psched ticks at `rate`.
+    pub buffer: u32,
+    /// Size of the peak rate bucket, in psched ticks at `peakrate`.
+    pub mtu: u32,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub enum TcaTbf {
+    #[default]
+    Unspec = 0,
+    Parms,
+    Rtab,
+    Ptab,
+    Rate64,
+    Prate64,
+    Burst,
+    Pburst,
+    Pad,
+    Max,
+}
+
+impl From<u16> for TcaTbf {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaTbf::Unspec,
+            1 => TcaTbf::Parms,
+            2 => TcaTbf::Rtab,
+            3 => TcaTbf::Ptab,
+            4 => TcaTbf::Rate64,
+            5 => TcaTbf::Prate64,
+            6 => TcaTbf::Burst,
+            7 => TcaTbf::Pburst,
+            8 => TcaTbf::Pad,
+            _ => TcaTbf::Max,
+        }
+    }
+}
+
+impl Tbf {
+    pub fn new(opts: Vec<TcOption>) -> Self {
+        unmarshal_tbf(opts)
+    }
+
+    /// Encodes the qdisc into `TCA_TBF_*` options.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_tbf(self)
+    }
+
+    /// Returns the rate in bytes per second, including rates above 32 bits.
+    pub fn rate(&self) -> Option<u64> {
+        self.rate64
+            .or_else(|| self.parms.as_ref().map(|parms| parms.rate.rate as u64))
+            .filter(|rate| *rate != 0)
+    }
+
+    /// Returns the peak rate in bytes per second, including rates above 32 bits.
+    pub fn peakrate(&self) -> Option<u64> {
+        self.prate64
+            .or_else(|| self.parms.as_ref().map(|parms| parms.peakrate.rate as u64))
+            .filter(|rate| *rate != 0)
+    }
+}
+
+/// Size of an ethernet frame with a 1500 bytes MTU,
+/// the smallest burst accepted by `TbfBuilder` when no MTU is given.
+const ETH_FRAME_LEN: u32 = 1514;
+
+/// `TbfBuilder` computes the options of a `tbf` qdisc the way `tc qdisc add ... tbf` does,
+/// including the rate tables, the time based buffer and the limit derived from the latency.
+///
+/// Rates are in bytes per second and bursts in bytes.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use netlink_tc::qdiscs::TbfBuilder;
+///
+/// // tbf rate 1mbit burst 10kb latency 50ms
+/// let tbf = TbfBuilder::new(125_000, 10_000)
+///     .latency(Duration::from_millis(50))
+///     .build()
+///     .unwrap();
+/// assert_eq!(tbf.parms.unwrap().limit, 16_250);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TbfBuilder {
+    rate: u64,
+    burst: u32,
+    limit: Option<u32>,
+    latency: Option<Duration>,
+    peakrate: Option<u64>,
+    mtu: u32,
+    mpu: u16,
+    overhead: u16,
+    cell_log: Option<u8>,
+    linklayer: LinkLayer,
+}
+
+impl TbfBuilder {
+    /// Creates a builder for a qdisc shaping traffic to `rate` bytes per second,
+    /// with a bucket of `burst` bytes.
+    /// Either a limit or a latency must be set before building.
+    pub fn new(rate: u64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the maximum number of bytes queued.
+    pub fn limit(&mut self, limit: u32) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the maximum time a packet can be queued, from which the limit is computed.
+    pub fn latency(&mut self, latency: Duration) -> &mut Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Sets the maximum rate in bytes per second at which the bucket is emptied,
+    /// which requires the MTU.
+    pub fn peakrate(&mut self, peakrate: u64) -> &mut Self {
+        self.peakrate = Some(peakrate);
+        self
+    }
+
+    /// Sets the largest packet size, also the size of the peak rate bucket.
+    /// The rate tables default to an MTU of 2047.
+    pub fn mtu(&mut self, mtu: u32) -> &mut Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Sets the minimum packet unit, packets smaller than `mpu` are accounted as `mpu` bytes.
+    pub fn mpu(&mut self, mpu: u16) -> &mut Self {
+        self.mpu = mpu;
+        self
+    }
+
+    /// Sets the per packet overhead added by the kernel when computing the rate.
+    pub fn overhead(&mut self, overhead: u16) -> &mut Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Sets the cell log of the rate tables, derived from the MTU by default.
+    pub fn cell_log(&mut self, cell_log: u8) -> &mut Self {
+        self.cell_log = Some(cell_log);
+        self
+    }
+
+    /// Sets the link layer used to compute the size of packets on the wire.
+    pub fn linklayer(&mut self, linklayer: LinkLayer) -> &mut Self {
+        self.linklayer = linklayer;
+        self
+    }
+
+    /// Builds the qdisc options, failing on the combinations `tc` or the kernel refuse.
+    pub fn build(&self) -> Result<Tbf, Error> {
+        if self.rate == 0 {
+            return Err(Error::Encode("tbf requires a rate".to_string()));
+        }
+        let max_packet = if self.mtu == 0 {
+            ETH_FRAME_LEN
+        } else {
+            self.mtu
+        };
+        if self.burst < max_packet {
+            return Err(Error::Encode(format!(
+                "tbf burst {} is smaller than the MTU {max_packet}",
+                self.burst
+            )));
+        }
+        if buffer_ticks(self.rate, self.burst) > u32::MAX as f64 {
+            return Err(Error::Encode(format!(
+                "tbf burst {} takes too long to send at {} bytes per second",
+                self.burst, self.rate
+            )));
+        }
+        if let Some(cell_log) = self.cell_log.filter(|cell_log| *cell_log >= 32) {
+            return Err(Error::Encode(format!(
+                "tbf cell_log {cell_log} must be below 32"
+            )));
+        }
+        if let Some(peakrate) = self.peakrate {
+            if self.mtu == 0 {
+                return Err(Error::Encode("tbf peakrate requires an MTU".to_string()));
+            }
+            if peakrate <= self.rate {
+                return Err(Error::Encode(format!(
+                    "tbf peakrate {peakrate} must be greater than the rate {}",
+                    self.rate
+                )));
+            }
+        }
+        let limit = match (self.limit, self.latency) {
+            (Some(limit), None) => limit,
+            (None, Some(latency)) => self.latency_limit(latency)?,
+            (Some(_), Some(_)) => {
+                return Err(Error::Encode(
+                    "tbf takes either a limit or a latency".to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(Error::Encode(
+                    "tbf requires a limit or a latency".to_string(),
+                ))
+            }
+        };
+
+        let mut parms = TbfQopt {
+            rate: self.rate_spec(self.rate),
+            limit,
+            buffer: calc_xmittime(self.rate, self.burst),
+            ..Default::default()
+        };
+        let rtab = calc_rtable(
+            &mut parms.rate,
+            self.rate,
+            self.cell_log,
+            self.mtu,
+            self.linklayer,
+        );
+        let mut tbf = Tbf {
+            rtab,
+            rate64: (self.rate > u32::MAX as u64).then_some(self.rate),
+            burst: Some(self.burst),
+            ..Default::default()
+        };
+        if let Some(peakrate) = self.peakrate {
+            parms.peakrate = self.rate_spec(peakrate);
+            tbf.ptab = calc_rtable(
+                &mut parms.peakrate,
+                peakrate,
+                self.cell_log,
+                self.mtu,
+                self.linklayer,
+            );
+            parms.mtu = calc_xmittime(peakrate, self.mtu);
+            tbf.prate64 = (peakrate > u32::MAX as u64).then_some(peakrate);
+            tbf.pburst = Some(self.mtu);
+        }
+        tbf.parms = Some(parms);
+
+        Ok(tbf)
+    }
+
+    /// Returns the number of bytes that can be queued for `latency`, like `tc`:
+    /// what the rate drains in that time plus the bucket,
+    /// or the same at the peak rate with the peak bucket when lower.
+    fn latency_limit(&self, latency: Duration) -> Result<u32, Error> {
+        let latency = latency.as_micros() as f64 / TIME_UNITS_PER_SEC;
+        let mut limit = self.rate as f64 * latency + self.burst as f64;
+        if let Some(peakrate) = self.peakrate {
+            limit = limit.min(peakrate as f64 * latency + self.mtu as f64);
+        }
+        if limit > u32::MAX as f64 {
+            return Err(Error::Encode(
+                "tbf latency is too large for the rate".to_string(),
+            ));
+        }
+        Ok(limit as u32)
+    }
+
+    fn rate_spec(&self, rate: u64) -> RateSpec {
+        RateSpec {
+            rate: rate.min(u32::MAX as u64) as u32,
+            overhead: self.overhead,
+            mpu: self.mpu,
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns the time in ticks needed to send `size` bytes at `rate` bytes per second,
+/// before truncation to the 32 bits of `tc_tbf_qopt`.
+fn buffer_ticks(rate: u64, size: u32) -> f64 {
+    TIME_UNITS_PER_SEC * (size as f64 / rate as f64) * TICKS_PER_USEC
+}
+
+fn unmarshal_tbf(opts: Vec<TcOption>) -> Tbf {
+    let mut tbf = Tbf::default();
+
+    for opt in opts {
+        let kind = TcaTbf::from(opt.kind);
+        match kind {
+            TcaTbf::Parms => tbf.parms = bincode::deserialize(&opt.bytes).ok(),
+            TcaTbf::Rtab => tbf.rtab = opt.bytes,
+            TcaTbf::Ptab => tbf.ptab = opt.bytes,
+            TcaTbf::Rate64 => tbf.rate64 = unmarshal_u64_value(&opt.bytes),
+            TcaTbf::Prate64 => tbf.prate64 = unmarshal_u64_value(&opt.bytes),
+            TcaTbf::Burst => tbf.burst = unmarshal_u32_value(&opt.bytes),
+            TcaTbf::Pburst => tbf.pburst = unmarshal_u32_value(&opt.bytes),
+            _ => (),
+        }
+    }
+
+    tbf
+}
+
+fn marshal_tbf(tbf: &Tbf) -> Result<Vec<TcOption>, Error> {
+    let mut opts = Vec::new();
+
+    let parms = tbf
+        .parms
+        .as_ref()
+        .ok_or_else(|| Error::Encode("tbf requires parms".to_string()))?;
+    opts.push(TcOption {
+        kind: TcaTbf::Parms as u16,
+        bytes: bincode::serialize(parms).map_err(|e| Error::Encode(e.to_string()))?,
+    });
+    for (kind, value) in [(TcaTbf::Burst, tbf.burst), (TcaTbf::Pburst, tbf.pburst)] {
+        if let Some(value) = value {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: value.to_ne_bytes().to_vec(),
+            });
+        }
+    }
+    for (kind, value) in [(TcaTbf::Rate64, tbf.rate64), (TcaTbf::Prate64, tbf.prate64)] {
+        if let Some(value) = value {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: value.to_ne_bytes().to_vec(),
+            });
+        }
+    }
+    for (kind, table) in [(TcaTbf::Rtab, &tbf.rtab), (TcaTbf::Ptab, &tbf.ptab)] {
+        if !table.is_empty() {
+            opts.push(TcOption {
+                kind: kind as u16,
+                bytes: table.clone(),
+            });
+        }
+    }
+
+    Ok(opts)
+}
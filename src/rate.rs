@@ -0,0 +1,151 @@
+//! Rate table helpers, ported from `tc/tc_core.c` in iproute2.
+
+use std::time::Duration;
+
+use crate::errors::Error;
+use crate::stab::SizeSpec;
+use crate::types::RateSpec;
+
+/// Number of microseconds in a second, `TIME_UNITS_PER_SEC` in iproute2.
+pub const TIME_UNITS_PER_SEC: f64 = 1_000_000.0;
+
+/// Number of psched ticks per microsecond.
+/// The kernel has used 64ns ticks (`PSCHED_SHIFT` of 6) since 2.6.31,
+/// which is what `/proc/net/psched` reports.
+pub const TICKS_PER_USEC: f64 = 1000.0 / 64.0;
+
+/// Number of entries in a rate table.
+pub const RTAB_SIZE: usize = 256;
+
+const ATM_CELL_SIZE: u32 = 53;
+const ATM_CELL_PAYLOAD: u32 = 48;
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `enum tc_link_layer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkLayer {
+    Unaware = 0,
+    #[default]
+    Ethernet,
+    Atm,
+}
+
+impl From<u8> for LinkLayer {
+    fn from(v: u8) -> Self {
+        match v {
+            1 => LinkLayer::Ethernet,
+            2 => LinkLayer::Atm,
+            _ => LinkLayer::Unaware,
+        }
+    }
+}
+
+/// Converts a duration in microseconds to psched ticks.
+pub fn time_to_ticks(time: f64) -> u32 {
+    (time * TICKS_PER_USEC) as u32
+}
+
+/// Converts psched ticks to a duration in microseconds.
+pub fn ticks_to_time(ticks: u32) -> f64 {
+    ticks as f64 / TICKS_PER_USEC
+}
+
+/// Converts `time` to microseconds, the unit of the time options of most qdiscs.
+pub fn time_to_usecs(time: Duration) -> Result<u32, Error> {
+    time.as_micros()
+        .try_into()
+        .map_err(|_| Error::Encode(format!("{time:?} does not fit in 32-bit microseconds")))
+}
+
+/// Returns the time in ticks needed to send `size` bytes at `rate` bytes per second.
+pub fn calc_xmittime(rate: u64, size: u32) -> u32 {
+    time_to_ticks(TIME_UNITS_PER_SEC * (size as f64 / rate as f64))
+}
+
+/// Returns the number of bytes that can be sent at `rate` bytes per second in `ticks`.
+pub fn calc_xmitsize(rate: u64, ticks: u32) -> u32 {
+    (rate as f64 * ticks_to_time(ticks) / TIME_UNITS_PER_SEC) as u32
+}
+
+/// Returns the size of a packet of `size` bytes on the wire, accounting for the minimum
+/// packet unit and the link layer.
+pub fn adjust_size(size: u32, mpu: u32, linklayer: LinkLayer) -> u32 {
+    let size = size.max(mpu);
+    match linklayer {
+        LinkLayer::Atm => size.div_ceil(ATM_CELL_PAYLOAD) * ATM_CELL_SIZE,
+        _ => size,
+    }
+}
+
+/// Computes the rate table for `rate` bytes per second, like `tc_calc_rtable_64`.
+///
+/// `cell_log` is derived from `mtu` when not given, and `mtu` defaults to 2047 when zero.
+/// The table accounts for `spec.mpu` and `linklayer`; `spec.overhead` is applied by the kernel.
+/// `spec` is updated with the cell parameters used for the table.
+/// The table is returned in native byte order, ready to be sent as a `TCA_*_RTAB` option.
+pub fn calc_rtable(
+    spec: &mut RateSpec,
+    rate: u64,
+    cell_log: Option<u8>,
+    mtu: u32,
+    linklayer: LinkLayer,
+) -> Vec<u8> {
+    let mtu = if mtu == 0 { 2047 } else { mtu };
+    let cell_log = cell_log.unwrap_or_else(|| {
+        let mut cell_log = 0;
+        while (mtu >> cell_log) > 255 {
+            cell_log += 1;
+        }
+        cell_log
+    });
+
+    let mut rtab = Vec::with_capacity(RTAB_SIZE * 4);
+    for i in 0..RTAB_SIZE as u32 {
+        let size = adjust_size((i + 1) << cell_log, spec.mpu as u32, linklayer);
+        rtab.extend(calc_xmittime(rate, size).to_ne_bytes());
+    }
+
+    spec.cell_align = u16::MAX; // -1
+    spec.cell_log = cell_log;
+    spec.linklayer = linklayer as u8;
+    rtab
+}
+
+/// Computes the size table described by `spec`, like `tc_calc_size_table`.
+///
+/// `spec.mtu` and `spec.tsize` default to 2047 and 512 when zero, and the cell parameters
+/// of `spec` are updated for the table. No table is needed, and none is returned,
+/// for ethernet link layers without an MPU.
+pub fn calc_size_table(spec: &mut SizeSpec) -> Vec<u16> {
+    let linklayer = LinkLayer::from(spec.linklayer as u8);
+    if linklayer != LinkLayer::Atm && spec.mpu == 0 {
+        spec.mtu = 0;
+        spec.tsize = 0;
+        spec.cell_log = 0;
+        spec.cell_align = 0;
+        return Vec::new();
+    }
+
+    if spec.mtu == 0 {
+        spec.mtu = 2047;
+    }
+    if spec.tsize == 0 {
+        spec.tsize = 512;
+    }
+    spec.cell_log = 0;
+    while (spec.mtu >> spec.cell_log) > spec.tsize - 1 {
+        spec.cell_log += 1;
+    }
+
+    let table = loop {
+        let table: Vec<u32> = (0..spec.tsize)
+            .map(|i| adjust_size((i + 1) << spec.cell_log, spec.mpu, linklayer) >> spec.size_log)
+            .collect();
+        if table.iter().all(|size| *size <= u16::MAX as u32) {
+            break table;
+        }
+        spec.size_log += 1;
+    };
+
+    spec.cell_align = -1;
+    table.into_iter().map(|size| size as u16).collect()
+}
@@ -0,0 +1,487 @@
+use netlink_packet_core::{
+    NetlinkHeader, NetlinkMessage, NetlinkPayload, NLM_F_ACK, NLM_F_CREATE, NLM_F_EXCL,
+    NLM_F_REPLACE, NLM_F_REQUEST,
+};
+use netlink_packet_route::{tc as netlink_tc, RtnlMessage, TcHeader, TcMessage};
+use netlink_packet_utils::nla::DefaultNla;
+
+use crate::constants::{
+    BPF, CAKE, CLSACT, CODEL, ETH_P_ALL, FLOWER, FQ, FQ_CODEL, FQ_PIE, HTB, MATCHALL, PIE, TBF,
+    TC_H_CLSACT, TC_H_MAJ_MASK, TC_H_ROOT, U32,
+};
+use crate::errors::Error;
+use crate::handle::TcHandle;
+use crate::stab::Stab;
+use crate::types::{Class, Classifier, Estimator, QDisc, TcOption};
+
+/// Write operations supported on `tc` objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// Creates the object, failing if it already exists (`tc ... add`).
+    Add,
+    /// Modifies an existing object (`tc ... change`).
+    Change,
+    /// Creates the object or replaces an existing one (`tc ... replace`).
+    Replace,
+    /// Deletes the object (`tc ... del`).
+    Delete,
+}
+
+impl Operation {
+    /// Netlink flags sent along with the request.
+    pub fn flags(&self) -> u16 {
+        let flags = NLM_F_REQUEST | NLM_F_ACK;
+        match self {
+            Operation::Add => flags | NLM_F_CREATE | NLM_F_EXCL,
+            Operation::Change | Operation::Delete => flags,
+            Operation::Replace => flags | NLM_F_CREATE | NLM_F_REPLACE,
+        }
+    }
+}
+
+/// `QDiscRequest` builds `RTM_NEWQDISC` and `RTM_DELQDISC` requests for a queueing discipline.
+///
+/// # Example
+/// ```no_run
+/// use netlink_tc::{qdiscs::FqCodel, types::QDisc, QDiscRequest};
+///
+/// let fq_codel = FqCodel {
+///     limit: 10240,
+///     ..Default::default()
+/// };
+/// QDiscRequest::new(1, QDisc::FqCodel(fq_codel)).replace().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct QDiscRequest {
+    index: i32,
+    handle: u32,
+    parent: u32,
+    qdisc: QDisc,
+    stab: Option<Stab>,
+    estimator: Option<Estimator>,
+}
+
+impl QDiscRequest {
+    /// Creates a request for `qdisc` on the interface with index `index`.
+    /// The qdisc is attached to the root of the interface with a kernel assigned handle,
+    /// except for `clsact` which is attached to its dedicated parent.
+    pub fn new(index: i32, qdisc: QDisc) -> Self {
+        let (handle, parent) = match qdisc {
+            QDisc::Clsact(_) => (TC_H_CLSACT & TC_H_MAJ_MASK, TC_H_CLSACT),
+            _ => (0, TC_H_ROOT),
+        };
+        Self {
+            index,
+            handle,
+            parent,
+            qdisc,
+            stab: None,
+            estimator: None,
+        }
+    }
+
+    /// Sets the handle of the qdisc, see `constants::tc_handle`.
+    pub fn handle(&mut self, handle: u32) -> &mut Self {
+        self.handle = handle;
+        self
+    }
+
+    /// Sets the parent of the qdisc.
+    pub fn parent(&mut self, parent: u32) -> &mut Self {
+        self.parent = parent;
+        self
+    }
+
+    /// Sets the size table the qdisc uses to account packets, see `stab::StabBuilder`.
+    pub fn stab(&mut self, stab: Stab) -> &mut Self {
+        self.stab = Some(stab);
+        self
+    }
+
+    /// Attaches a rate estimator to the qdisc, reported in `Stats2::rate_est`.
+    pub fn estimator(&mut self, estimator: Estimator) -> &mut Self {
+        self.estimator = Some(estimator);
+        self
+    }
+
+    /// Builds the netlink message for `operation` without sending it.
+    pub fn message(&self, operation: Operation) -> Result<NetlinkMessage<RtnlMessage>, Error> {
+        let options = match operation {
+            Operation::Delete => None,
+            _ => qdisc_options(&self.qdisc)?,
+        };
+        let mut message = tc_message(
+            self.index,
+            self.handle,
+            self.parent,
+            qdisc_kind(&self.qdisc),
+            options,
+        );
+        if let Some(stab) = self
+            .stab
+            .as_ref()
+            .filter(|_| operation != Operation::Delete)
+        {
+            message.nlas.push(netlink_tc::Nla::Stab(stab.to_bytes()?));
+        }
+        if let Some(estimator) = self.estimator.filter(|_| operation != Operation::Delete) {
+            message.nlas.push(estimator_nla(&estimator)?);
+        }
+        let message = match operation {
+            Operation::Delete => RtnlMessage::DelQueueDiscipline(message),
+            _ => RtnlMessage::NewQueueDiscipline(message),
+        };
+        Ok(netlink_message(message, operation))
+    }
+
+    /// Sends the request for `operation` over a new `TcHandle`
+    /// and waits for the kernel to acknowledge it.
+    pub fn send(&self, operation: Operation) -> Result<(), Error> {
+        TcHandle::new()?.request(self.message(operation)?)
+    }
+
+    /// Creates the qdisc, failing if one already exists at the same parent.
+    pub fn add(&self) -> Result<(), Error> {
+        self.send(Operation::Add)
+    }
+
+    /// Changes the options of an existing qdisc.
+    pub fn change(&self) -> Result<(), Error> {
+        self.send(Operation::Change)
+    }
+
+    /// Creates the qdisc or replaces the existing one at the same parent.
+    pub fn replace(&self) -> Result<(), Error> {
+        self.send(Operation::Replace)
+    }
+
+    /// Deletes the qdisc.
+    pub fn delete(&self) -> Result<(), Error> {
+        self.send(Operation::Delete)
+    }
+}
+
+/// `ClassRequest` builds `RTM_NEWTCLASS` and `RTM_DELTCLASS` requests for a traffic class.
+///
+/// # Example
+/// ```no_run
+/// use netlink_tc::{class::HtbClassBuilder, constants::tc_handle, types::Class, ClassRequest};
+///
+/// // 1:10 under the root HTB qdisc 1:, shaped to 1mbit
+/// let htb = HtbClassBuilder::new(125_000).build().unwrap();
+/// ClassRequest::new(1, tc_handle(1, 0x10), Class::Htb(htb))
+///     .add()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ClassRequest {
+    index: i32,
+    handle: u32,
+    parent: u32,
+    class: Class,
+    estimator: Option<Estimator>,
+}
+
+impl ClassRequest {
+    /// Creates a request for `class` with the class id `handle` on the interface with index `index`.
+    /// The class is attached to the qdisc with the same major number as `handle`.
+    pub fn new(index: i32, handle: u32, class: Class) -> Self {
+        Self {
+            index,
+            handle,
+            parent: handle & TC_H_MAJ_MASK,
+            class,
+            estimator: None,
+        }
+    }
+
+    /// Sets the parent of the class, either a qdisc or another class.
+    pub fn parent(&mut self, parent: u32) -> &mut Self {
+        self.parent = parent;
+        self
+    }
+
+    /// Attaches a rate estimator to the class, reported in `Stats2::rate_est`.
+    pub fn estimator(&mut self, estimator: Estimator) -> &mut Self {
+        self.estimator = Some(estimator);
+        self
+    }
+
+    /// Builds the netlink message for `operation` without sending it.
+    pub fn message(&self, operation: Operation) -> Result<NetlinkMessage<RtnlMessage>, Error> {
+        let options = match operation {
+            Operation::Delete => None,
+            _ => class_options(&self.class)?,
+        };
+        let mut message = tc_message(
+            self.index,
+            self.handle,
+            self.parent,
+            class_kind(&self.class),
+            options,
+        );
+        if let Some(estimator) = self.estimator.filter(|_| operation != Operation::Delete) {
+            message.nlas.push(estimator_nla(&estimator)?);
+        }
+        let message = match operation {
+            Operation::Delete => RtnlMessage::DelTrafficClass(message),
+            _ => RtnlMessage::NewTrafficClass(message),
+        };
+        Ok(netlink_message(message, operation))
+    }
+
+    /// Sends the request for `operation` over a new `TcHandle`
+    /// and waits for the kernel to acknowledge it.
+    pub fn send(&self, operation: Operation) -> Result<(), Error> {
+        TcHandle::new()?.request(self.message(operation)?)
+    }
+
+    /// Creates the class, failing if it already exists.
+    pub fn add(&self) -> Result<(), Error> {
+        self.send(Operation::Add)
+    }
+
+    /// Changes the options of an existing class.
+    pub fn change(&self) -> Result<(), Error> {
+        self.send(Operation::Change)
+    }
+
+    /// Creates the class or replaces the existing one.
+    pub fn replace(&self) -> Result<(), Error> {
+        self.send(Operation::Replace)
+    }
+
+    /// Deletes the class.
+    pub fn delete(&self) -> Result<(), Error> {
+        self.send(Operation::Delete)
+    }
+}
+
+/// `FilterRequest` builds `RTM_NEWTFILTER` and `RTM_DELTFILTER` requests for a filter.
+///
+/// # Example
+/// ```no_run
+/// use netlink_tc::{filter, qdiscs::ClsactHook, types::Classifier, FilterRequest};
+///
+/// // tc filter replace dev eth0 ingress prio 1 handle 1 bpf da pinned /sys/fs/bpf/prog
+/// let prog = filter::open_pinned("/sys/fs/bpf/prog").unwrap();
+/// # use std::os::fd::AsRawFd;
+/// let bpf = filter::Bpf::with_fd(prog.as_raw_fd(), "prog");
+/// FilterRequest::new(1, ClsactHook::Ingress.parent(), Classifier::Bpf(bpf))
+///     .priority(1)
+///     .handle(1)
+///     .replace()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FilterRequest {
+    index: i32,
+    handle: u32,
+    parent: u32,
+    priority: u16,
+    protocol: u16,
+    chain: Option<u32>,
+    classifier: Classifier,
+}
+
+impl FilterRequest {
+    /// Creates a request for a filter running `classifier`, attached to `parent`
+    /// on the interface with index `index`.
+    /// The filter matches all protocols, and the kernel assigns its priority and handle.
+    pub fn new(index: i32, parent: u32, classifier: Classifier) -> Self {
+        Self {
+            index,
+            handle: 0,
+            parent,
+            priority: 0,
+            protocol: ETH_P_ALL,
+            chain: None,
+            classifier,
+        }
+    }
+
+    /// Sets the handle of the filter, required to replace or delete a single filter.
+    pub fn handle(&mut self, handle: u32) -> &mut Self {
+        self.handle = handle;
+        self
+    }
+
+    /// Sets the priority of the filter, lower values run first.
+    pub fn priority(&mut self, priority: u16) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the protocol matched by the filter, e.g. `ETH_P_IP`, in host byte order.
+    pub fn protocol(&mut self, protocol: u16) -> &mut Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets the chain of the filter.
+    pub fn chain(&mut self, chain: u32) -> &mut Self {
+        self.chain = Some(chain);
+        self
+    }
+
+    /// Builds the netlink message for `operation` without sending it.
+    pub fn message(&self, operation: Operation) -> Result<NetlinkMessage<RtnlMessage>, Error> {
+        let options = match operation {
+            Operation::Delete => None,
+            _ => Some(classifier_options(&self.classifier)?),
+        };
+        let mut message = tc_message(
+            self.index,
+            self.handle,
+            self.parent,
+            classifier_kind(&self.classifier),
+            options,
+        );
+        message.header.info = ((self.priority as u32) << 16) | self.protocol.to_be() as u32;
+        if let Some(chain) = self.chain {
+            message
+                .nlas
+                .push(netlink_tc::Nla::Chain(chain.to_ne_bytes().to_vec()));
+        }
+        let message = match operation {
+            Operation::Delete => RtnlMessage::DelTrafficFilter(message),
+            _ => RtnlMessage::NewTrafficFilter(message),
+        };
+        Ok(netlink_message(message, operation))
+    }
+
+    /// Sends the request for `operation` over a new `TcHandle`
+    /// and waits for the kernel to acknowledge it.
+    pub fn send(&self, operation: Operation) -> Result<(), Error> {
+        TcHandle::new()?.request(self.message(operation)?)
+    }
+
+    /// Creates the filter, failing if one already exists with the same handle.
+    pub fn add(&self) -> Result<(), Error> {
+        self.send(Operation::Add)
+    }
+
+    /// Changes the options of an existing filter.
+    pub fn change(&self) -> Result<(), Error> {
+        self.send(Operation::Change)
+    }
+
+    /// Creates the filter or atomically replaces the existing one with the same handle.
+    pub fn replace(&self) -> Result<(), Error> {
+        self.send(Operation::Replace)
+    }
+
+    /// Deletes the filter, or all the filters at its priority when no handle is set.
+    pub fn delete(&self) -> Result<(), Error> {
+        self.send(Operation::Delete)
+    }
+}
+
+fn tc_message(
+    index: i32,
+    handle: u32,
+    parent: u32,
+    kind: &str,
+    options: Option<Vec<TcOption>>,
+) -> TcMessage {
+    let mut nlas = vec![netlink_tc::Nla::Kind(kind.to_string())];
+    if let Some(options) = options {
+        nlas.push(netlink_tc::Nla::Options(to_nl_options(options)));
+    }
+
+    let header = TcHeader {
+        index,
+        handle,
+        parent,
+        ..Default::default()
+    };
+    TcMessage::from_parts(header, nlas)
+}
+
+fn estimator_nla(estimator: &Estimator) -> Result<netlink_tc::Nla, Error> {
+    let bytes = bincode::serialize(estimator).map_err(|e| Error::Encode(e.to_string()))?;
+    Ok(netlink_tc::Nla::Rate(bytes))
+}
+
+fn netlink_message(message: RtnlMessage, operation: Operation) -> NetlinkMessage<RtnlMessage> {
+    let mut nl_hdr = NetlinkHeader::default();
+    nl_hdr.flags = operation.flags();
+    let mut packet = NetlinkMessage::new(nl_hdr, NetlinkPayload::from(message));
+    packet.finalize();
+    packet
+}
+
+fn qdisc_kind(qdisc: &QDisc) -> &'static str {
+    match qdisc {
+        QDisc::Cake(_) => CAKE,
+        QDisc::Codel(_) => CODEL,
+        QDisc::Fq(_) => FQ,
+        QDisc::FqCodel(_) => FQ_CODEL,
+        QDisc::FqPie(_) => FQ_PIE,
+        QDisc::Clsact(_) => CLSACT,
+        QDisc::Htb(_) => HTB,
+        QDisc::Pie(_) => PIE,
+        QDisc::Tbf(_) => TBF,
+    }
+}
+
+fn qdisc_options(qdisc: &QDisc) -> Result<Option<Vec<TcOption>>, Error> {
+    let options = match qdisc {
+        QDisc::Cake(cake) => Some(cake.to_options()),
+        QDisc::Codel(codel) => Some(codel.to_options()),
+        QDisc::Fq(fq) => Some(fq.to_options()?),
+        QDisc::FqCodel(fq_codel) => Some(fq_codel.to_options()),
+        QDisc::FqPie(fq_pie) => Some(fq_pie.to_options()),
+        QDisc::Clsact(_) => None,
+        QDisc::Htb(htb) => Some(htb.to_options()?),
+        QDisc::Pie(pie) => Some(pie.to_options()),
+        QDisc::Tbf(tbf) => Some(tbf.to_options()?),
+    };
+    Ok(options)
+}
+
+fn class_kind(class: &Class) -> &'static str {
+    match class {
+        Class::FqCodel(_) => FQ_CODEL,
+        Class::Htb(_) => HTB,
+    }
+}
+
+fn class_options(class: &Class) -> Result<Option<Vec<TcOption>>, Error> {
+    let options = match class {
+        Class::FqCodel(_) => {
+            return Err(Error::Encode(
+                "fq_codel flows can not be created".to_string(),
+            ))
+        }
+        Class::Htb(htb) => Some(htb.to_options()?),
+    };
+    Ok(options)
+}
+
+fn classifier_kind(classifier: &Classifier) -> &'static str {
+    match classifier {
+        Classifier::Bpf(_) => BPF,
+        Classifier::Flower(_) => FLOWER,
+        Classifier::Matchall(_) => MATCHALL,
+        Classifier::U32(_) => U32,
+    }
+}
+
+fn classifier_options(classifier: &Classifier) -> Result<Vec<TcOption>, Error> {
+    match classifier {
+        Classifier::Bpf(bpf) => bpf.to_options(),
+        Classifier::Matchall(matchall) => matchall.to_options(),
+        _ => Err(Error::Encode(format!(
+            "Creating {} filters is not supported",
+            classifier_kind(classifier)
+        ))),
+    }
+}
+
+fn to_nl_options(options: Vec<TcOption>) -> Vec<netlink_tc::TcOpt> {
+    options
+        .into_iter()
+        .map(|opt| netlink_tc::TcOpt::Other(DefaultNla::new(opt.kind, opt.bytes)))
+        .collect()
+}
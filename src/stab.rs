@@ -0,0 +1,194 @@
+//! Size tables, which make qdiscs account packets by their size on the wire
+//! (`tc qdisc add ... stab linklayer atm overhead 10 ...`).
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Error;
+use crate::rate::{calc_size_table, LinkLayer};
+use crate::types::{marshal_nested, TcOption};
+
+/// Size table of a qdisc, from the `TCA_STAB` attribute.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stab {
+    pub base: SizeSpec,
+    /// Size on the wire of packets up to `(i + 1) << base.cell_log` bytes,
+    /// shifted right by `base.size_log`.
+    /// The kernel only reports `TCA_STAB_BASE` in dumps, so this is empty for dumped qdiscs.
+    pub data: Vec<u16>,
+}
+
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_sizespec`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizeSpec {
+    pub cell_log: u8,
+    pub size_log: u8,
+    pub cell_align: i16,
+    pub overhead: i32,
+    /// `LinkLayer` of the interface.
+    pub linklayer: u32,
+    pub mpu: u32,
+    pub mtu: u32,
+    /// Number of entries in the table.
+    pub tsize: u32,
+}
+
+pub enum TcaStab {
+    Unspec = 0,
+    Base,
+    Data,
+    Max,
+}
+
+impl From<u16> for TcaStab {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaStab::Unspec,
+            1 => TcaStab::Base,
+            2 => TcaStab::Data,
+            _ => TcaStab::Max,
+        }
+    }
+}
+
+impl Stab {
+    pub fn new(opts: Vec<TcOption>) -> Result<Self, Error> {
+        unmarshal_stab(opts)
+    }
+
+    /// Returns the size the qdisc accounts for a packet of `len` bytes,
+    /// like `__qdisc_calculate_pkt_len` in the kernel.
+    /// Only the overhead is applied when the table has no data.
+    pub fn packet_len(&self, len: u32) -> u32 {
+        let base = &self.base;
+        let mut slot = len as i64 + base.cell_align as i64 + base.overhead as i64;
+        if slot < 0 {
+            slot = 0;
+        }
+        let slot = (slot >> base.cell_log) as usize;
+        let size = match self.data.len() {
+            0 => return (len as i64 + base.overhead as i64).max(1) as u32,
+            n if slot < n => self.data[slot] as u32,
+            n => self.data[n - 1] as u32 * (slot / n) as u32 + self.data[slot % n] as u32,
+        };
+        (size << base.size_log).max(1)
+    }
+
+    /// Encodes the table into the nested `TCA_STAB_*` attributes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        marshal_stab(self)
+    }
+}
+
+fn unmarshal_stab(opts: Vec<TcOption>) -> Result<Stab, Error> {
+    let mut stab = Stab::default();
+
+    for opt in opts {
+        match TcaStab::from(opt.kind) {
+            TcaStab::Base => {
+                stab.base = bincode::deserialize(&opt.bytes)
+                    .map_err(|e| Error::Parse(format!("Failed to parse TCA_STAB_BASE: {e}")))?
+            }
+            TcaStab::Data => {
+                stab.data = opt
+                    .bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                    .collect()
+            }
+            _ => (),
+        }
+    }
+
+    Ok(stab)
+}
+
+fn marshal_stab(stab: &Stab) -> Result<Vec<u8>, Error> {
+    if stab.data.len() != stab.base.tsize as usize {
+        return Err(Error::Encode(format!(
+            "Size table has {} entries but tsize is {}",
+            stab.data.len(),
+            stab.base.tsize
+        )));
+    }
+
+    let mut opts = vec![TcOption {
+        kind: TcaStab::Base as u16,
+        bytes: bincode::serialize(&stab.base).map_err(|e| Error::Encode(e.to_string()))?,
+    }];
+    if !stab.data.is_empty() {
+        opts.push(TcOption {
+            kind: TcaStab::Data as u16,
+            bytes: stab.data.iter().flat_map(|v| v.to_ne_bytes()).collect(),
+        });
+    }
+
+    Ok(marshal_nested(&opts))
+}
+
+/// `StabBuilder` computes a size table the way `tc qdisc add ... stab` does.
+///
+/// # Example
+/// ```
+/// use netlink_tc::{rate::LinkLayer, stab::StabBuilder};
+///
+/// // ADSL uplink with PPPoA/VC-mux encapsulation
+/// let stab = StabBuilder::new(LinkLayer::Atm).overhead(10).build();
+/// assert_eq!(stab.packet_len(100), 159);
+/// ```
+#[derive(Debug, Default)]
+pub struct StabBuilder {
+    linklayer: LinkLayer,
+    overhead: i32,
+    mpu: u32,
+    mtu: u32,
+    tsize: u32,
+}
+
+impl StabBuilder {
+    /// Creates a size table for `linklayer`.
+    pub fn new(linklayer: LinkLayer) -> Self {
+        Self {
+            linklayer,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the per packet overhead, which can be negative.
+    pub fn overhead(&mut self, overhead: i32) -> &mut Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Sets the minimum packet unit, packets smaller than `mpu` are accounted as `mpu` bytes.
+    pub fn mpu(&mut self, mpu: u32) -> &mut Self {
+        self.mpu = mpu;
+        self
+    }
+
+    /// Sets the largest packet size covered by the table, defaults to 2047.
+    pub fn mtu(&mut self, mtu: u32) -> &mut Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Sets the number of entries in the table, defaults to 512.
+    pub fn tsize(&mut self, tsize: u32) -> &mut Self {
+        self.tsize = tsize;
+        self
+    }
+
+    /// Builds the size table.
+    /// Only the overhead is sent, without a table, for ethernet link layers without an MPU.
+    pub fn build(&self) -> Stab {
+        let mut base = SizeSpec {
+            overhead: self.overhead,
+            linklayer: self.linklayer as u32,
+            mpu: self.mpu,
+            mtu: self.mtu,
+            tsize: self.tsize,
+            ..Default::default()
+        };
+        let data = calc_size_table(&mut base);
+        Stab { base, data }
+    }
+}
@@ -1,14 +1,36 @@
+use crate::action::{
+    ActBpf, Connmark, Csum, Ct, Ctinfo, Gact, Mirred, Mpls, Nat, Pedit, Police, Skbedit, Skbmod,
+    TcaAct, TunnelKey, Vlan,
+};
 use crate::class::{Htb, HtbXstats};
-use crate::constants::{CLSACT, FQ_CODEL, HTB};
+use crate::constants::{
+    BPF, CAKE, CLSACT, CODEL, CONNMARK, CSUM, CT, CTINFO, FLOWER, FQ, FQ_CODEL, FQ_PIE, GACT, HTB,
+    MATCHALL, MIRRED, MPLS, NAT, PEDIT, PIE, POLICE, SKBEDIT, SKBMOD, TBF, TUNNEL_KEY,
+    U32 as U32_KIND, VLAN,
+};
 use crate::errors::Error;
-use crate::qdiscs::{Clsact, FqCodel, FqCodelXStats};
+use crate::filter::{Bpf, Flower, Matchall, U32};
+use crate::qdiscs::{
+    Cake, CakeXStats, Clsact, Codel, CodelXStats, Fq, FqCodel, FqCodelClass, FqCodelClassXStats,
+    FqCodelXStats, FqPie, FqPieXStats, FqQdStats, Pie, PieXStats, Tbf,
+};
+use crate::stab::Stab;
 use crate::types::{
-    Attribute, Class, QDisc, Stats, Stats2, Tc, TcAttr, TcMessage, TcMsg, TcOption, TcStats2,
-    XStats,
+    unmarshal_nested, unmarshal_u32_value, unmarshal_u64_value, Action, ActionOptions, Attribute,
+    Class, Classifier, Filter, QDisc, Stats, Stats2, StatsBasic, StatsRateEst, Tc, TcAttr,
+    TcMessage, TcMsg, TcOption, TcStats2, TcaStats, XStats,
 };
 use crate::{ParseOptions, RtNetlinkMessage};
 
-fn get_qdiscs(message: TcMsg, classful: bool, opts: &ParseOptions) -> Result<Tc, Error> {
+/// The kind of object a `tc` message describes.
+#[derive(PartialEq)]
+enum TcType {
+    QDisc,
+    Class,
+    Filter,
+}
+
+fn get_qdiscs(message: TcMsg, tc_type: TcType, opts: &ParseOptions) -> Result<Tc, Error> {
     let tc = TcMessage {
         index: message.header.index as u32,
         handle: message.header.handle,
@@ -18,13 +40,24 @@ fn get_qdiscs(message: TcMsg, classful: bool, opts: &ParseOptions) -> Result<Tc,
 
     let mut tc_opts = Vec::new();
     let mut xstats = Vec::new();
+    let mut app = None;
+    let mut chain = None;
     for attr in &message.attrs {
         match attr {
             TcAttr::Kind(kind) => attribute.kind = kind.to_string(),
             TcAttr::Options(options) => tc_opts = options.to_vec(),
             TcAttr::Stats(bytes) => attribute.stats = parse_stats(bytes).ok(),
             TcAttr::Xstats(bytes) => xstats.extend(bytes.as_slice()),
-            TcAttr::Stats2(stats) => attribute.stats2 = parse_stats2(stats).ok(),
+            TcAttr::Stats2(stats) => {
+                attribute.stats2 = parse_stats2(stats).ok();
+                app = stats.iter().find_map(|stat| match stat {
+                    TcStats2::StatsApp(bytes) => Some(bytes.as_slice()),
+                    _ => None,
+                });
+            }
+            TcAttr::Stab(bytes) => attribute.stab = parse_stab(bytes).ok(),
+            TcAttr::HwOffload(offload) => attribute.hw_offload = *offload != 0,
+            TcAttr::Chain(bytes) if tc_type == TcType::Filter => chain = parse_u32(bytes).ok(),
             _ => {
                 if opts.fail_on_unknown_attribute {
                     return Err(Error::Parse(format!(
@@ -36,12 +69,46 @@ fn get_qdiscs(message: TcMsg, classful: bool, opts: &ParseOptions) -> Result<Tc,
         }
     }
 
-    if classful {
-        attribute.class = parse_classes(attribute.kind.as_str(), tc_opts, opts)?;
-    } else {
-        attribute.qdisc = parse_qdiscs(attribute.kind.as_str(), tc_opts, opts)?;
+    if let Some(stats) = attribute.stats.as_mut() {
+        // `struct tc_stats` only has 32 bits, complete it with the basic stats of the same dump
+        stats.packets64 = match attribute.stats2.as_ref().and_then(|s| s.basic.as_ref()) {
+            Some(basic) if basic.packets == stats.packets => basic.packets64,
+            _ => stats.packets as u64,
+        };
+    }
+
+    match tc_type {
+        TcType::QDisc => {
+            attribute.qdisc = parse_qdiscs(attribute.kind.as_str(), tc_opts, opts)?;
+        }
+        TcType::Class => {
+            attribute.class = parse_classes(attribute.kind.as_str(), tc_opts, opts)?;
+        }
+        TcType::Filter => {
+            let mut filter = Filter::new(
+                message.header.info,
+                message.header.handle,
+                chain,
+                attribute.kind.clone(),
+            );
+            filter.classifier = parse_filters(attribute.kind.as_str(), tc_opts, opts)?;
+            attribute.filter = Some(filter);
+        }
+    }
+    if tc_type != TcType::Filter {
+        // both attributes carry the same structure, `TCA_STATS_APP` takes precedence
+        let app = match app {
+            Some(bytes) => parse_xstats(attribute.kind.as_str(), bytes, opts)?,
+            None => None,
+        };
+        attribute.xstats = match app {
+            Some(_) => app.clone(),
+            None => parse_xstats(attribute.kind.as_str(), xstats.as_slice(), opts)?,
+        };
+        if let Some(stats2) = attribute.stats2.as_mut() {
+            stats2.app = app;
+        }
     }
-    attribute.xstats = parse_xstats(attribute.kind.as_str(), xstats.as_slice(), opts)?;
 
     Ok(Tc {
         msg: tc,
@@ -51,12 +118,17 @@ fn get_qdiscs(message: TcMsg, classful: bool, opts: &ParseOptions) -> Result<Tc,
 
 /// `qdiscs` returns a list of queuing disciplines by parsing the passed `TcMsg` vector.
 pub fn qdiscs(message: TcMsg, opts: &ParseOptions) -> Result<Tc, Error> {
-    get_qdiscs(message, false, opts)
+    get_qdiscs(message, TcType::QDisc, opts)
 }
 
 /// `classes` returns a list of traffic control classes by parsing the passed `TcMsg` vector.
 pub fn classes(message: TcMsg, opts: &ParseOptions) -> Result<Tc, Error> {
-    get_qdiscs(message, true, opts)
+    get_qdiscs(message, TcType::Class, opts)
+}
+
+/// `filters` returns a list of traffic filters by parsing the passed `TcMsg` vector.
+pub fn filters(message: TcMsg, opts: &ParseOptions) -> Result<Tc, Error> {
+    get_qdiscs(message, TcType::Filter, opts)
 }
 
 pub fn tc_stats(messages: Vec<RtNetlinkMessage>, opts: &ParseOptions) -> Result<Vec<Tc>, Error> {
@@ -66,32 +138,138 @@ pub fn tc_stats(messages: Vec<RtNetlinkMessage>, opts: &ParseOptions) -> Result<
         match message {
             RtNetlinkMessage::GetQdisc(message) => tcs.push(qdiscs(message, opts)?),
             RtNetlinkMessage::GetClass(message) => tcs.push(classes(message, opts)?),
+            RtNetlinkMessage::GetFilter(message) => tcs.push(filters(message, opts)?),
         }
     }
 
     Ok(tcs)
 }
 
+/// `actions` returns the list of actions nested in `bytes`,
+/// the value of `TCA_ROOT_TAB` or of the `TCA_*_ACT` option of a classifier.
+pub fn actions(bytes: &[u8], opts: &ParseOptions) -> Result<Vec<Action>, Error> {
+    unmarshal_nested(bytes)?
+        .into_iter()
+        .map(|opt| action(opt.kind, unmarshal_nested(&opt.bytes)?, opts))
+        .collect()
+}
+
+fn action(order: u16, attrs: Vec<TcOption>, opts: &ParseOptions) -> Result<Action, Error> {
+    let mut action = Action {
+        order,
+        ..Default::default()
+    };
+
+    let mut act_opts = Vec::new();
+    for attr in attrs {
+        match TcaAct::from(attr.kind) {
+            TcaAct::Kind => {
+                let kind = attr.bytes.split(|b| *b == 0).next().unwrap_or_default();
+                action.kind = String::from_utf8_lossy(kind).to_string();
+            }
+            TcaAct::Options => act_opts = unmarshal_nested(&attr.bytes)?,
+            TcaAct::Index => action.index = parse_u32(&attr.bytes).ok(),
+            TcaAct::Stats => action.stats = parse_action_stats(&attr.bytes).ok(),
+            TcaAct::Cookie => action.cookie = Some(attr.bytes),
+            // struct nla_bitfield32, only the value is reported
+            TcaAct::Flags => action.flags = parse_u32(&attr.bytes).ok(),
+            TcaAct::HwStats => action.hw_stats = parse_u32(&attr.bytes).ok(),
+            TcaAct::UsedHwStats => action.used_hw_stats = parse_u32(&attr.bytes).ok(),
+            TcaAct::InHwCount => action.in_hw_count = parse_u32(&attr.bytes).ok(),
+            TcaAct::Pad => (),
+            _ => {
+                if opts.fail_on_unknown_attribute {
+                    return Err(Error::Parse(format!(
+                        "Action attribute {} not implemented",
+                        attr.kind
+                    )));
+                }
+            }
+        }
+    }
+    action.options = parse_actions(action.kind.as_str(), act_opts, opts)?;
+
+    Ok(action)
+}
+
+fn parse_u32(bytes: &[u8]) -> Result<u32, Error> {
+    unmarshal_u32_value(bytes).ok_or_else(|| Error::Parse("Expected 4 bytes for u32".to_string()))
+}
+
+fn parse_u64(bytes: &[u8]) -> Result<u64, Error> {
+    unmarshal_u64_value(bytes).ok_or_else(|| Error::Parse("Expected 8 bytes for u64".to_string()))
+}
+
 fn parse_stats(bytes: &[u8]) -> Result<Stats, Error> {
     bincode::deserialize(bytes).map_err(|e| Error::Parse(e.to_string()))
 }
 
+fn parse_stab(bytes: &[u8]) -> Result<Stab, Error> {
+    Stab::new(unmarshal_nested(bytes)?)
+}
+
+fn parse_stats_basic(bytes: &[u8]) -> Result<StatsBasic, Error> {
+    let mut basic: StatsBasic =
+        bincode::deserialize(bytes).map_err(|e| Error::Parse(e.to_string()))?;
+    basic.packets64 = basic.packets as u64;
+    Ok(basic)
+}
+
 fn parse_stats2(stats2: &Vec<TcStats2>) -> Result<Stats2, Error> {
     let mut stats = Stats2::default();
     let mut errors = Vec::new();
+    // `TCA_STATS_PKT64` follows the basic stats it completes, either software or hardware
+    let mut last_hw = false;
     for stat in stats2 {
         match stat {
-            TcStats2::StatsBasic(bytes) => match bincode::deserialize(bytes.as_slice()) {
-                Ok(stats_basic) => stats.basic = Some(stats_basic),
+            TcStats2::StatsBasic(bytes) => match parse_stats_basic(bytes) {
+                Ok(stats_basic) => {
+                    stats.basic = Some(stats_basic);
+                    last_hw = false;
+                }
                 Err(e) => errors.push(format!("Failed to parse StatsBasic: {e}")),
             },
             TcStats2::StatsQueue(bytes) => match bincode::deserialize(bytes.as_slice()) {
                 Ok(stats_queue) => stats.queue = Some(stats_queue),
                 Err(e) => errors.push(format!("Failed to parse StatsQueue: {e}")),
             },
-            // TODO: StatsApp parsing
-            // TcStats2::StatsApp(bytes) => stats.app = bincode::deserialize(bytes.as_slice()).ok(),
-            _ => (),
+            // decoded by kind, see `get_qdiscs`
+            TcStats2::StatsApp(_) => (),
+            TcStats2::RateEst(bytes) => match bincode::deserialize::<(u32, u32)>(bytes) {
+                // the 64-bit rates are sent along when the rates do not fit in 32 bits
+                Ok((bps, pps)) => {
+                    if stats.rate_est.is_none() {
+                        stats.rate_est = Some(StatsRateEst {
+                            bps: bps as u64,
+                            pps: pps as u64,
+                        })
+                    }
+                }
+                Err(e) => errors.push(format!("Failed to parse RateEst: {e}")),
+            },
+            TcStats2::RateEst64(bytes) => match bincode::deserialize::<(u64, u64)>(bytes) {
+                Ok((bps, pps)) => stats.rate_est = Some(StatsRateEst { bps, pps }),
+                Err(e) => errors.push(format!("Failed to parse RateEst64: {e}")),
+            },
+            TcStats2::BasicHw(bytes) => match parse_stats_basic(bytes) {
+                Ok(stats_basic) => {
+                    stats.basic_hw = Some(stats_basic);
+                    last_hw = true;
+                }
+                Err(e) => errors.push(format!("Failed to parse StatsBasicHw: {e}")),
+            },
+            TcStats2::Pkt64(bytes) => match parse_u64(bytes) {
+                Ok(packets) => {
+                    let basic = match last_hw {
+                        true => stats.basic_hw.as_mut(),
+                        false => stats.basic.as_mut(),
+                    };
+                    if let Some(basic) = basic {
+                        basic.packets64 = packets;
+                    }
+                }
+                Err(e) => errors.push(format!("Failed to parse Pkt64: {e}")),
+            },
         }
     }
 
@@ -105,13 +283,37 @@ fn parse_stats2(stats2: &Vec<TcStats2>) -> Result<Stats2, Error> {
     }
 }
 
+/// Parses the `TCA_STATS_*` attributes nested in `TCA_ACT_STATS`.
+fn parse_action_stats(bytes: &[u8]) -> Result<Stats2, Error> {
+    let stats2 = unmarshal_nested(bytes)?
+        .into_iter()
+        .filter_map(|opt| match TcaStats::from(opt.kind) {
+            TcaStats::Basic => Some(TcStats2::StatsBasic(opt.bytes)),
+            TcaStats::Queue => Some(TcStats2::StatsQueue(opt.bytes)),
+            TcaStats::App => Some(TcStats2::StatsApp(opt.bytes)),
+            TcaStats::RateEst => Some(TcStats2::RateEst(opt.bytes)),
+            TcaStats::RateEst64 => Some(TcStats2::RateEst64(opt.bytes)),
+            TcaStats::BasicHw => Some(TcStats2::BasicHw(opt.bytes)),
+            TcaStats::Pkt64 => Some(TcStats2::Pkt64(opt.bytes)),
+            _ => None,
+        })
+        .collect();
+    parse_stats2(&stats2)
+}
+
 fn parse_qdiscs(
     kind: &str,
     tc_opts: Vec<TcOption>,
     opts: &ParseOptions,
 ) -> Result<Option<QDisc>, Error> {
     let qdisc = match kind {
+        CAKE => Some(QDisc::Cake(Cake::new(tc_opts))),
+        CODEL => Some(QDisc::Codel(Codel::new(tc_opts))),
+        FQ => Some(QDisc::Fq(Fq::new(tc_opts))),
         FQ_CODEL => Some(QDisc::FqCodel(FqCodel::new(tc_opts))),
+        FQ_PIE => Some(QDisc::FqPie(FqPie::new(tc_opts))),
+        PIE => Some(QDisc::Pie(Pie::new(tc_opts))),
+        TBF => Some(QDisc::Tbf(Tbf::new(tc_opts))),
         CLSACT => Some(QDisc::Clsact(Clsact {})),
         HTB => Htb::new(tc_opts).init.map(QDisc::Htb),
         _ => {
@@ -131,6 +333,7 @@ fn parse_classes(
     opts: &ParseOptions,
 ) -> Result<Option<Class>, Error> {
     let class = match kind {
+        FQ_CODEL => Some(Class::FqCodel(FqCodelClass {})),
         HTB => Some(Class::Htb(Htb::new(tc_opts))),
         _ => {
             if opts.fail_on_unknown_option {
@@ -143,10 +346,74 @@ fn parse_classes(
     Ok(class)
 }
 
+fn parse_filters(
+    kind: &str,
+    tc_opts: Vec<TcOption>,
+    opts: &ParseOptions,
+) -> Result<Option<Classifier>, Error> {
+    let classifier = match kind {
+        BPF => Some(Classifier::Bpf(Bpf::new(tc_opts))),
+        FLOWER => Some(Classifier::Flower(Box::new(Flower::new(tc_opts)))),
+        MATCHALL => Some(Classifier::Matchall(Matchall::new(tc_opts))),
+        U32_KIND => Some(Classifier::U32(U32::new(tc_opts))),
+        _ => {
+            if opts.fail_on_unknown_option {
+                return Err(Error::Parse(format!("Filter {kind} not implemented",)));
+            } else {
+                None
+            }
+        }
+    };
+    Ok(classifier)
+}
+
+fn parse_actions(
+    kind: &str,
+    tc_opts: Vec<TcOption>,
+    opts: &ParseOptions,
+) -> Result<Option<ActionOptions>, Error> {
+    let action = match kind {
+        BPF => Some(ActionOptions::Bpf(ActBpf::new(tc_opts))),
+        CONNMARK => Some(ActionOptions::Connmark(Connmark::new(tc_opts))),
+        CSUM => Some(ActionOptions::Csum(Csum::new(tc_opts))),
+        CT => Some(ActionOptions::Ct(Ct::new(tc_opts))),
+        CTINFO => Some(ActionOptions::Ctinfo(Ctinfo::new(tc_opts))),
+        GACT => Some(ActionOptions::Gact(Gact::new(tc_opts))),
+        MIRRED => Some(ActionOptions::Mirred(Mirred::new(tc_opts))),
+        MPLS => Some(ActionOptions::Mpls(Mpls::new(tc_opts))),
+        NAT => Some(ActionOptions::Nat(Nat::new(tc_opts))),
+        PEDIT => Some(ActionOptions::Pedit(Pedit::new(tc_opts))),
+        POLICE => Some(ActionOptions::Police(Police::new(tc_opts))),
+        SKBEDIT => Some(ActionOptions::Skbedit(Skbedit::new(tc_opts))),
+        SKBMOD => Some(ActionOptions::Skbmod(Skbmod::new(tc_opts))),
+        TUNNEL_KEY => Some(ActionOptions::TunnelKey(TunnelKey::new(tc_opts))),
+        VLAN => Some(ActionOptions::Vlan(Vlan::new(tc_opts))),
+        _ => {
+            if opts.fail_on_unknown_option {
+                return Err(Error::Parse(format!("Action {kind} not implemented",)));
+            } else {
+                None
+            }
+        }
+    };
+    Ok(action)
+}
+
 fn parse_xstats(kind: &str, bytes: &[u8], opts: &ParseOptions) -> Result<Option<XStats>, Error> {
     let xstats = match kind {
-        FQ_CODEL => FqCodelXStats::new(bytes).ok().map(XStats::FqCodel),
+        CAKE => CakeXStats::new(bytes).ok().map(XStats::Cake),
+        CODEL => CodelXStats::new(bytes).ok().map(XStats::Codel),
+        FQ => FqQdStats::new(bytes).ok().map(XStats::Fq),
+        // the qdisc and its flows share the structure, told apart by its type
+        FQ_CODEL => FqCodelXStats::new(bytes)
+            .map(XStats::FqCodel)
+            .or_else(|_| FqCodelClassXStats::new(bytes).map(XStats::FqCodelClass))
+            .ok(),
+        FQ_PIE => FqPieXStats::new(bytes).ok().map(XStats::FqPie),
         HTB => HtbXstats::new(bytes).ok().map(XStats::Htb),
+        PIE => PieXStats::new(bytes).ok().map(XStats::Pie),
+        // tbf reports no extended stats
+        TBF => None,
         _ => {
             if opts.fail_on_unknown_option {
                 return Err(Error::Parse(format!("XStats {kind} not implemented",)));
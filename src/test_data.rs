@@ -1,6 +1,8 @@
 use netlink_packet_core::{NetlinkHeader, NetlinkMessage, NetlinkPayload};
+
+use crate::action::ActionMessage;
 use netlink_packet_route::tc::Nla;
-use netlink_packet_route::{tc, RtnlMessage, TcHeader as NlTcHeader, TcMessage};
+use netlink_packet_route::{tc, RtnlMessage, TcHeader as NlTcHeader, TcMessage, TcMessageBuffer};
 use netlink_packet_utils::{nla, Parseable};
 
 pub fn nl_qdiscs() -> Vec<TcMessage> {
@@ -310,3 +312,95 @@ pub fn nlas(kind: &str) -> Vec<Nla> {
         ]),
     ]
 }
+
+pub fn nl_filters() -> Vec<TcMessage> {
+    vec![
+        // u32 on clsact ingress: `tc filter add dev ifb0 ingress prio 1 protocol ip u32 match ip dst 10.0.0.1/32 classid 1:1`
+        // TcMessage { header: TcHeader { family: 0, index: 2, handle: 0, parent: 4294967282, info: 65544 }, nlas: [Kind("u32"), Chain([0, 0, 0, 0])] }
+        filter(&[
+            0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 242, 255, 255, 255, 8, 0, 1, 0, // header
+            8, 0, 1, 0, 117, 51, 50, 0, // kind
+            8, 0, 11, 0, 0, 0, 0, 0, // chain
+        ]),
+        // TcMessage { header: TcHeader { family: 0, index: 2, handle: 2147483648, parent: 4294967282, info: 65544 }, nlas: [Kind("u32"), Chain([0, 0, 0, 0]), Options([U32(Divisor(1))])] }
+        filter(&[
+            0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 128, 242, 255, 255, 255, 8, 0, 1, 0, // header
+            8, 0, 1, 0, 117, 51, 50, 0, // kind
+            8, 0, 11, 0, 0, 0, 0, 0, // chain
+            12, 0, 2, 0, 8, 0, 4, 0, 1, 0, 0, 0, // options
+        ]),
+        // TcMessage { header: TcHeader { family: 0, index: 2, handle: 2147485696, parent: 4294967282, info: 65544 }, nlas: [Kind("u32"), Chain([0, 0, 0, 0]), Options([U32(Sel(Sel { flags: 1, offshift: 0, nkeys: 1, offmask: 0, off: 0, offoff: 0, hoff: 0, hmask: 0, keys: [Key { mask: 4294967295, val: 16777226, off: 16, offmask: 0 }] })), U32(Hash(2147483648)), U32(ClassId(65537)), U32(Flags(8))])] }
+        filter(&[
+            0, 0, 0, 0, 2, 0, 0, 0, 0, 8, 0, 128, 242, 255, 255, 255, 8, 0, 1, 0, // header
+            8, 0, 1, 0, 117, 51, 50, 0, // kind
+            8, 0, 11, 0, 0, 0, 0, 0, // chain
+            64, 0, 2, 0, // options
+            36, 0, 5, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // sel
+            255, 255, 255, 255, 10, 0, 0, 1, 16, 0, 0, 0, 0, 0, 0, 0, // key
+            8, 0, 2, 0, 0, 0, 0, 128, // hash
+            8, 0, 1, 0, 1, 0, 1, 0, // classid
+            8, 0, 11, 0, 8, 0, 0, 0, // flags
+        ]),
+        // classic BPF: `tc filter add dev ifb0 ingress prio 20 bpf bytecode '1,6 0 0 4294967295,' classid 1:2`
+        // TcMessage { header: TcHeader { family: 0, index: 2, handle: 0, parent: 4294967282, info: 1311488 }, nlas: [Kind("bpf"), Chain([0, 0, 0, 0])] }
+        filter(&[
+            0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 242, 255, 255, 255, 0, 3, 20, 0, // header
+            8, 0, 1, 0, 98, 112, 102, 0, // kind
+            8, 0, 11, 0, 0, 0, 0, 0, // chain
+        ]),
+        // TcMessage { header: TcHeader { family: 0, index: 2, handle: 1, parent: 4294967282, info: 1311488 }, nlas: [Kind("bpf"), Chain([0, 0, 0, 0]), Options([Other(DefaultNla { kind: 3, value: [2, 0, 1, 0] }), Other(DefaultNla { kind: 4, value: [1, 0] }), Other(DefaultNla { kind: 5, value: [6, 0, 0, 0, 255, 255, 255, 255] }), Other(DefaultNla { kind: 9, value: [8, 0, 0, 0] })])] }
+        filter(&[
+            0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 242, 255, 255, 255, 0, 3, 20, 0, // header
+            8, 0, 1, 0, 98, 112, 102, 0, // kind
+            8, 0, 11, 0, 0, 0, 0, 0, // chain
+            40, 0, 2, 0, // options
+            8, 0, 3, 0, 2, 0, 1, 0, // classid
+            6, 0, 4, 0, 1, 0, 0, 0, // ops len
+            12, 0, 5, 0, 6, 0, 0, 0, 255, 255, 255, 255, // ops
+            8, 0, 9, 0, 8, 0, 0, 0, // flags gen
+        ]),
+    ]
+}
+
+pub fn get_filters() -> Vec<NetlinkMessage<RtnlMessage>> {
+    nl_filters()
+        .into_iter()
+        .map(|filter| {
+            NetlinkMessage::new(
+                NetlinkHeader::default(),
+                NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficFilter(filter)),
+            )
+        })
+        .collect()
+}
+
+/// Parses a `tc` message captured from the kernel, without the netlink header.
+fn filter(bytes: &[u8]) -> TcMessage {
+    TcMessage::parse(&TcMessageBuffer::new(&bytes)).unwrap()
+}
+
+/// Actions dumped with `tc actions ls action mirred`, including the netlink header.
+/// The action was created by `tc filter add dev ifb0 ingress prio 12 protocol ip u32
+/// match ip dst 10.9.9.12/32 action mirred egress mirror dev ifb1`.
+pub fn nl_actions() -> Vec<NetlinkMessage<ActionMessage>> {
+    let message: &[u8] = &[
+        208, 0, 0, 0, 50, 0, 2, 0, 1, 0, 0, 0, 253, 99, 0, 0, // netlink header
+        0, 0, 0, 0, // tcamsg
+        8, 0, 3, 0, 1, 0, 0, 0, // root count
+        180, 0, 1, 0, // root tab
+        176, 0, 0, 0, // order 0
+        11, 0, 1, 0, 109, 105, 114, 114, 101, 100, 0, 0, // kind
+        68, 0, 4, 0, // stats
+        20, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // basic
+        20, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // basic hw
+        24, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // queue
+        12, 0, 9, 0, 0, 0, 0, 0, 3, 0, 0, 0, // used hw stats
+        8, 0, 10, 0, 0, 0, 0, 0, // in hw count
+        72, 0, 2, 0, // options
+        32, 0, 2, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0,
+        0, 0, // parms
+        36, 0, 1, 0, 180, 108, 3, 0, 0, 0, 0, 0, 180, 108, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, // tm
+    ];
+    vec![<NetlinkMessage<ActionMessage>>::deserialize(message).unwrap()]
+}
@@ -1,10 +1,39 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
 use netlink_packet_core::NetlinkHeader;
 use netlink_packet_route::TcMessage;
 
-use crate::class::{Htb, HtbGlob, HtbOpt, HtbXstats};
-use crate::qdiscs::{FqCodel, FqCodelXStats};
-use crate::test_data::{get_classes, get_qdiscs, nlas, qdisc};
-use crate::types::{Class, QDisc, RateSpec, XStats};
+use crate::action::{
+    marshal_actions, unmarshal_actions, ActBpf, ActGen, Connmark, Ct, Ctinfo, Gact, GactProb,
+    Mirred, MirredParms, Mpls, PeditKey, PeditKeyEx, Police, PoliceBuilder, TcaActBpf, TcaPolice,
+    Tcft, TunnelKey, MPLS_LABEL_MAX, PGACT_NETRAND, SKBMOD_F_ETYPE, TCA_CSUM_UPDATE_FLAG_IPV4HDR,
+    TCA_CSUM_UPDATE_FLAG_TCP, TCA_CT_ACT_COMMIT, TCA_CT_ACT_NAT, TCA_CT_ACT_NAT_SRC,
+    TCA_EGRESS_MIRROR, TCA_MPLS_ACT_PUSH, TCA_NAT_FLAG_EGRESS, TCA_PEDIT_KEY_EX_CMD_SET,
+    TCA_PEDIT_KEY_EX_HDR_TYPE_IP4, TCA_TUNNEL_KEY_ACT_RELEASE, TCA_TUNNEL_KEY_ACT_SET,
+    TCA_VLAN_ACT_PUSH, TC_ACT_OK, TC_ACT_PIPE, TC_ACT_RECLASSIFY, TC_ACT_SHOT,
+};
+use crate::class::{Htb, HtbClassBuilder, HtbGlob, HtbOpt, HtbXstats};
+use crate::constants::{tc_handle, TC_H_CLSACT, TC_H_ROOT};
+use crate::filter::{
+    Bpf, BpfOp, Flower, FlowerMplsLse, FlowerPortRange, Matchall, TcaBpf, TcaFlower, TcaMatchall,
+    TcaU32, U32Key, U32Pcnt, U32Selector, TCA_BPF_FLAG_ACT_DIRECT,
+    TCA_FLOWER_KEY_CT_FLAGS_ESTABLISHED, TCA_FLOWER_KEY_CT_FLAGS_TRACKED, U32,
+};
+use crate::qdiscs::{
+    Cake, CakeTinStats, CakeXStats, Clsact, ClsactHook, Codel, CodelBuilder, CodelXStats, Fq,
+    FqCodel, FqCodelClass, FqCodelClassXStats, FqCodelXStats, FqPie, FqPieBuilder, FqPieXStats,
+    FqPrioMap, FqQdStats, Pie, PieBuilder, PieXStats, TbfBuilder, TcaFqCodel, CAKE_ACK_FILTER,
+    CAKE_ATM_PTM, CAKE_DIFFSERV_DIFFSERV4, CAKE_FLOW_TRIPLE, TCA_FQ_CODEL_XSTATS_CLASS,
+};
+use crate::rate::{calc_xmittime, LinkLayer};
+use crate::stab::StabBuilder;
+use crate::test_data::{get_classes, get_filters, get_qdiscs, nl_actions, nlas, qdisc};
+use crate::types::{
+    marshal_nested, unmarshal_nested, Action, ActionOptions, Class, Classifier, ClsFlags,
+    Estimator, Filter, QDisc, RateSpec, Stats2, StatsBasic, StatsHwSw, StatsRateEst, TcAttr,
+    TcOption, XStats, TCA_CLS_FLAGS_IN_HW, TCA_CLS_FLAGS_SKIP_HW, TCA_CLS_FLAGS_SKIP_SW,
+};
 
 use super::*;
 
@@ -95,7 +124,7 @@ fn test_fq_codel() {
             target: 4999,
             limit: 10240,
             interval: 99999,
-            ecn: 1,
+            ecn: Some(1),
             flows: 1024,
             quantum: 1514,
             ce_threshold: 0,
@@ -121,6 +150,520 @@ fn test_fq_codel() {
     );
 }
 
+#[test]
+fn test_fq() {
+    // defaults of `tc qdisc add ... fq`: limit 10000p flow_limit 100p buckets 1024
+    // orphan_mask 1023 quantum 3028b initial_quantum 15140b low_rate_threshold 550Kbit
+    // refill_delay 40ms timer_slack 10us horizon 10s horizon_drop
+    // bands 3 priomap 1 2 2 2 1 2 0 0 1 1 1 1 1 1 1 1 weights 589824 196608 65536
+    let u32_opt = |kind: u16, value: u32| TcOption {
+        kind,
+        bytes: value.to_ne_bytes().to_vec(),
+    };
+    let mut priomap = 3i32.to_ne_bytes().to_vec();
+    priomap.extend([1, 2, 2, 2, 1, 2, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1]);
+    let opts = vec![
+        u32_opt(1, 10000),
+        u32_opt(2, 100),
+        u32_opt(3, 3028),
+        u32_opt(4, 15140),
+        u32_opt(5, 1),
+        u32_opt(7, u32::MAX),
+        u32_opt(8, 10),
+        u32_opt(9, 40000),
+        u32_opt(10, 1023),
+        u32_opt(11, 68750),
+        u32_opt(12, u32::MAX),
+        u32_opt(13, 10000),
+        u32_opt(14, 10_000_000),
+        TcOption {
+            kind: 15, // TCA_FQ_HORIZON_DROP
+            bytes: vec![1],
+        },
+        TcOption {
+            kind: 16, // TCA_FQ_PRIOMAP
+            bytes: priomap,
+        },
+        TcOption {
+            kind: 17, // TCA_FQ_WEIGHTS
+            bytes: [589824i32, 196608, 65536]
+                .iter()
+                .flat_map(|w| w.to_ne_bytes())
+                .collect(),
+        },
+    ];
+    let fq = Fq::new(opts.clone());
+    assert_eq!(fq.plimit, Some(10000));
+    assert_eq!(fq.buckets_log, Some(10));
+    assert_eq!(fq.flow_max_rate, Some(u32::MAX));
+    assert_eq!(fq.horizon_drop, Some(1));
+    assert_eq!(
+        fq.priomap,
+        Some(FqPrioMap {
+            bands: 3,
+            priomap: [1, 2, 2, 2, 1, 2, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1],
+        })
+    );
+    assert_eq!(fq.weights, Some(vec![589824, 196608, 65536]));
+    assert_eq!(fq.to_options().unwrap(), opts);
+
+    let message = QDiscRequest::new(2, QDisc::Fq(fq.clone()))
+        .message(Operation::Replace)
+        .unwrap();
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.kind, "fq");
+    assert_eq!(tcs[0].attr.qdisc, Some(QDisc::Fq(fq)));
+
+    let weights = Fq {
+        weights: Some(vec![1]),
+        ..Default::default()
+    };
+    assert!(weights.to_options().is_err());
+
+    // `struct tc_fq_qd_stats`, with the fields of the first version only
+    let mut bytes = vec![0u8; 80];
+    bytes[..8].copy_from_slice(&12u64.to_ne_bytes()); // gc_flows
+    bytes[24..32].copy_from_slice(&3u64.to_ne_bytes()); // throttled
+    bytes[64..68].copy_from_slice(&5u32.to_ne_bytes()); // flows
+    let stats = FqQdStats::new(&bytes).unwrap();
+    assert_eq!((stats.gc_flows, stats.throttled, stats.flows), (12, 3, 5));
+    assert_eq!(stats.horizon_drops, 0);
+
+    bytes.resize(152, 0);
+    bytes[88..96].copy_from_slice(&7u64.to_ne_bytes()); // horizon_drops
+    bytes[120..128].copy_from_slice(&2u64.to_ne_bytes()); // band_drops[1]
+    bytes[144..148].copy_from_slice(&9u32.to_ne_bytes()); // band_pkt_count[2]
+    let stats = FqQdStats::new(&bytes).unwrap();
+    assert_eq!(stats.horizon_drops, 7);
+    assert_eq!(stats.band_drops, [0, 2, 0]);
+    assert_eq!(stats.band_pkt_count, [0, 0, 9]);
+    assert!(FqQdStats::new(&bytes[..40]).is_err());
+}
+
+#[test]
+fn test_fq_codel_class() {
+    use netlink_packet_route::{tc, TcHeader as NlTcHeader};
+
+    // flow 37 of `fq_codel` 1:, from `tc -s class show dev eth0`
+    let mut xstats = TCA_FQ_CODEL_XSTATS_CLASS.to_ne_bytes().to_vec();
+    for value in [-58i32 as u32, 1210, 2, 1, 1, -870i32 as u32] {
+        xstats.extend(value.to_ne_bytes());
+    }
+    xstats.resize(40, 0);
+    let header = NlTcHeader {
+        index: 2,
+        handle: tc_handle(1, 38),
+        parent: tc_handle(1, 0),
+        ..Default::default()
+    };
+    let class = TcMessage::from_parts(
+        header,
+        vec![
+            tc::Nla::Kind("fq_codel".to_string()),
+            tc::Nla::Stats2(vec![tc::nlas::Stats2::StatsApp(xstats.clone())]),
+            tc::Nla::XStats(xstats),
+        ],
+    );
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficClass(class)),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_option(true)
+        .tc(vec![message])
+        .unwrap();
+
+    assert_eq!(tcs[0].msg.handle & 0xffff, 38);
+    assert_eq!(tcs[0].attr.class, Some(Class::FqCodel(FqCodelClass {})));
+    assert_eq!(
+        tcs[0].attr.xstats,
+        Some(XStats::FqCodelClass(FqCodelClassXStats {
+            deficit: -58,
+            ldelay: 1210,
+            count: 2,
+            lastcount: 1,
+            dropping: 1,
+            drop_next: -870,
+        }))
+    );
+
+    // the qdisc stats decoder still rejects the flow stats
+    let mut xstats = TCA_FQ_CODEL_XSTATS_CLASS.to_ne_bytes().to_vec();
+    xstats.resize(40, 0);
+    assert!(FqCodelXStats::new(&xstats).is_err());
+    assert!(
+        ClassRequest::new(2, tc_handle(1, 38), Class::FqCodel(FqCodelClass {}))
+            .message(Operation::Add)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_codel() {
+    // codel target 5ms interval 100ms ce_threshold 1ms ecn
+    let codel = CodelBuilder::new()
+        .target(Duration::from_millis(5))
+        .interval(Duration::from_millis(100))
+        .ce_threshold(Duration::from_millis(1))
+        .ecn(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        codel,
+        Codel {
+            target: 5000,
+            limit: 0,
+            interval: 100_000,
+            ecn: 1,
+            ce_threshold: 1000,
+        }
+    );
+    // the limit is left to the kernel default
+    assert_eq!(
+        codel
+            .to_options()
+            .iter()
+            .map(|o| o.kind)
+            .collect::<Vec<_>>(),
+        vec![1, 3, 4, 5]
+    );
+    assert!(CodelBuilder::new()
+        .interval(Duration::from_secs(5000))
+        .build()
+        .is_err());
+
+    let mut message = QDiscRequest::new(2, QDisc::Codel(codel.clone()))
+        .message(Operation::Replace)
+        .unwrap();
+    // `struct tc_codel_xstats` as reported by a dropping qdisc
+    let mut xstats = Vec::new();
+    for value in [1514u32, 3, 2, 5210, -120i32 as u32, 0, 7, 1, 4] {
+        xstats.extend(value.to_ne_bytes());
+    }
+    if let NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc)) = &mut message.payload
+    {
+        tc.nlas.push(netlink_packet_route::tc::Nla::XStats(xstats));
+    }
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.kind, "codel");
+    assert_eq!(tcs[0].attr.qdisc, Some(QDisc::Codel(codel)));
+    assert_eq!(
+        tcs[0].attr.xstats,
+        Some(XStats::Codel(CodelXStats {
+            maxpacket: 1514,
+            count: 3,
+            lastcount: 2,
+            ldelay: 5210,
+            drop_next: -120,
+            drop_overlimit: 0,
+            ecn_mark: 7,
+            dropping: 1,
+            ce_mark: 4,
+        }))
+    );
+    assert!(CodelXStats::new(&[0; 20]).is_err());
+}
+
+#[test]
+fn test_pie() {
+    // pie target 20ms tupdate 30ms limit 1000 alpha 2 beta 20 ecn
+    let pie = PieBuilder::new()
+        .target(Duration::from_millis(20))
+        .tupdate(Duration::from_millis(30))
+        .limit(1000)
+        .alpha(2)
+        .beta(20)
+        .ecn(true)
+        .build()
+        .unwrap();
+    assert_eq!(
+        pie,
+        Pie {
+            target: 20_000,
+            limit: 1000,
+            tupdate: 30_000,
+            alpha: 2,
+            beta: 20,
+            ecn: 1,
+            bytemode: 0,
+            dq_rate_estimator: 0,
+        }
+    );
+    // flags are sent even when cleared, so that replacing a qdisc can turn them off
+    let opts = pie.to_options();
+    assert_eq!(opts.len(), 8);
+    assert_eq!(Pie::new(opts), pie);
+    assert!(PieBuilder::new().alpha(33).build().is_err());
+
+    let message = QDiscRequest::new(2, QDisc::Pie(pie.clone()))
+        .message(Operation::Replace)
+        .unwrap();
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.kind, "pie");
+    assert_eq!(tcs[0].attr.qdisc, Some(QDisc::Pie(pie)));
+
+    let mut bytes = (1u64 << 50).to_ne_bytes().to_vec();
+    for value in [15000u32, 125_000, 1, 300, 4, 0, 42, 2] {
+        bytes.extend(value.to_ne_bytes());
+    }
+    let stats = PieXStats::new(&bytes).unwrap();
+    assert_eq!(stats.prob, 1 << 50);
+    assert_eq!((stats.delay, stats.avg_dq_rate), (15000, 125_000));
+    assert_eq!((stats.dropped, stats.maxq, stats.ecn_mark), (4, 42, 2));
+    assert!(PieXStats::new(&bytes[..36]).is_err());
+
+    // fq_pie flows 512 ecn_prob 20 memory_limit 16mb dq_rate_estimator
+    let fq_pie = FqPieBuilder::new()
+        .flows(512)
+        .target(Duration::from_millis(10))
+        .ecn_prob(20)
+        .memory_limit(16 << 20)
+        .dq_rate_estimator(true)
+        .build()
+        .unwrap();
+    assert_eq!(fq_pie.flows, 512);
+    assert_eq!(fq_pie.target, 10_000);
+    assert_eq!(fq_pie.dq_rate_estimator, 1);
+    assert_eq!(FqPie::new(fq_pie.to_options()), fq_pie);
+    assert!(FqPieBuilder::new().flows(65537).build().is_err());
+    assert!(FqPieBuilder::new().ecn_prob(101).build().is_err());
+
+    let message = QDiscRequest::new(2, QDisc::FqPie(fq_pie.clone()))
+        .message(Operation::Replace)
+        .unwrap();
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.kind, "fq_pie");
+    assert_eq!(tcs[0].attr.qdisc, Some(QDisc::FqPie(fq_pie)));
+
+    let mut bytes = Vec::new();
+    for value in [1000u32, 10, 1, 0, 3, 25, 2, 5, 65536] {
+        bytes.extend(value.to_ne_bytes());
+    }
+    let stats = FqPieXStats::new(&bytes).unwrap();
+    assert_eq!(
+        (stats.packets_in, stats.dropped, stats.ecn_mark),
+        (1000, 10, 3)
+    );
+    assert_eq!(stats.new_flow_count, 25);
+    assert_eq!(stats.memory_usage, 65536);
+}
+
+#[test]
+fn test_cake() {
+    use netlink_packet_route::tc;
+
+    // cake bandwidth 100Mbit diffserv4 triple-isolate nat wash ingress ack-filter
+    // ptm overhead 22 mpu 64 rtt 50ms split-gso fwmark 0xff
+    let cake = Cake {
+        bandwidth: Some(12_500_000),
+        diffserv_mode: Some(CAKE_DIFFSERV_DIFFSERV4),
+        atm: Some(CAKE_ATM_PTM),
+        flow_mode: Some(CAKE_FLOW_TRIPLE),
+        overhead: Some(22),
+        rtt: Some(50_000),
+        target: Some(2500),
+        autorate: Some(0),
+        memory: Some(0),
+        nat: Some(1),
+        raw: None,
+        wash: Some(1),
+        mpu: Some(64),
+        ingress: Some(1),
+        ack_filter: Some(CAKE_ACK_FILTER),
+        split_gso: Some(1),
+        fwmark: Some(0xff),
+    };
+    let opts = cake.to_options();
+    assert_eq!(opts[0].kind, 2); // TCA_CAKE_BASE_RATE64
+    assert_eq!(opts[0].bytes, 12_500_000u64.to_ne_bytes());
+    assert_eq!(Cake::new(opts), cake);
+    let negative = Cake {
+        overhead: Some(-4),
+        ..Default::default()
+    };
+    assert_eq!(Cake::new(negative.to_options()), negative);
+
+    let u32_opt = |kind: u16, value: u32| TcOption {
+        kind,
+        bytes: value.to_ne_bytes().to_vec(),
+    };
+    let u64_opt = |kind: u16, value: u64| TcOption {
+        kind,
+        bytes: value.to_ne_bytes().to_vec(),
+    };
+    let tin = |sent: u32, delay: u32| {
+        marshal_nested(&[
+            u32_opt(2, sent),               // SENT_PACKETS
+            u64_opt(3, sent as u64 * 1000), // SENT_BYTES64
+            u32_opt(4, 2),                  // DROPPED_PACKETS
+            u64_opt(5, 3000),               // DROPPED_BYTES64
+            u32_opt(8, 1),                  // ECN_MARKED_PACKETS
+            u64_opt(9, 1500),               // ECN_MARKED_BYTES64
+            u32_opt(11, 4500),              // BACKLOG_BYTES
+            u64_opt(12, 781_248),           // THRESHOLD_RATE64
+            u32_opt(15, 5),                 // WAY_INDIRECT_HITS
+            u32_opt(17, 1),                 // WAY_COLLISIONS
+            u32_opt(18, delay * 4),         // PEAK_DELAY_US
+            u32_opt(19, delay),             // AVG_DELAY_US
+            u32_opt(20, delay / 2),         // BASE_DELAY_US
+            u32_opt(21, 3),                 // SPARSE_FLOWS
+            u32_opt(23, 1),                 // UNRESPONSIVE_FLOWS
+        ])
+    };
+    let app = marshal_nested(&[
+        u64_opt(2, 12_500_000), // CAPACITY_ESTIMATE64
+        u32_opt(3, 4_000_000),  // MEMORY_LIMIT
+        u32_opt(4, 36_864),     // MEMORY_USED
+        u32_opt(5, 14),         // AVG_NETOFF
+        u32_opt(8, 64),         // MIN_ADJLEN
+        u32_opt(9, 1536),       // MAX_ADJLEN
+        TcOption {
+            kind: 10, // TIN_STATS
+            bytes: marshal_nested(&[
+                TcOption {
+                    kind: 1,
+                    bytes: tin(100, 80),
+                },
+                TcOption {
+                    kind: 2,
+                    bytes: tin(2000, 300),
+                },
+            ]),
+        },
+    ]);
+
+    let mut message = QDiscRequest::new(2, QDisc::Cake(cake.clone()))
+        .message(Operation::Replace)
+        .unwrap();
+    if let NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc)) = &mut message.payload
+    {
+        tc.nlas
+            .push(tc::Nla::Stats2(vec![tc::nlas::Stats2::StatsApp(app)]));
+    }
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.kind, "cake");
+    assert_eq!(tcs[0].attr.qdisc, Some(QDisc::Cake(cake)));
+    let Some(XStats::Cake(stats)) = &tcs[0].attr.xstats else {
+        panic!("missing cake xstats: {:?}", tcs[0].attr.xstats);
+    };
+    assert_eq!(stats.capacity_estimate, 12_500_000);
+    assert_eq!((stats.memory_limit, stats.memory_used), (4_000_000, 36_864));
+    assert_eq!((stats.min_adjlen, stats.max_adjlen), (64, 1536));
+    assert_eq!(stats.tins.len(), 2);
+    assert_eq!(
+        stats.tins[1],
+        CakeTinStats {
+            sent_packets: 2000,
+            sent_bytes: 2_000_000,
+            dropped_packets: 2,
+            dropped_bytes: 3000,
+            ecn_marked_packets: 1,
+            ecn_marked_bytes: 1500,
+            backlog_bytes: 4500,
+            threshold_rate: 781_248,
+            way_indirect_hits: 5,
+            way_collisions: 1,
+            peak_delay_us: 1200,
+            avg_delay_us: 300,
+            base_delay_us: 150,
+            sparse_flows: 3,
+            unresponsive_flows: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(stats.tins[0].avg_delay_us, 80);
+    assert_eq!(CakeXStats::new(&[]).unwrap(), CakeXStats::default());
+    assert!(CakeXStats::new(&[8, 0, 1]).is_err());
+}
+
+#[test]
+fn test_tbf() {
+    // tbf rate 1mbit burst 10000 latency 50ms
+    let tbf = TbfBuilder::new(125_000, 10_000)
+        .latency(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let parms = tbf.parms.as_ref().unwrap();
+    assert_eq!(parms.rate.rate, 125_000);
+    assert_eq!(parms.limit, 16_250);
+    // 80ms in 64ns ticks
+    assert_eq!(parms.buffer, 1_250_000);
+    assert_eq!(parms.peakrate, RateSpec::default());
+    assert_eq!(tbf.rtab.len(), 1024);
+    assert_eq!(tbf.burst, Some(10_000));
+    assert_eq!((tbf.rate(), tbf.peakrate()), (Some(125_000), None));
+
+    let message = QDiscRequest::new(2, QDisc::Tbf(tbf))
+        .message(Operation::Replace)
+        .unwrap();
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.kind, "tbf");
+    let Some(QDisc::Tbf(parsed)) = &tcs[0].attr.qdisc else {
+        panic!("missing tbf options: {:?}", tcs[0].attr.qdisc);
+    };
+    assert_eq!(parsed.parms.as_ref().unwrap().limit, 16_250);
+    assert_eq!(parsed.burst, Some(10_000));
+    assert_eq!(parsed.rtab.len(), 1024);
+
+    // tbf rate 10gbit peakrate 40gbit burst 1mb mtu 9000 limit 10mb
+    let tbf = TbfBuilder::new(1_250_000_000, 1 << 20)
+        .peakrate(5_000_000_000)
+        .mtu(9000)
+        .limit(10 << 20)
+        .build()
+        .unwrap();
+    let parms = tbf.parms.as_ref().unwrap();
+    assert_eq!(parms.rate.rate, 1_250_000_000);
+    assert_eq!(parms.peakrate.rate, u32::MAX);
+    assert_eq!(parms.mtu, calc_xmittime(5_000_000_000, 9000));
+    assert_eq!((tbf.rate64, tbf.prate64), (None, Some(5_000_000_000)));
+    assert_eq!(tbf.peakrate(), Some(5_000_000_000));
+    assert_eq!(tbf.pburst, Some(9000));
+    assert_eq!(tbf.ptab.len(), 1024);
+    // the peak bucket caps the limit derived from the latency
+    let tbf = TbfBuilder::new(125_000, 10_000)
+        .peakrate(250_000)
+        .mtu(1514)
+        .latency(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    assert_eq!(tbf.parms.unwrap().limit, 14_014);
+
+    let invalid = [
+        TbfBuilder::new(0, 10_000).limit(1 << 20).clone(),
+        // the bucket can't hold a full sized packet
+        TbfBuilder::new(125_000, 1000).limit(1 << 20).clone(),
+        TbfBuilder::new(125_000, 9000)
+            .mtu(9001)
+            .limit(1 << 20)
+            .clone(),
+        // the bucket takes longer than 2^32 ticks to drain
+        TbfBuilder::new(10, 1 << 20).limit(1 << 20).clone(),
+        TbfBuilder::new(125_000, 10_000).clone(),
+        TbfBuilder::new(125_000, 10_000)
+            .limit(1 << 20)
+            .latency(Duration::from_millis(50))
+            .clone(),
+        TbfBuilder::new(125_000, 10_000)
+            .limit(1 << 20)
+            .peakrate(250_000)
+            .clone(),
+        TbfBuilder::new(125_000, 10_000)
+            .limit(1 << 20)
+            .peakrate(125_000)
+            .mtu(1514)
+            .clone(),
+        // the rate table cell sizes overflow 32 bits
+        TbfBuilder::new(125_000, 10_000)
+            .limit(1 << 20)
+            .cell_log(32)
+            .clone(),
+    ];
+    for builder in invalid {
+        assert!(builder.build().is_err(), "{builder:?}");
+    }
+}
+
 #[test]
 fn test_htb() {
     let qdiscs = get_qdiscs();
@@ -231,8 +774,11 @@ fn test_unknown_netlink_msg_fail() {
 
 #[test]
 fn test_unknown_attribute_fail() {
-    // hwoffload not implemented
-    let tc_message = qdisc("fq_codel");
+    // fcnt not implemented
+    let mut tc_message = qdisc("fq_codel");
+    tc_message
+        .nlas
+        .push(netlink_packet_route::tc::Nla::Fcnt(vec![0; 4]));
     let messages = NetlinkMessage::new(
         NetlinkHeader::default(),
         NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message)),
@@ -270,6 +816,254 @@ fn test_stats_parse_fail() {
     assert!(tc.attr.stats2.is_none());
 }
 
+#[test]
+fn test_stats_app() {
+    use netlink_packet_route::tc;
+
+    let kind = "fq_codel";
+    let mut tc_message = qdisc(kind);
+    let mut nlas = nlas(kind);
+    // a stale legacy copy, which `TCA_STATS_APP` takes precedence over
+    nlas[5] = tc::Nla::XStats(vec![0; 40]);
+    tc_message.nlas = nlas;
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message.clone())),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .tc(vec![message])
+        .unwrap();
+    let Some(XStats::FqCodel(xstats)) = &tcs[0].attr.xstats else {
+        panic!("expected fq_codel xstats");
+    };
+    assert_eq!(xstats.maxpacket, 258);
+    assert_eq!(tcs[0].attr.stats2.as_ref().unwrap().app, tcs[0].attr.xstats);
+
+    // the legacy attribute is used when there is no `TCA_STATS_APP`
+    tc_message.nlas[3] = tc::Nla::Stats2(vec![]);
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message)),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .tc(vec![message])
+        .unwrap();
+    let Some(XStats::FqCodel(xstats)) = &tcs[0].attr.xstats else {
+        panic!("expected fq_codel xstats");
+    };
+    assert_eq!(xstats.maxpacket, 0);
+    assert_eq!(tcs[0].attr.stats2.as_ref().unwrap().app, None);
+}
+
+#[test]
+fn test_rate_est() {
+    use netlink_packet_route::tc;
+    use netlink_packet_utils::nla::DefaultNla;
+
+    let rate_est =
+        |kind: u16, bytes: Vec<u8>| tc::nlas::Stats2::Other(DefaultNla::new(kind, bytes));
+    let mut rate_est32 = 1000u32.to_ne_bytes().to_vec();
+    rate_est32.extend(10u32.to_ne_bytes());
+    let mut rate_est64 = 5_000_000_000u64.to_ne_bytes().to_vec();
+    rate_est64.extend(4_000_000u64.to_ne_bytes());
+
+    let kind = "fq_codel";
+    let mut tc_message = qdisc(kind);
+    tc_message.nlas[3] = tc::Nla::Stats2(vec![rate_est(2, rate_est32.clone())]);
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message.clone())),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .tc(vec![message])
+        .unwrap();
+    assert_eq!(
+        tcs[0].attr.stats2.as_ref().unwrap().rate_est,
+        Some(StatsRateEst { bps: 1000, pps: 10 })
+    );
+
+    // the 64-bit rates take precedence over the truncated ones
+    tc_message.nlas[3] = tc::Nla::Stats2(vec![
+        rate_est(5, rate_est64),
+        rate_est(2, rate_est32),
+        rate_est(6, vec![]), // TCA_STATS_PAD
+    ]);
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message)),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .tc(vec![message])
+        .unwrap();
+    assert_eq!(
+        tcs[0].attr.stats2.as_ref().unwrap().rate_est,
+        Some(StatsRateEst {
+            bps: 5_000_000_000,
+            pps: 4_000_000
+        })
+    );
+}
+
+#[test]
+fn test_estimator() {
+    // `tc ... estimator 250ms 1sec`
+    let estimator = Estimator::new(Duration::from_millis(250), Duration::from_secs(1)).unwrap();
+    assert_eq!(
+        estimator,
+        Estimator {
+            interval: -2,
+            ewma_log: 2
+        }
+    );
+    // `tc ... estimator 1sec 8sec`
+    let estimator = Estimator::new(Duration::from_secs(1), Duration::from_secs(8)).unwrap();
+    assert_eq!((estimator.interval, estimator.ewma_log), (0, 3));
+    assert!(Estimator::new(Duration::from_secs(16), Duration::from_secs(64)).is_err());
+
+    let message = ClassRequest::new(
+        3,
+        tc_handle(1, 0x10),
+        Class::Htb(HtbClassBuilder::new(125000).build().unwrap()),
+    )
+    .estimator(estimator)
+    .message(Operation::Add)
+    .unwrap();
+    let NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficClass(message)) = message.payload
+    else {
+        panic!("expected RTM_NEWTCLASS");
+    };
+    assert!(message.nlas.contains(&netlink_tc::Nla::Rate(vec![0, 3])));
+}
+
+#[test]
+fn test_hw_stats() {
+    // `gnet_stats_basic` of 1000 bytes in 10 packets, 600 bytes in 6 packets of them in hardware
+    let basic = |bytes: u64, packets: u32| {
+        let mut buf = bytes.to_ne_bytes().to_vec();
+        buf.extend(packets.to_ne_bytes());
+        buf.extend([0; 4]);
+        buf
+    };
+    let stats = marshal_nested(&[
+        TcOption {
+            kind: 1, // TCA_STATS_BASIC
+            bytes: basic(1000, 10),
+        },
+        TcOption {
+            kind: 7, // TCA_STATS_BASIC_HW
+            bytes: basic(600, 6),
+        },
+    ]);
+    let action = marshal_nested(&[
+        TcOption {
+            kind: 1, // TCA_ACT_KIND
+            bytes: b"mirred\0".to_vec(),
+        },
+        TcOption {
+            kind: 4, // TCA_ACT_STATS
+            bytes: stats,
+        },
+    ]);
+    let bytes = marshal_nested(&[TcOption {
+        kind: 1,
+        bytes: action,
+    }]);
+
+    let stats = unmarshal_actions(&bytes).remove(0).stats.unwrap();
+    assert_eq!(
+        stats.hw_sw_split(),
+        Some(StatsHwSw {
+            hw: StatsBasic {
+                bytes: 600,
+                packets: 6,
+                packets64: 6,
+            },
+            sw: StatsBasic {
+                bytes: 400,
+                packets: 4,
+                packets64: 4,
+            },
+        })
+    );
+
+    // without hardware counters all the traffic is in software
+    let stats = Stats2 {
+        basic: Some(StatsBasic {
+            bytes: 1000,
+            packets: 10,
+            packets64: 10,
+        }),
+        ..Default::default()
+    };
+    assert_eq!(stats.hw_sw_split().unwrap().sw, stats.basic.unwrap());
+
+    let mut tc_message = qdisc("fq_codel");
+    tc_message.nlas[2] = netlink_packet_route::tc::Nla::HwOffload(1);
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message)),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(true)
+        .tc(vec![message])
+        .unwrap();
+    assert!(tcs[0].attr.hw_offload);
+}
+
+#[test]
+fn test_pkt64() {
+    use netlink_packet_route::tc;
+    use netlink_packet_utils::{nla::DefaultNla, Parseable};
+
+    let packets = 5_000_000_000u64;
+    let mut basic = 1_000_000_000_000u64.to_ne_bytes().to_vec();
+    basic.extend((packets as u32).to_ne_bytes());
+    basic.extend([0; 4]);
+
+    let kind = "fq_codel";
+    let mut tc_message = qdisc(kind);
+    tc_message.nlas[3] = tc::Nla::Stats2(vec![
+        tc::nlas::Stats2::StatsBasic(basic),
+        tc::nlas::Stats2::Other(DefaultNla::new(8, packets.to_ne_bytes().to_vec())), // TCA_STATS_PKT64
+    ]);
+    let mut stats = [0u8; 36];
+    stats[8..12].copy_from_slice(&(packets as u32).to_ne_bytes());
+    tc_message.nlas[4] =
+        tc::Nla::Stats(tc::nlas::Stats::parse(&tc::nlas::StatsBuffer::new(&stats)).unwrap());
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(tc_message)),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .tc(vec![message])
+        .unwrap();
+
+    let basic = tcs[0].attr.stats2.as_ref().unwrap().basic.as_ref().unwrap();
+    assert_eq!(basic.packets, packets as u32);
+    assert_eq!(basic.packets64, packets);
+    let stats = tcs[0].attr.stats.as_ref().unwrap();
+    assert_eq!(stats.packets, packets as u32);
+    assert_eq!(stats.packets64, packets);
+
+    // counts fitting in 32 bits come without `TCA_STATS_PKT64`
+    let message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(qdisc(kind))),
+    );
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .tc(vec![message])
+        .unwrap();
+    let basic = tcs[0].attr.stats2.as_ref().unwrap().basic.as_ref().unwrap();
+    assert_eq!(basic.packets64, basic.packets as u64);
+    assert_eq!(tcs[0].attr.stats.as_ref().unwrap().packets64, 165687);
+}
+
 #[test]
 fn test_unknown_option_fail() {
     let messages = vec![NetlinkMessage::new(
@@ -282,3 +1076,1296 @@ fn test_unknown_option_fail() {
 
     assert!(stats.is_err());
 }
+
+#[test]
+fn test_qdisc_request_add() {
+    use netlink_packet_core::{NLM_F_ACK, NLM_F_CREATE, NLM_F_EXCL, NLM_F_REQUEST};
+
+    let fq_codel = FqCodel {
+        target: 4999,
+        limit: 10240,
+        interval: 99999,
+        ecn: Some(1),
+        flows: 1024,
+        quantum: 1514,
+        ce_threshold: 0,
+        drop_batch_size: 64,
+        memory_limit: 33554432,
+    };
+    let message = QDiscRequest::new(2, QDisc::FqCodel(fq_codel.clone()))
+        .handle(tc_handle(1, 0))
+        .message(Operation::Add)
+        .unwrap();
+    assert_eq!(
+        message.header.flags,
+        NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL
+    );
+
+    // round trip through the wire format and the parser
+    let mut buf = vec![0; message.header.length as usize];
+    message.serialize(&mut buf[..]);
+    let message = <NetlinkMessage<RtnlMessage>>::deserialize(&buf).unwrap();
+    assert!(matches!(
+        message.payload,
+        NetlinkPayload::InnerMessage(RtnlMessage::NewQueueDiscipline(_))
+    ));
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    let tc = tcs.first().unwrap();
+    assert_eq!(tc.msg.index, 2);
+    assert_eq!(tc.msg.handle, 0x10000);
+    assert_eq!(tc.msg.parent, TC_H_ROOT);
+    assert_eq!(tc.attr.kind, "fq_codel");
+    assert_eq!(tc.attr.qdisc, Some(QDisc::FqCodel(fq_codel)));
+}
+
+#[test]
+fn test_fq_codel_ecn() {
+    // `tc qdisc replace ... fq_codel limit 10240` keeps the kernel's ECN default
+    let fq_codel = FqCodel {
+        limit: 10240,
+        ..Default::default()
+    };
+    let opts = fq_codel.to_options();
+    assert!(opts.iter().all(|opt| opt.kind != TcaFqCodel::Ecn as u16));
+    assert_eq!(FqCodel::new(opts), fq_codel);
+
+    let no_ecn = FqCodel {
+        ecn: Some(0),
+        ..Default::default()
+    };
+    let opts = no_ecn.to_options();
+    assert_eq!(
+        opts,
+        vec![TcOption {
+            kind: TcaFqCodel::Ecn as u16,
+            bytes: 0u32.to_ne_bytes().to_vec(),
+        }]
+    );
+    assert_eq!(FqCodel::new(opts), no_ecn);
+}
+
+#[test]
+fn test_qdisc_request_htb_replace() {
+    use netlink_packet_core::{NLM_F_ACK, NLM_F_CREATE, NLM_F_REPLACE, NLM_F_REQUEST};
+
+    let glob = HtbGlob {
+        version: 196625,
+        rate2quantum: 10,
+        defcls: 32,
+        debug: 0,
+        direct_pkts: 0,
+    };
+    let message = QDiscRequest::new(3, QDisc::Htb(glob))
+        .message(Operation::Replace)
+        .unwrap();
+    assert_eq!(
+        message.header.flags,
+        NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_REPLACE
+    );
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(
+        tcs.first().unwrap().attr.qdisc,
+        Some(QDisc::Htb(HtbGlob {
+            version: 3,
+            rate2quantum: 10,
+            defcls: 32,
+            debug: 0,
+            direct_pkts: 0,
+        }))
+    );
+}
+
+#[test]
+fn test_qdisc_stab() {
+    // `tc qdisc add ... stab linklayer atm overhead 10 mpu 64 htb`
+    let mut builder = StabBuilder::new(LinkLayer::Atm);
+    builder.overhead(10).mpu(64);
+    let stab = builder.build();
+    assert_eq!(stab.base.cell_log, 2);
+    assert_eq!(stab.base.cell_align, -1);
+    assert_eq!(stab.base.tsize, 512);
+    assert_eq!(&stab.data[..14], [106; 14]);
+    // 1500 + 10 bytes fill 32 ATM cells
+    assert_eq!(stab.packet_len(1500), 32 * 53);
+    assert_eq!(stab.packet_len(0), 106);
+
+    let message = QDiscRequest::new(3, QDisc::Htb(HtbGlob::default()))
+        .stab(stab.clone())
+        .message(Operation::Add)
+        .unwrap();
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    assert_eq!(tcs[0].attr.stab, Some(stab));
+
+    // only the overhead is needed on ethernet
+    let stab = StabBuilder::new(LinkLayer::Ethernet).overhead(-4).build();
+    assert_eq!((stab.base.tsize, stab.base.overhead), (0, -4));
+    assert!(stab.data.is_empty());
+    assert_eq!(stab.packet_len(100), 96);
+
+    let mut stab = builder.build();
+    stab.data.pop();
+    assert!(stab.to_bytes().is_err());
+}
+
+#[test]
+fn test_qdisc_request_delete() {
+    let message = QDiscRequest::new(1, QDisc::Clsact(Clsact {}))
+        .message(Operation::Delete)
+        .unwrap();
+    match message.payload {
+        NetlinkPayload::InnerMessage(RtnlMessage::DelQueueDiscipline(message)) => {
+            assert_eq!(message.header.index, 1);
+            assert_eq!(message.header.handle, 0xFFFF0000);
+            assert_eq!(message.header.parent, TC_H_CLSACT);
+            assert_eq!(
+                message.nlas,
+                vec![netlink_tc::Nla::Kind("clsact".to_string())]
+            );
+        }
+        _ => panic!("expected RTM_DELQDISC"),
+    }
+}
+
+#[test]
+fn test_htb_class_builder() {
+    let htb = HtbClassBuilder::new(125000).build().unwrap();
+    let parms = htb.parms.as_ref().unwrap();
+    let rate = RateSpec {
+        cell_log: 3,
+        linklayer: 1,
+        overhead: 0,
+        cell_align: u16::MAX,
+        mpu: 0,
+        rate: 125000,
+    };
+    assert_eq!(parms.rate, rate);
+    assert_eq!(parms.ceil, rate);
+    // default burst of 1600 bytes at 1mbit
+    assert_eq!(parms.buffer, 200000);
+    assert_eq!(parms.cbuffer, 200000);
+
+    // 256 entries, the first one being 8 bytes at 1mbit
+    assert_eq!(htb.rtab.len(), 1024);
+    assert_eq!(u32::from_ne_bytes(htb.rtab[0..4].try_into().unwrap()), 1000);
+    assert_eq!(
+        u32::from_ne_bytes(htb.rtab[1020..1024].try_into().unwrap()),
+        256000
+    );
+    assert_eq!(htb.ctab, htb.rtab);
+    assert_eq!(htb.rate64, None);
+    assert_eq!(htb.ceil64, None);
+}
+
+#[test]
+fn test_htb_class_builder_64bit_atm() {
+    use crate::rate::{adjust_size, LinkLayer};
+
+    assert_eq!(adjust_size(100, 0, LinkLayer::Atm), 159);
+    assert_eq!(adjust_size(10, 64, LinkLayer::Ethernet), 64);
+
+    let htb = HtbClassBuilder::new(125000)
+        .ceil(5_000_000_000)
+        .linklayer(LinkLayer::Atm)
+        .mpu(64)
+        .overhead(10)
+        .build()
+        .unwrap();
+    let parms = htb.parms.as_ref().unwrap();
+    assert_eq!(parms.rate.linklayer, LinkLayer::Atm as u8);
+    assert_eq!(parms.rate.overhead, 10);
+    assert_eq!(parms.ceil.rate, u32::MAX);
+    assert_eq!(htb.rate64, None);
+    assert_eq!(htb.ceil64, Some(5_000_000_000));
+    // first cell of 8 bytes is raised to the mpu and padded to two ATM cells
+    assert_eq!(
+        u32::from_ne_bytes(htb.rtab[0..4].try_into().unwrap()),
+        13250
+    );
+}
+
+#[test]
+fn test_htb_class_builder_invalid() {
+    assert!(HtbClassBuilder::new(0).build().is_err());
+    assert!(HtbClassBuilder::new(125000).ceil(0).build().is_err());
+    assert!(HtbClassBuilder::new(125000).cell_log(32).build().is_err());
+    assert!(HtbClassBuilder::new(125000).ccell_log(40).build().is_err());
+    assert!(HtbClassBuilder::new(125000).cell_log(31).build().is_ok());
+}
+
+#[test]
+fn test_class_request() {
+    let htb = HtbClassBuilder::new(125000)
+        .ceil(5_000_000_000)
+        .build()
+        .unwrap();
+    let message = ClassRequest::new(3, tc_handle(1, 1), Class::Htb(htb))
+        .message(Operation::Add)
+        .unwrap();
+
+    let mut buf = vec![0; message.header.length as usize];
+    message.serialize(&mut buf[..]);
+    let message = <NetlinkMessage<RtnlMessage>>::deserialize(&buf).unwrap();
+    assert!(matches!(
+        message.payload,
+        NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficClass(_))
+    ));
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    let tc = tcs.first().unwrap();
+    assert_eq!(tc.msg.handle, 65537);
+    assert_eq!(tc.msg.parent, 65536);
+    let Some(Class::Htb(htb)) = tc.attr.class.as_ref() else {
+        panic!("expected htb class");
+    };
+    assert_eq!(htb.parms.as_ref().unwrap().rate.rate, 125000);
+    assert_eq!(htb.rtab.len(), 1024);
+    assert_eq!(htb.ctab.len(), 1024);
+    assert_eq!(htb.ceil64, Some(5_000_000_000));
+
+    let message = ClassRequest::new(3, tc_handle(1, 1), Class::Htb(Htb::default()))
+        .message(Operation::Delete)
+        .unwrap();
+    assert!(matches!(
+        message.payload,
+        NetlinkPayload::InnerMessage(RtnlMessage::DelTrafficClass(_))
+    ));
+}
+
+#[test]
+fn test_strip_options() {
+    // pfifo_fast carries a `tc_prio_qopt` struct in TCA_OPTIONS, which `netlink-packet-route`
+    // fails to decode as attributes
+    let bytes: Vec<u8> = vec![
+        176, 0, 0, 0, 36, 0, 2, 0, 1, 0, 0, 0, 129, 17, 0, 0, // nlmsghdr
+        0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 2, 0, 0, 0, // tcmsg
+        15, 0, 1, 0, 112, 102, 105, 102, 111, 95, 102, 97, 115, 116, 0, 0, // kind
+        24, 0, 2, 0, 3, 0, 0, 0, 1, 2, 2, 2, 1, 2, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, // options
+        5, 0, 12, 0, 0, 0, 0, 0, // hw offload
+        48, 0, 7, 0, // stats2
+        20, 0, 1, 0, 139, 161, 0, 0, 0, 0, 0, 0, 193, 1, 0, 0, 0, 0, 0, 0, // basic
+        24, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // queue
+        44, 0, 3, 0, // stats
+        139, 161, 0, 0, 0, 0, 0, 0, 193, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    assert!(<NetlinkMessage<RtnlMessage>>::deserialize(&bytes).is_err());
+
+    let stripped = crate::handle::strip_options(&bytes);
+    assert_eq!(stripped.len(), bytes.len() - 24);
+    let message = <NetlinkMessage<RtnlMessage>>::deserialize(&stripped).unwrap();
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false)
+        .tc(vec![message])
+        .unwrap();
+    let tc = tcs.first().unwrap();
+    assert_eq!(tc.attr.kind, "pfifo_fast");
+    assert_eq!(
+        tc.attr
+            .stats2
+            .as_ref()
+            .unwrap()
+            .basic
+            .as_ref()
+            .unwrap()
+            .bytes,
+        41355
+    );
+}
+
+#[test]
+fn test_filters() {
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false)
+        .tc(get_filters())
+        .unwrap();
+    assert_eq!(tcs.len(), 5);
+
+    let tc = tcs.get(2).unwrap();
+    assert_eq!(tc.msg.index, 2);
+    assert_eq!(tc.msg.handle, 0x80000800);
+    assert_eq!(tc.msg.parent, 0xFFFFFFF2);
+    assert_eq!(tc.attr.kind, "u32");
+    assert!(tc.attr.qdisc.is_none());
+    assert!(tc.attr.class.is_none());
+    assert_eq!(
+        tc.attr.filter,
+        Some(Filter {
+            protocol: 0x0800,
+            priority: 1,
+            chain: Some(0),
+            handle: 0x80000800,
+            kind: "u32".to_string(),
+            classifier: Some(Classifier::U32(U32 {
+                classid: Some(0x10001),
+                hash: Some(0x80000000),
+                sel: Some(U32Selector {
+                    flags: 1,
+                    nkeys: 1,
+                    keys: vec![U32Key {
+                        mask: 0xFFFFFFFF,
+                        val: 0x0A000001,
+                        off: 16,
+                        offmask: 0,
+                    }],
+                    ..Default::default()
+                }),
+                flags: Some(8),
+                ..Default::default()
+            })),
+        })
+    );
+
+    let filter = tcs.get(1).unwrap().attr.filter.as_ref().unwrap();
+    assert_eq!(
+        filter.classifier,
+        Some(Classifier::U32(U32 {
+            divisor: Some(1),
+            ..Default::default()
+        }))
+    );
+}
+
+#[test]
+fn test_u32_link_and_pcnt() {
+    // `tc filter add ... u32 ht 800: match ip protocol 6 0xff offset at 0 mask 0x0f00 shift 6 link 1:`
+    let mut sel = vec![
+        0x0A, 6, 1, 0, 0x0F, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // tc_u32_sel
+    ];
+    sel.extend([0, 0xFF, 0, 0, 0, 6, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0]); // tc_u32_key
+    let mut pcnt = Vec::new();
+    for count in [100u64, 40, 40] {
+        pcnt.extend(count.to_ne_bytes());
+    }
+    let opts = vec![
+        TcOption {
+            kind: TcaU32::Link as u16,
+            bytes: 0x10000u32.to_ne_bytes().to_vec(),
+        },
+        TcOption {
+            kind: TcaU32::Sel as u16,
+            bytes: sel,
+        },
+        TcOption {
+            kind: TcaU32::Pcnt as u16,
+            bytes: pcnt,
+        },
+    ];
+
+    let u32 = U32::new(opts);
+    assert_eq!(u32.link, Some(0x10000));
+    let sel = u32.sel.unwrap();
+    assert_eq!(sel.offshift, 6);
+    assert_eq!(sel.offmask, 0x0F00);
+    assert_eq!(
+        sel.keys,
+        vec![U32Key {
+            mask: 0x00FF0000,
+            val: 0x00060000,
+            off: 8,
+            offmask: 0,
+        }]
+    );
+    assert_eq!(
+        u32.pcnt,
+        Some(U32Pcnt {
+            rcnt: 100,
+            rhit: 40,
+            kcnts: vec![40],
+        })
+    );
+
+    // a selector announcing more keys than it carries is rejected
+    assert!(U32Selector::new(&[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn test_flower() {
+    // `tc filter add ... protocol ip flower skip_sw ip_proto tcp dst_ip 10.0.0.0/24 dst_port 80
+    //   src_port 1000-2000 ct_state +trk+est tcp_flags 0x2/0x12`
+    let option = |kind: TcaFlower, bytes: &[u8]| TcOption {
+        kind: kind as u16,
+        bytes: bytes.to_vec(),
+    };
+    // TCA_FLOWER_KEY_MPLS_OPTS > TCA_FLOWER_KEY_MPLS_OPTS_LSE > DEPTH, LABEL
+    let mpls_opts = [
+        20, 0, 0x01, 0x80, // LSE, nested
+        5, 0, 1, 0, 1, 0, 0, 0, // depth 1
+        8, 0, 5, 0, 100, 0, 0, 0, // label 100
+    ];
+    let opts = vec![
+        option(TcaFlower::ClassId, &0x10001u32.to_ne_bytes()),
+        option(TcaFlower::KeyEthType, &[0x08, 0x00]),
+        option(TcaFlower::KeyIpProto, &[6]),
+        option(TcaFlower::KeyIpv4Dst, &[10, 0, 0, 0]),
+        option(TcaFlower::KeyIpv4DstMask, &[255, 255, 255, 0]),
+        option(TcaFlower::KeyTcpDst, &80u16.to_be_bytes()),
+        option(TcaFlower::KeyTcpDstMask, &[0xFF, 0xFF]),
+        option(TcaFlower::KeyPortSrcMin, &1000u16.to_be_bytes()),
+        option(TcaFlower::KeyPortSrcMax, &2000u16.to_be_bytes()),
+        option(TcaFlower::KeyVlanId, &100u16.to_ne_bytes()),
+        option(TcaFlower::KeyTcpFlags, &[0x00, 0x02]),
+        option(TcaFlower::KeyTcpFlagsMask, &[0x00, 0x12]),
+        option(
+            TcaFlower::KeyCtState,
+            &(TCA_FLOWER_KEY_CT_FLAGS_TRACKED | TCA_FLOWER_KEY_CT_FLAGS_ESTABLISHED).to_ne_bytes(),
+        ),
+        option(TcaFlower::KeyMplsOpts, &mpls_opts),
+        option(TcaFlower::KeyEthDst, &[0x02, 0, 0, 0, 0, 0x01]),
+        option(TcaFlower::Flags, &6u32.to_ne_bytes()),
+        option(TcaFlower::InHwCount, &1u32.to_ne_bytes()),
+    ];
+
+    let flower = Flower::new(opts);
+    assert_eq!(flower.classid, Some(0x10001));
+    assert_eq!(flower.eth_type, Some(0x0800));
+    assert_eq!(flower.eth_dst, Some([0x02, 0, 0, 0, 0, 0x01]));
+    assert_eq!(flower.ip_proto, Some(6));
+    assert_eq!(flower.ipv4_dst, Some(Ipv4Addr::new(10, 0, 0, 0)));
+    assert_eq!(flower.ipv4_dst_mask, Some(Ipv4Addr::new(255, 255, 255, 0)));
+    assert_eq!(flower.dst_port, Some(80));
+    assert_eq!(flower.dst_port_mask, Some(0xFFFF));
+    assert_eq!(
+        flower.src_port_range,
+        Some(FlowerPortRange {
+            min: 1000,
+            max: 2000
+        })
+    );
+    assert_eq!(flower.dst_port_range, None);
+    assert_eq!(flower.vlan_id, Some(100));
+    assert_eq!(flower.tcp_flags, Some(0x02));
+    assert_eq!(flower.tcp_flags_mask, Some(0x12));
+    assert_eq!(flower.ct_state, Some(0b1010));
+    assert_eq!(
+        flower.mpls_lses,
+        vec![FlowerMplsLse {
+            depth: 1,
+            label: Some(100),
+            ..Default::default()
+        }]
+    );
+    assert_eq!(
+        flower.flags,
+        ClsFlags {
+            skip_sw: true,
+            in_hw: true,
+            ..Default::default()
+        }
+    );
+    assert_eq!(u32::from(flower.flags), 6);
+    assert_eq!(flower.in_hw_count, Some(1));
+}
+
+#[test]
+fn test_bpf_programs() {
+    let tcs = ParseOptions::new()
+        .fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false)
+        .tc(get_filters())
+        .unwrap();
+
+    let programs = handle::bpf_programs(tcs);
+    assert_eq!(programs.len(), 1);
+    let filter = programs[0].attr.filter.as_ref().unwrap();
+    assert_eq!(filter.priority, 20);
+    assert_eq!(filter.protocol, 0x0003); // ETH_P_ALL
+    assert_eq!(
+        filter.classifier,
+        Some(Classifier::Bpf(Bpf {
+            classid: Some(0x10002),
+            ops: vec![BpfOp {
+                code: 6, // BPF_RET | BPF_K
+                jt: 0,
+                jf: 0,
+                k: u32::MAX,
+            }],
+            flags_gen: ClsFlags {
+                not_in_hw: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+    );
+}
+
+#[test]
+fn test_bpf_direct_action() {
+    let option = |kind: TcaBpf, bytes: &[u8]| TcOption {
+        kind: kind as u16,
+        bytes: bytes.to_vec(),
+    };
+    let opts = vec![
+        option(TcaBpf::Id, &42u32.to_ne_bytes()),
+        option(TcaBpf::Name, b"tc_ingress:[*]\0"),
+        option(TcaBpf::Tag, &[0xde, 0xad, 0xbe, 0xef, 0, 1, 2, 3]),
+        option(TcaBpf::Flags, &TCA_BPF_FLAG_ACT_DIRECT.to_ne_bytes()),
+    ];
+
+    let bpf = Bpf::new(opts);
+    assert_eq!(bpf.id, Some(42));
+    assert_eq!(bpf.name.as_deref(), Some("tc_ingress:[*]"));
+    assert_eq!(bpf.tag, Some([0xde, 0xad, 0xbe, 0xef, 0, 1, 2, 3]));
+    assert!(bpf.direct_action());
+    assert!(bpf.ops.is_empty());
+}
+
+#[test]
+fn test_clsact_hook_parent() {
+    assert_eq!(ClsactHook::Ingress.parent(), 0xFFFFFFF2);
+    assert_eq!(ClsactHook::Egress.parent(), 0xFFFFFFF3);
+}
+
+#[test]
+fn test_act_bpf() {
+    let parms = ActGen {
+        index: 3,
+        action: TC_ACT_SHOT,
+        refcnt: 1,
+        bindcnt: 1,
+        ..Default::default()
+    };
+    let option = |kind: TcaActBpf, bytes: &[u8]| TcOption {
+        kind: kind as u16,
+        bytes: bytes.to_vec(),
+    };
+    let opts = vec![
+        option(TcaActBpf::Parms, &bincode::serialize(&parms).unwrap()),
+        option(TcaActBpf::Id, &7u32.to_ne_bytes()),
+        option(TcaActBpf::Name, b"act_drop\0"),
+        option(TcaActBpf::Tag, &[1, 2, 3, 4, 5, 6, 7, 8]),
+    ];
+
+    let bpf = ActBpf::new(opts);
+    assert_eq!(bpf.parms, parms);
+    assert_eq!(bpf.id, Some(7));
+    assert_eq!(bpf.name.as_deref(), Some("act_drop"));
+    assert_eq!(bpf.tag, Some([1, 2, 3, 4, 5, 6, 7, 8]));
+    assert_eq!(bpf.tm, None);
+    assert!(bpf.ops.is_empty());
+}
+
+#[test]
+fn test_filter_request_bpf() {
+    let bpf = Bpf::with_fd(7, "prog");
+    let message = FilterRequest::new(2, ClsactHook::Ingress.parent(), Classifier::Bpf(bpf))
+        .priority(10)
+        .handle(1)
+        .chain(0)
+        .message(Operation::Replace)
+        .unwrap();
+    assert_eq!(message.header.flags, Operation::Replace.flags());
+    let NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficFilter(message)) = message.payload
+    else {
+        panic!("expected RTM_NEWTFILTER");
+    };
+    assert_eq!(message.header.parent, 0xFFFFFFF2);
+    assert_eq!(message.header.handle, 1);
+    // priority 10, ETH_P_ALL in network byte order
+    assert_eq!(message.header.info, 0x000A0300);
+
+    let attr = to_tc(message, &ParseOptions::new()).unwrap().attrs;
+    let Some(TcAttr::Options(opts)) = attr.iter().find(|a| matches!(a, TcAttr::Options(_))) else {
+        panic!("expected options");
+    };
+    assert_eq!(
+        opts,
+        &vec![
+            TcOption {
+                kind: TcaBpf::Fd as u16,
+                bytes: 7i32.to_ne_bytes().to_vec(),
+            },
+            TcOption {
+                kind: TcaBpf::Name as u16,
+                bytes: b"prog\0".to_vec(),
+            },
+            TcOption {
+                kind: TcaBpf::Flags as u16,
+                bytes: TCA_BPF_FLAG_ACT_DIRECT.to_ne_bytes().to_vec(),
+            },
+        ]
+    );
+    assert!(attr.contains(&TcAttr::Chain(vec![0, 0, 0, 0])));
+
+    // filters without a program can't be created
+    let request = FilterRequest::new(
+        2,
+        ClsactHook::Ingress.parent(),
+        Classifier::Bpf(Bpf::default()),
+    );
+    assert!(request.message(Operation::Add).is_err());
+    assert!(request.message(Operation::Delete).is_ok());
+}
+
+#[test]
+fn test_bpf_flags_gen_round_trip() {
+    // a program read back from the kernel, which reports its offload state
+    let mut bpf = Bpf::with_fd(7, "prog");
+    bpf.flags_gen = ClsFlags {
+        skip_hw: true,
+        not_in_hw: true,
+        ..Default::default()
+    };
+    let opts = bpf.to_options().unwrap();
+    let flags_gen = opts
+        .iter()
+        .find(|opt| opt.kind == TcaBpf::FlagsGen as u16)
+        .unwrap();
+    assert_eq!(flags_gen.bytes, TCA_CLS_FLAGS_SKIP_HW.to_ne_bytes());
+    assert_eq!(
+        Bpf::new(opts).flags_gen,
+        ClsFlags {
+            skip_hw: true,
+            ..Default::default()
+        }
+    );
+
+    bpf.flags_gen = ClsFlags {
+        in_hw: true,
+        ..Default::default()
+    };
+    let opts = bpf.to_options().unwrap();
+    assert!(opts.iter().all(|opt| opt.kind != TcaBpf::FlagsGen as u16));
+}
+
+#[test]
+fn test_filter_request_matchall() {
+    // `tc filter add dev eth0 ingress matchall skip_hw action bpf bytecode '1,6 0 0 0,'`
+    let bpf = ActBpf {
+        ops: vec![BpfOp {
+            code: 6,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let matchall = Matchall {
+        flags: ClsFlags {
+            skip_hw: true,
+            ..Default::default()
+        },
+        actions: vec![Action::new("bpf", Some(ActionOptions::Bpf(bpf)))],
+        ..Default::default()
+    };
+    let message = FilterRequest::new(
+        2,
+        ClsactHook::Ingress.parent(),
+        Classifier::Matchall(matchall),
+    )
+    .priority(1)
+    .message(Operation::Add)
+    .unwrap();
+
+    // round trip through the wire format and the parser
+    let mut buf = vec![0; message.header.length as usize];
+    message.serialize(&mut buf[..]);
+    let message = <NetlinkMessage<RtnlMessage>>::deserialize(&buf).unwrap();
+    let tcs = ParseOptions::new().tc(vec![message]).unwrap();
+    let filter = tcs[0].attr.filter.as_ref().unwrap();
+    assert_eq!(filter.kind, "matchall");
+    let Some(Classifier::Matchall(matchall)) = &filter.classifier else {
+        panic!("expected a matchall filter");
+    };
+    assert!(matchall.flags.skip_hw);
+    assert_eq!(matchall.classid, None);
+    assert_eq!(matchall.actions.len(), 1);
+    let action = &matchall.actions[0];
+    assert_eq!((action.order, action.kind.as_str()), (1, "bpf"));
+    let Some(ActionOptions::Bpf(bpf)) = &action.options else {
+        panic!("expected a bpf action");
+    };
+    assert_eq!(bpf.parms.action, TC_ACT_OK);
+    assert_eq!(bpf.ops.len(), 1);
+
+    let matchall = Matchall::new(vec![TcOption {
+        kind: TcaMatchall::Pcnt as u16,
+        bytes: 42u64.to_ne_bytes().to_vec(),
+    }]);
+    assert_eq!(matchall.pcnt, Some(42));
+}
+
+#[test]
+fn test_matchall_flags_round_trip() {
+    // a filter read back from the kernel, which reports its offload state
+    let matchall = Matchall::new(vec![TcOption {
+        kind: TcaMatchall::Flags as u16,
+        bytes: (TCA_CLS_FLAGS_SKIP_SW | TCA_CLS_FLAGS_IN_HW)
+            .to_ne_bytes()
+            .to_vec(),
+    }]);
+    assert!(matchall.flags.in_hw);
+
+    let opts = matchall.to_options().unwrap();
+    assert_eq!(
+        opts,
+        vec![TcOption {
+            kind: TcaMatchall::Flags as u16,
+            bytes: TCA_CLS_FLAGS_SKIP_SW.to_ne_bytes().to_vec(),
+        }]
+    );
+    assert_eq!(
+        Matchall::new(opts).flags,
+        ClsFlags {
+            skip_sw: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_actions() {
+    let actions = ParseOptions::new().actions(nl_actions()).unwrap();
+    assert_eq!(actions.len(), 1);
+    let action = &actions[0];
+    assert_eq!(action.order, 0);
+    assert_eq!(action.kind, "mirred");
+    let Some(ActionOptions::Mirred(mirred)) = &action.options else {
+        panic!("expected a mirred action");
+    };
+    assert_eq!(
+        mirred.parms,
+        MirredParms {
+            gen: ActGen {
+                index: 1,
+                capab: 0,
+                action: TC_ACT_PIPE,
+                refcnt: 1,
+                bindcnt: 1,
+            },
+            eaction: TCA_EGRESS_MIRROR,
+            ifindex: 3,
+        }
+    );
+    assert!(mirred.is_mirror() && !mirred.is_ingress());
+    assert_eq!(
+        mirred.tm,
+        Some(Tcft {
+            install: 224436,
+            lastuse: 224436,
+            expires: 0,
+            firstuse: 0,
+        })
+    );
+    assert_eq!(action.used_hw_stats, Some(0));
+    assert_eq!(action.in_hw_count, Some(0));
+    let stats = action.stats.as_ref().unwrap();
+    assert_eq!(
+        stats.basic,
+        Some(StatsBasic {
+            bytes: 0,
+            packets: 0,
+            packets64: 0,
+        })
+    );
+    assert!(stats.queue.is_some());
+}
+
+#[test]
+fn test_action_message() {
+    let mut message = NetlinkMessage::new(
+        NetlinkHeader::default(),
+        NetlinkPayload::InnerMessage(ActionMessage::dump("mirred")),
+    );
+    message.finalize();
+    let mut buf = vec![0; message.header.length as usize];
+    message.serialize(&mut buf[..]);
+    assert_eq!(message.header.message_type, action::RTM_GETACTION);
+    assert_eq!(
+        buf[16..],
+        [
+            0, 0, 0, 0, // tcamsg
+            20, 0, 1, 0, 16, 0, 1, 0, 11, 0, 1, 0, 109, 105, 114, 114, 101, 100, 0, 0, // tab
+            12, 0, 2, 0, 1, 0, 0, 0, 1, 0, 0, 0, // flags
+        ]
+    );
+    assert_eq!(
+        <NetlinkMessage<ActionMessage>>::deserialize(&buf).unwrap(),
+        message
+    );
+}
+
+#[test]
+fn test_gact_mirred_options() {
+    // `tc ... action pass random netrand drop 10 action mirred egress mirror dev ifb1`
+    let mut gact = Gact::with_action(TC_ACT_OK);
+    gact.random(PGACT_NETRAND, 10, TC_ACT_SHOT);
+    let actions = vec![
+        Action::new("gact", Some(ActionOptions::Gact(gact))),
+        Action::new(
+            "mirred",
+            Some(ActionOptions::Mirred(Mirred::with_ifindex(
+                TCA_EGRESS_MIRROR,
+                3,
+            ))),
+        ),
+    ];
+    let bytes = action::marshal_actions(&actions).unwrap();
+
+    let actions = action::unmarshal_actions(&bytes);
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[0].order, 1);
+    let Some(ActionOptions::Gact(gact)) = &actions[0].options else {
+        panic!("expected a gact action");
+    };
+    assert_eq!(gact.parms.action, TC_ACT_OK);
+    assert_eq!(
+        gact.prob,
+        Some(GactProb {
+            ptype: PGACT_NETRAND,
+            pval: 10,
+            paction: TC_ACT_SHOT,
+        })
+    );
+    assert_eq!(actions[1].order, 2);
+    let Some(ActionOptions::Mirred(mirred)) = &actions[1].options else {
+        panic!("expected a mirred action");
+    };
+    assert_eq!(mirred.parms.gen.action, TC_ACT_PIPE);
+    assert_eq!(mirred.parms.ifindex, 3);
+}
+
+#[test]
+fn test_police() {
+    // `tc ... action police rate 1mbit burst 10k conform-exceed drop/pipe`
+    let police = PoliceBuilder::new(125_000, 10_240)
+        .exceed(TC_ACT_SHOT)
+        .conform(TC_ACT_PIPE)
+        .build()
+        .unwrap();
+    let tbf = police.tbf.as_ref().unwrap();
+    assert_eq!(tbf.action, TC_ACT_SHOT);
+    assert_eq!(tbf.burst, 1_280_000);
+    assert_eq!((tbf.rate.rate, tbf.rate.cell_log), (125_000, 3));
+    assert_eq!(police.rtab.len(), 1024);
+    assert!(police.ptab.is_empty());
+    assert_eq!(police.result, Some(TC_ACT_PIPE));
+    assert_eq!(police.rate64, None);
+
+    let opts = police.to_options().unwrap();
+    let kinds: Vec<u16> = opts.iter().map(|opt| opt.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TcaPolice::Tbf as u16,
+            TcaPolice::Rate as u16,
+            TcaPolice::Result as u16
+        ]
+    );
+    assert_eq!(opts[0].bytes.len(), 56); // struct tc_police
+    assert_eq!(Police::new(opts), police);
+
+    // rates above 32 bits are sent separately
+    let police = PoliceBuilder::new(10_000_000_000, 1_000_000)
+        .peakrate(15_000_000_000)
+        .mtu(9000)
+        .build()
+        .unwrap();
+    let tbf = police.tbf.as_ref().unwrap();
+    assert_eq!(tbf.action, TC_ACT_RECLASSIFY);
+    assert_eq!(tbf.rate.rate, u32::MAX);
+    assert_eq!(police.rate(), Some(10_000_000_000));
+    assert_eq!(police.peakrate(), Some(15_000_000_000));
+    assert_eq!(police.ptab.len(), 1024);
+    assert_eq!(Police::new(police.to_options().unwrap()), police);
+
+    let police = PoliceBuilder::with_pkt_rate(1000, 100).build().unwrap();
+    assert_eq!(police.pktrate64, Some(1000));
+    assert_eq!(police.pktburst64, Some(1_562_500));
+    assert_eq!(police.rate(), None);
+
+    assert!(PoliceBuilder::new(125_000, 0).build().is_err());
+    assert!(PoliceBuilder::new(125_000, 10_240)
+        .peakrate(250_000)
+        .build()
+        .is_err());
+    assert!(PoliceBuilder::new(125_000, 10_240)
+        .mtu(1514)
+        .peakrate(0)
+        .build()
+        .is_err());
+    assert!(PoliceBuilder::new(125_000, 10_240)
+        .cell_log(32)
+        .build()
+        .is_err());
+}
+
+/// Encodes an action of `kind` with raw `TCA_ACT_OPTIONS`, as the kernel dumps it.
+fn raw_action(kind: &str, opts: &[TcOption]) -> Vec<u8> {
+    let mut name = kind.as_bytes().to_vec();
+    name.push(0);
+    let action = marshal_nested(&[
+        TcOption {
+            kind: 1, // TCA_ACT_KIND
+            bytes: name,
+        },
+        TcOption {
+            kind: 2, // TCA_ACT_OPTIONS
+            bytes: marshal_nested(opts),
+        },
+    ]);
+    marshal_nested(&[TcOption {
+        kind: 1,
+        bytes: action,
+    }])
+}
+
+/// Encodes `tc_gen` with the verdict `action`.
+fn act_gen(action: i32) -> Vec<u8> {
+    let mut bytes = vec![0u8; 20];
+    bytes[8..12].copy_from_slice(&action.to_ne_bytes());
+    bytes
+}
+
+#[test]
+fn test_pedit() {
+    // `tc ... action pedit ex munge ip ttl set 10`
+    let mut sel = act_gen(TC_ACT_OK);
+    sel.extend([1, 0, 0, 0]); // nkeys, flags, padding
+    sel.extend([0, 255, 255, 255]); // mask
+    sel.extend([10, 0, 0, 0]); // val
+    sel.extend(8u32.to_ne_bytes()); // off
+    sel.extend([0; 12]); // at, offmask, shift
+    let key_ex = marshal_nested(&[
+        TcOption {
+            kind: 1, // TCA_PEDIT_KEY_EX_HTYPE
+            bytes: TCA_PEDIT_KEY_EX_HDR_TYPE_IP4.to_ne_bytes().to_vec(),
+        },
+        TcOption {
+            kind: 2, // TCA_PEDIT_KEY_EX_CMD
+            bytes: TCA_PEDIT_KEY_EX_CMD_SET.to_ne_bytes().to_vec(),
+        },
+    ]);
+    let bytes = raw_action(
+        "pedit",
+        &[
+            TcOption {
+                kind: 4, // TCA_PEDIT_PARMS_EX
+                bytes: sel,
+            },
+            TcOption {
+                kind: 5, // TCA_PEDIT_KEYS_EX
+                bytes: marshal_nested(&[TcOption {
+                    kind: 6, // TCA_PEDIT_KEY_EX
+                    bytes: key_ex,
+                }]),
+            },
+        ],
+    );
+
+    let actions = unmarshal_actions(&bytes);
+    let Some(ActionOptions::Pedit(pedit)) = &actions[0].options else {
+        panic!("expected a pedit action");
+    };
+    let sel = pedit.sel.as_ref().unwrap();
+    assert_eq!(sel.nkeys, 1);
+    assert_eq!(
+        sel.keys,
+        [PeditKey {
+            mask: 0x00ff_ffff,
+            val: 0x0a00_0000,
+            off: 8,
+            ..Default::default()
+        }]
+    );
+    assert_eq!(
+        pedit.keys_ex,
+        [PeditKeyEx {
+            htype: TCA_PEDIT_KEY_EX_HDR_TYPE_IP4,
+            cmd: TCA_PEDIT_KEY_EX_CMD_SET,
+        }]
+    );
+}
+
+#[test]
+fn test_editing_actions() {
+    // `tc ... action skbedit priority 1:10 mark 5/0xff`
+    let bytes = raw_action(
+        "skbedit",
+        &[
+            TcOption {
+                kind: 2, // TCA_SKBEDIT_PARMS
+                bytes: act_gen(TC_ACT_PIPE),
+            },
+            TcOption {
+                kind: 3, // TCA_SKBEDIT_PRIORITY
+                bytes: 0x10010u32.to_ne_bytes().to_vec(),
+            },
+            TcOption {
+                kind: 5, // TCA_SKBEDIT_MARK
+                bytes: 5u32.to_ne_bytes().to_vec(),
+            },
+            TcOption {
+                kind: 8, // TCA_SKBEDIT_MASK
+                bytes: 0xffu32.to_ne_bytes().to_vec(),
+            },
+        ],
+    );
+    let Some(ActionOptions::Skbedit(skbedit)) = unmarshal_actions(&bytes).remove(0).options else {
+        panic!("expected a skbedit action");
+    };
+    assert_eq!(skbedit.parms.action, TC_ACT_PIPE);
+    assert_eq!(skbedit.priority, Some(0x10010));
+    assert_eq!((skbedit.mark, skbedit.mask), (Some(5), Some(0xff)));
+    assert_eq!(skbedit.queue_mapping, None);
+
+    // `tc ... action skbmod set etype 0x8100`, `struct tc_skbmod` aligns its flags to 8 bytes
+    let mut parms = act_gen(TC_ACT_PIPE);
+    parms.extend([0; 4]);
+    parms.extend(SKBMOD_F_ETYPE.to_ne_bytes());
+    let bytes = raw_action(
+        "skbmod",
+        &[
+            TcOption {
+                kind: 2, // TCA_SKBMOD_PARMS
+                bytes: parms,
+            },
+            TcOption {
+                kind: 5, // TCA_SKBMOD_ETYPE
+                bytes: 0x8100u16.to_ne_bytes().to_vec(),
+            },
+        ],
+    );
+    let Some(ActionOptions::Skbmod(skbmod)) = unmarshal_actions(&bytes).remove(0).options else {
+        panic!("expected a skbmod action");
+    };
+    assert_eq!(skbmod.flags, SKBMOD_F_ETYPE);
+    assert_eq!(skbmod.etype, Some(0x8100));
+
+    // `tc ... action vlan push id 10 protocol 802.1ad priority 3`
+    let mut parms = act_gen(TC_ACT_PIPE);
+    parms.extend(TCA_VLAN_ACT_PUSH.to_ne_bytes());
+    let bytes = raw_action(
+        "vlan",
+        &[
+            TcOption {
+                kind: 2, // TCA_VLAN_PARMS
+                bytes: parms,
+            },
+            TcOption {
+                kind: 3, // TCA_VLAN_PUSH_VLAN_ID
+                bytes: 10u16.to_ne_bytes().to_vec(),
+            },
+            TcOption {
+                kind: 4, // TCA_VLAN_PUSH_VLAN_PROTOCOL
+                bytes: vec![0x88, 0xa8],
+            },
+            TcOption {
+                kind: 6, // TCA_VLAN_PUSH_VLAN_PRIORITY
+                bytes: vec![3],
+            },
+        ],
+    );
+    let Some(ActionOptions::Vlan(vlan)) = unmarshal_actions(&bytes).remove(0).options else {
+        panic!("expected a vlan action");
+    };
+    assert_eq!(vlan.parms.v_action, TCA_VLAN_ACT_PUSH);
+    assert_eq!(vlan.push_vlan_id, Some(10));
+    assert_eq!(vlan.push_vlan_protocol, Some(0x88a8));
+    assert_eq!(vlan.push_vlan_priority, Some(3));
+
+    // `tc ... action csum iph tcp`
+    let mut parms = act_gen(TC_ACT_OK);
+    parms.extend((TCA_CSUM_UPDATE_FLAG_IPV4HDR | TCA_CSUM_UPDATE_FLAG_TCP).to_ne_bytes());
+    let bytes = raw_action(
+        "csum",
+        &[TcOption {
+            kind: 1, // TCA_CSUM_PARMS
+            bytes: parms,
+        }],
+    );
+    let Some(ActionOptions::Csum(csum)) = unmarshal_actions(&bytes).remove(0).options else {
+        panic!("expected a csum action");
+    };
+    assert_eq!(
+        csum.parms.update_flags,
+        TCA_CSUM_UPDATE_FLAG_IPV4HDR | TCA_CSUM_UPDATE_FLAG_TCP
+    );
+
+    // `tc ... action nat egress 10.0.0.0/8 192.168.1.1`
+    let mut parms = act_gen(TC_ACT_OK);
+    parms.extend([10, 0, 0, 0, 192, 168, 1, 1, 255, 0, 0, 0]);
+    parms.extend(TCA_NAT_FLAG_EGRESS.to_ne_bytes());
+    let bytes = raw_action(
+        "nat",
+        &[TcOption {
+            kind: 1, // TCA_NAT_PARMS
+            bytes: parms,
+        }],
+    );
+    let action = unmarshal_actions(&bytes).remove(0);
+    let Some(ActionOptions::Nat(nat)) = &action.options else {
+        panic!("expected a nat action");
+    };
+    assert_eq!(nat.old_addr(), Ipv4Addr::new(10, 0, 0, 0));
+    assert_eq!(nat.new_addr(), Ipv4Addr::new(192, 168, 1, 1));
+    assert_eq!(nat.prefix_len(), 8);
+    assert!(nat.is_egress());
+
+    // decoded actions can't be created yet
+    assert!(action.to_options().is_err());
+}
+
+#[test]
+fn test_tunnel_key() {
+    // `tc ... action tunnel_key set src_ip 10.0.0.1 dst_ip 10.0.0.2 id 42 dst_port 6081
+    //  geneve_opts 0102:80:00880022 ttl 64`
+    let mut parms = act_gen(TC_ACT_PIPE);
+    parms.extend(TCA_TUNNEL_KEY_ACT_SET.to_ne_bytes());
+    let geneve = marshal_nested(&[
+        TcOption {
+            kind: 1, // TCA_TUNNEL_KEY_ENC_OPT_GENEVE_CLASS
+            bytes: vec![0x01, 0x02],
+        },
+        TcOption {
+            kind: 2, // TCA_TUNNEL_KEY_ENC_OPT_GENEVE_TYPE
+            bytes: vec![0x80],
+        },
+        TcOption {
+            kind: 3, // TCA_TUNNEL_KEY_ENC_OPT_GENEVE_DATA
+            bytes: vec![0x00, 0x88, 0x00, 0x22],
+        },
+    ]);
+    let bytes = raw_action(
+        "tunnel_key",
+        &[
+            TcOption {
+                kind: 2, // TCA_TUNNEL_KEY_PARMS
+                bytes: parms,
+            },
+            TcOption {
+                kind: 3, // TCA_TUNNEL_KEY_ENC_IPV4_SRC
+                bytes: vec![10, 0, 0, 1],
+            },
+            TcOption {
+                kind: 4, // TCA_TUNNEL_KEY_ENC_IPV4_DST
+                bytes: vec![10, 0, 0, 2],
+            },
+            TcOption {
+                kind: 7, // TCA_TUNNEL_KEY_ENC_KEY_ID
+                bytes: 42u32.to_be_bytes().to_vec(),
+            },
+            TcOption {
+                kind: 9, // TCA_TUNNEL_KEY_ENC_DST_PORT
+                bytes: 6081u16.to_be_bytes().to_vec(),
+            },
+            TcOption {
+                kind: 11, // TCA_TUNNEL_KEY_ENC_OPTS
+                bytes: marshal_nested(&[TcOption {
+                    kind: 1, // TCA_TUNNEL_KEY_ENC_OPTS_GENEVE
+                    bytes: geneve,
+                }]),
+            },
+            TcOption {
+                kind: 13, // TCA_TUNNEL_KEY_ENC_TTL
+                bytes: vec![64],
+            },
+        ],
+    );
+    let Some(ActionOptions::TunnelKey(key)) = unmarshal_actions(&bytes).remove(0).options else {
+        panic!("expected a tunnel_key action");
+    };
+
+    let mut expected = TunnelKey::set(
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+    );
+    expected
+        .key_id(42)
+        .dst_port(6081)
+        .geneve_opt(0x0102, 0x80, &[0x00, 0x88, 0x00, 0x22]);
+    expected.ttl = Some(64);
+    assert_eq!(key, expected);
+
+    // re-creating the action gives back the same options
+    let action = Action::new("tunnel_key", Some(ActionOptions::TunnelKey(expected)));
+    let mut actions = unmarshal_actions(&marshal_actions(&[action]).unwrap());
+    assert_eq!(actions[0].order, 1);
+    assert_eq!(
+        actions.remove(0).options,
+        Some(ActionOptions::TunnelKey(key))
+    );
+
+    let Some(ActionOptions::TunnelKey(release)) = unmarshal_actions(&raw_action(
+        "tunnel_key",
+        &TunnelKey::release().to_options().unwrap(),
+    ))
+    .remove(0)
+    .options
+    else {
+        panic!("expected a tunnel_key action");
+    };
+    assert_eq!(release.parms.t_action, TCA_TUNNEL_KEY_ACT_RELEASE);
+    assert_eq!(release.enc_src, None);
+}
+
+#[test]
+fn test_tunnel_key_geneve_opts() {
+    // `tc ... action tunnel_key set src_ip 10.0.0.1 dst_ip 10.0.0.2
+    //  geneve_opts 0102:80:00880022,0103:01:0102030405060708`
+    let mut key = TunnelKey::set(
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+    );
+    key.geneve_opt(0x0102, 0x80, &[0x00, 0x88, 0x00, 0x22])
+        .geneve_opt(0x0103, 0x01, &[1, 2, 3, 4, 5, 6, 7, 8]);
+    let opts = key.to_options().unwrap();
+
+    // each option is in its own TCA_TUNNEL_KEY_ENC_OPTS_GENEVE nest
+    let enc_opts = opts.iter().find(|opt| opt.kind == 11).unwrap(); // TCA_TUNNEL_KEY_ENC_OPTS
+    let geneve = unmarshal_nested(&enc_opts.bytes).unwrap();
+    assert_eq!(geneve.len(), 2);
+    assert!(geneve.iter().all(|opt| opt.kind == 1)); // TCA_TUNNEL_KEY_ENC_OPTS_GENEVE
+    assert_eq!(
+        unmarshal_nested(&geneve[1].bytes).unwrap()[0].bytes,
+        [0x01, 0x03]
+    );
+
+    assert_eq!(TunnelKey::new(opts), key);
+}
+
+#[test]
+fn test_conntrack_actions() {
+    // `tc ... action ct commit zone 2 mark 0x10/0xff nat src addr 192.0.2.1-192.0.2.9 port 1000-2000`
+    let mut ct = Ct::with_flags(TCA_CT_ACT_COMMIT | TCA_CT_ACT_NAT_SRC);
+    ct.zone(2).mark(0x10, 0xff).nat(
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 9)),
+        Some((1000, 2000)),
+    );
+    let opts = ct.to_options().unwrap();
+    // ports are in network byte order
+    let port = opts.iter().find(|opt| opt.kind == 13).unwrap();
+    assert_eq!(port.bytes, 1000u16.to_be_bytes());
+    let decoded = Ct::new(opts);
+    assert_eq!(
+        decoded.ct_action,
+        Some(TCA_CT_ACT_COMMIT | TCA_CT_ACT_NAT_SRC | TCA_CT_ACT_NAT)
+    );
+    assert!(decoded.is_commit());
+    assert_eq!(decoded, ct);
+
+    // `tc ... action ctinfo zone 1 dscp 0xfc000000 0x01000000`
+    let mut ctinfo = Ctinfo::with_dscp(0xfc000000, Some(0x01000000));
+    ctinfo.zone(1);
+    let mut opts = ctinfo.to_options().unwrap();
+    opts.push(TcOption {
+        kind: 8, // TCA_CTINFO_STATS_DSCP_SET
+        bytes: 7u64.to_ne_bytes().to_vec(),
+    });
+    let decoded = Ctinfo::new(opts);
+    assert_eq!(decoded.dscp_statemask, Some(0x01000000));
+    assert_eq!(decoded.stats_dscp_set, Some(7));
+
+    // `struct tc_connmark` is padded to 24 bytes
+    let opts = Connmark::with_zone(3).to_options().unwrap();
+    assert_eq!(opts[0].bytes.len(), 24);
+    assert_eq!(Connmark::new(opts).parms.zone, 3);
+
+    // `tc ... action mpls push protocol mpls_uc label 100 tc 3 ttl 64 bos 1`
+    let mut mpls = Mpls::with_action(TCA_MPLS_ACT_PUSH);
+    mpls.proto(0x8847).entry(100, 3, 64).bos(true);
+    let opts = mpls.to_options().unwrap();
+    assert_eq!(opts[1].bytes, [0x88, 0x47]);
+    assert_eq!(Mpls::new(opts), mpls);
+    mpls.entry(MPLS_LABEL_MAX + 1, 0, 64);
+    assert!(mpls.to_options().is_err());
+}
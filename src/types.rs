@@ -1,8 +1,21 @@
+use std::time::Duration;
+
+use netlink_packet_utils::nla::NlasIterator;
 use serde::{Deserialize, Serialize};
 
+use crate::action::{
+    marshal_action, ActBpf, Connmark, Csum, Ct, Ctinfo, Gact, Mirred, Mpls, Nat, Pedit, Police,
+    Skbedit, Skbmod, TunnelKey, Vlan,
+};
 use crate::class::{Htb, HtbGlob, HtbXstats};
 use crate::errors::Error;
-use crate::qdiscs::{Clsact, FqCodel, FqCodelXStats};
+use crate::filter::{Bpf, Flower, Matchall, U32};
+use crate::qdiscs::{
+    Cake, CakeXStats, Clsact, Codel, CodelXStats, Fq, FqCodel, FqCodelClass, FqCodelClassXStats,
+    FqCodelXStats, FqPie, FqPieXStats, FqQdStats, Pie, PieXStats, Tbf,
+};
+use crate::rate::TIME_UNITS_PER_SEC;
+use crate::stab::Stab;
 
 /// This struct is an intermediate representation for netlink `tc` messages.
 /// Any downstream structs should be constructed into this struct.
@@ -17,6 +30,7 @@ pub struct TcHeader {
     pub index: i32,
     pub handle: u32,
     pub parent: u32,
+    pub info: u32,
 }
 
 #[derive(Debug, PartialEq)]
@@ -46,6 +60,42 @@ pub enum TcStats2 {
     StatsBasic(Vec<u8>),
     StatsQueue(Vec<u8>),
     StatsApp(Vec<u8>),
+    RateEst(Vec<u8>),
+    RateEst64(Vec<u8>),
+    BasicHw(Vec<u8>),
+    Pkt64(Vec<u8>),
+}
+
+/// Statistics nested in `TCA_STATS2` or `TCA_ACT_STATS`,
+/// defined in `include/uapi/linux/gen_stats.h`.
+pub enum TcaStats {
+    Unspec = 0,
+    Basic,
+    RateEst,
+    Queue,
+    App,
+    RateEst64,
+    Pad,
+    BasicHw,
+    Pkt64,
+    Max,
+}
+
+impl From<u16> for TcaStats {
+    fn from(v: u16) -> Self {
+        match v {
+            0 => TcaStats::Unspec,
+            1 => TcaStats::Basic,
+            2 => TcaStats::RateEst,
+            3 => TcaStats::Queue,
+            4 => TcaStats::App,
+            5 => TcaStats::RateEst64,
+            6 => TcaStats::Pad,
+            7 => TcaStats::BasicHw,
+            8 => TcaStats::Pkt64,
+            _ => TcaStats::Max,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -68,12 +118,87 @@ pub struct Attribute {
     pub stats2: Option<Stats2>,
     pub qdisc: Option<QDisc>,
     pub class: Option<Class>,
+    pub filter: Option<Filter>,
+    /// Kind specific statistics, from `TCA_STATS_APP` when the kernel sends it
+    /// and from the legacy `TCA_XSTATS` copy otherwise.
     pub xstats: Option<XStats>,
+    /// Size table of a qdisc.
+    pub stab: Option<Stab>,
+    /// Whether the object is offloaded to the hardware, from `TCA_HW_OFFLOAD`.
+    pub hw_offload: bool,
+}
+
+/// A traffic filter, with the fields `tc filter show` reports for it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Filter {
+    /// Protocol matched by the filter, e.g. `ETH_P_ALL` or `ETH_P_IP`, in host byte order.
+    pub protocol: u16,
+    pub priority: u16,
+    pub chain: Option<u32>,
+    pub handle: u32,
+    pub kind: String,
+    /// Options of the classifier, for the kinds this crate decodes.
+    pub classifier: Option<Classifier>,
+}
+
+impl Filter {
+    /// Creates a filter from the header fields encoded in `tcm_info`,
+    /// the priority in the upper 16 bits and the protocol in network byte order in the lower 16 bits.
+    pub fn new(info: u32, handle: u32, chain: Option<u32>, kind: String) -> Self {
+        Self {
+            protocol: u16::from_be((info & 0xFFFF) as u16),
+            priority: (info >> 16) as u16,
+            chain,
+            handle,
+            kind,
+            classifier: None,
+        }
+    }
+}
+
+/// An action run by a classifier or dumped with `RTM_GETACTION`,
+/// with the fields `tc actions show` reports for it.
+#[derive(Debug, Default, PartialEq)]
+pub struct Action {
+    /// Position of the action in its list, the actions run by increasing order.
+    pub order: u16,
+    pub kind: String,
+    pub index: Option<u32>,
+    pub stats: Option<Stats2>,
+    /// Opaque data attached by the user, up to 16 bytes.
+    pub cookie: Option<Vec<u8>>,
+    /// `TCA_ACT_FLAGS_*` flags.
+    pub flags: Option<u32>,
+    /// `TCA_ACT_HW_STATS_*` types of hardware statistics allowed for the action.
+    pub hw_stats: Option<u32>,
+    /// `TCA_ACT_HW_STATS_*` types of hardware statistics in use.
+    pub used_hw_stats: Option<u32>,
+    /// Number of devices the action is offloaded to.
+    pub in_hw_count: Option<u32>,
+    /// Options of the action, for the kinds this crate decodes.
+    pub options: Option<ActionOptions>,
+}
+
+impl Action {
+    /// Creates an action of `kind` with the given options.
+    pub fn new(kind: &str, options: Option<ActionOptions>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// Encodes the action into `TCA_ACT_*` attributes.
+    pub fn to_options(&self) -> Result<Vec<TcOption>, Error> {
+        marshal_action(self)
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Stats {
     pub bytes: u64,
+    /// Truncated to 32 bits by the kernel, see `packets64`.
     pub packets: u32,
     pub drops: u32,
     pub overlimits: u32,
@@ -81,15 +206,22 @@ pub struct Stats {
     pub pps: u32,
     pub qlen: u32,
     pub backlog: u32,
+    /// Full packet count, taken from `Stats2` when the object also reports it.
+    #[serde(skip)]
+    pub packets64: u64,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct StatsBasic {
     pub bytes: u64,
+    /// Truncated to 32 bits by the kernel, see `packets64`.
     pub packets: u32,
+    /// Full packet count, from `TCA_STATS_PKT64` when the count does not fit in 32 bits.
+    #[serde(skip)]
+    pub packets64: u64,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct StatsQueue {
     pub qlen: u32,
     pub backlog: u32,
@@ -98,29 +230,162 @@ pub struct StatsQueue {
     pub overlimits: u32,
 }
 
-#[derive(Debug, Default)]
+/// Rate measured by the estimator of an object, from `struct gnet_stats_rate_est`
+/// or, for rates not fitting in 32 bits, `struct gnet_stats_rate_est64`.
+#[derive(Debug, Default, PartialEq)]
+pub struct StatsRateEst {
+    /// Bytes per second.
+    pub bps: u64,
+    /// Packets per second.
+    pub pps: u64,
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct Stats2 {
     pub basic: Option<StatsBasic>,
     pub queue: Option<StatsQueue>,
-    // pub app: Option<StatsApp>,
+    /// Only reported for objects with an estimator, see `Estimator`.
+    pub rate_est: Option<StatsRateEst>,
+    /// Part of `basic` counted by the hardware, for offloaded objects.
+    pub basic_hw: Option<StatsBasic>,
+    /// Kind specific statistics from `TCA_STATS_APP`, decoded like `Attribute::xstats`.
+    pub app: Option<XStats>,
+}
+
+impl Stats2 {
+    /// Splits the traffic in `basic` into the parts handled by the hardware and the software.
+    /// Everything is accounted to the software when the kernel reports no hardware counters.
+    pub fn hw_sw_split(&self) -> Option<StatsHwSw> {
+        let basic = self.basic.as_ref()?;
+        let hw = self.basic_hw.clone().unwrap_or_default();
+        let sw = StatsBasic {
+            bytes: basic.bytes.saturating_sub(hw.bytes),
+            packets: basic.packets.saturating_sub(hw.packets),
+            packets64: basic.packets64.saturating_sub(hw.packets64),
+        };
+        Some(StatsHwSw { hw, sw })
+    }
+}
+
+/// Traffic of an object split between hardware and software, see `Stats2::hw_sw_split`.
+#[derive(Debug, Default, PartialEq)]
+pub struct StatsHwSw {
+    pub hw: StatsBasic,
+    pub sw: StatsBasic,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum QDisc {
+    Cake(Cake),
+    Codel(Codel),
+    Fq(Fq),
     FqCodel(FqCodel),
+    FqPie(FqPie),
     Clsact(Clsact),
     Htb(HtbGlob),
+    Pie(Pie),
+    Tbf(Tbf),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Class {
+    FqCodel(FqCodelClass),
     Htb(Htb),
 }
 
+/// Flags shared by classifiers supporting hardware offload,
+/// defined in `include/uapi/linux/pkt_cls.h` as `TCA_CLS_FLAGS_*`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClsFlags {
+    /// Don't offload the filter to hardware.
+    pub skip_hw: bool,
+    /// Don't run the filter in software.
+    pub skip_sw: bool,
+    /// The filter is offloaded to hardware.
+    pub in_hw: bool,
+    /// The filter isn't offloaded to hardware.
+    pub not_in_hw: bool,
+    pub verbose: bool,
+}
+
+pub const TCA_CLS_FLAGS_SKIP_HW: u32 = 1 << 0;
+pub const TCA_CLS_FLAGS_SKIP_SW: u32 = 1 << 1;
+pub const TCA_CLS_FLAGS_IN_HW: u32 = 1 << 2;
+pub const TCA_CLS_FLAGS_NOT_IN_HW: u32 = 1 << 3;
+pub const TCA_CLS_FLAGS_VERBOSE: u32 = 1 << 4;
+
+impl From<u32> for ClsFlags {
+    fn from(flags: u32) -> Self {
+        Self {
+            skip_hw: flags & TCA_CLS_FLAGS_SKIP_HW != 0,
+            skip_sw: flags & TCA_CLS_FLAGS_SKIP_SW != 0,
+            in_hw: flags & TCA_CLS_FLAGS_IN_HW != 0,
+            not_in_hw: flags & TCA_CLS_FLAGS_NOT_IN_HW != 0,
+            verbose: flags & TCA_CLS_FLAGS_VERBOSE != 0,
+        }
+    }
+}
+
+impl ClsFlags {
+    /// Returns the flags accepted when creating a filter, leaving out the offload state
+    /// the kernel reports on dumped filters but rejects in requests.
+    pub(crate) fn request_flags(self) -> u32 {
+        u32::from(self) & (TCA_CLS_FLAGS_SKIP_HW | TCA_CLS_FLAGS_SKIP_SW | TCA_CLS_FLAGS_VERBOSE)
+    }
+}
+
+impl From<ClsFlags> for u32 {
+    fn from(flags: ClsFlags) -> Self {
+        [
+            (flags.skip_hw, TCA_CLS_FLAGS_SKIP_HW),
+            (flags.skip_sw, TCA_CLS_FLAGS_SKIP_SW),
+            (flags.in_hw, TCA_CLS_FLAGS_IN_HW),
+            (flags.not_in_hw, TCA_CLS_FLAGS_NOT_IN_HW),
+            (flags.verbose, TCA_CLS_FLAGS_VERBOSE),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |bits, (_, flag)| bits | flag)
+    }
+}
+
 #[derive(Debug, PartialEq)]
+pub enum Classifier {
+    Bpf(Bpf),
+    Flower(Box<Flower>),
+    Matchall(Matchall),
+    U32(U32),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ActionOptions {
+    Bpf(ActBpf),
+    Connmark(Connmark),
+    Csum(Csum),
+    Ct(Ct),
+    Ctinfo(Ctinfo),
+    Gact(Gact),
+    Mirred(Mirred),
+    Mpls(Mpls),
+    Nat(Nat),
+    Pedit(Pedit),
+    Police(Police),
+    Skbedit(Skbedit),
+    Skbmod(Skbmod),
+    TunnelKey(TunnelKey),
+    Vlan(Vlan),
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum XStats {
+    Cake(CakeXStats),
+    Codel(CodelXStats),
+    Fq(FqQdStats),
     FqCodel(FqCodelXStats),
+    FqCodelClass(FqCodelClassXStats),
+    FqPie(FqPieXStats),
     Htb(HtbXstats),
+    Pie(PieXStats),
 }
 
 /// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_ratespec`
@@ -134,6 +399,100 @@ pub struct RateSpec {
     pub rate: u32,
 }
 
+/// Parameters of a rate estimator, sent as `TCA_RATE` when creating qdiscs and classes.
+/// Defined in `include/uapi/linux/pkt_sched.h` as `struct tc_estimator`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Estimator {
+    /// Sampling interval, of `250ms << (interval + 2)`.
+    pub interval: i8,
+    /// Log2 of the averaging time constant, in sampling intervals.
+    pub ewma_log: u8,
+}
+
+impl Estimator {
+    /// Computes the parameters sampling every `interval` and averaging over `time_const`,
+    /// like `tc ... estimator <interval> <time_const>`.
+    /// The interval is rounded up to a power of two between 250ms and 8s.
+    pub fn new(interval: Duration, time_const: Duration) -> Result<Self, Error> {
+        let a = interval.as_micros() as f64;
+        let time_const = time_const.as_micros() as f64;
+
+        let Some(shift) = (0..=5).find(|shift| a <= (1 << shift) as f64 * TIME_UNITS_PER_SEC / 4.0)
+        else {
+            return Err(Error::Encode(
+                "Estimator interval must not exceed 8s".to_string(),
+            ));
+        };
+        let ewma_log = (1..32)
+            .find(|log| a / -(1.0 - 1.0 / (1u64 << log) as f64).ln() > time_const)
+            .unwrap_or(32)
+            - 1;
+        if ewma_log == 0 || ewma_log >= 31 {
+            return Err(Error::Encode(
+                "Estimator time constant is out of range".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            interval: shift as i8 - 2,
+            ewma_log: ewma_log as u8,
+        })
+    }
+}
+
+/// Decodes a `u16` attribute value in host byte order, `None` if it is too short.
+pub(crate) fn unmarshal_u16_value(bytes: &[u8]) -> Option<u16> {
+    bytes
+        .get(..2)
+        .map(|b| u16::from_ne_bytes(b.try_into().unwrap()))
+}
+
+/// Decodes a `u32` attribute value in host byte order, `None` if it is too short.
+pub(crate) fn unmarshal_u32_value(bytes: &[u8]) -> Option<u32> {
+    bytes
+        .get(..4)
+        .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+}
+
+/// Decodes a `u64` attribute value in host byte order, `None` if it is too short.
+pub(crate) fn unmarshal_u64_value(bytes: &[u8]) -> Option<u64> {
+    bytes
+        .get(..8)
+        .map(|b| u64::from_ne_bytes(b.try_into().unwrap()))
+}
+
+/// Splits the value of a nested attribute into its attributes.
+pub fn unmarshal_nested(bytes: &[u8]) -> Result<Vec<TcOption>, Error> {
+    NlasIterator::new(bytes)
+        .map(|nla| {
+            let nla = nla.map_err(|e| Error::Parse(e.to_string()))?;
+            Ok(TcOption {
+                kind: nla.kind(),
+                bytes: nla.value().to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Encodes `opts` as the value of a nested attribute.
+pub fn marshal_nested(opts: &[TcOption]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for opt in opts {
+        let length = NLA_HEADER_LEN + opt.bytes.len();
+        bytes.extend((length as u16).to_ne_bytes());
+        bytes.extend(opt.kind.to_ne_bytes());
+        bytes.extend(&opt.bytes);
+        bytes.resize(bytes.len() + nla_padding(length), 0);
+    }
+    bytes
+}
+
+const NLA_HEADER_LEN: usize = 4;
+
+fn nla_padding(length: usize) -> usize {
+    ((length + 3) & !3) - length
+}
+
 pub fn unmarshal_rate_spec(buf: &[u8]) -> Result<RateSpec, Error> {
     bincode::deserialize(buf).map_err(|e| Error::Parse(e.to_string()))
 }
@@ -1,77 +1,25 @@
-use netlink_packet_core::{
-    NetlinkHeader, NetlinkMessage, NetlinkPayload, NLM_F_DUMP, NLM_F_REQUEST,
-};
-use netlink_packet_route::{RtnlMessage, TcHeader, TcMessage};
-use netlink_sys::{protocols::NETLINK_ROUTE, Socket, SocketAddr};
-use netlink_tc::ParseOptions;
+use netlink_tc::action::{Mirred, TCA_EGRESS_MIRROR};
+use netlink_tc::class::{HtbClassBuilder, HtbGlob};
+use netlink_tc::constants::tc_handle;
+use netlink_tc::filter::{open_pinned, Bpf, BpfOp};
+use netlink_tc::qdiscs::{ClsactHook, TbfBuilder};
+use netlink_tc::rate::LinkLayer;
+use netlink_tc::stab::StabBuilder;
+use netlink_tc::types::{Action, ActionOptions, Class, Classifier, Estimator, QDisc, StatsRateEst};
+use netlink_tc::{ClassRequest, FilterRequest, ParseOptions, QDiscRequest, TcHandle};
 use nix::ifaddrs::getifaddrs;
 use nix::net::if_::if_nametoindex;
 use std::collections::BTreeSet;
 use std::ffi::OsStr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
 
-fn socket() -> Socket {
-    let socket = Socket::new(NETLINK_ROUTE).unwrap();
-    socket.connect(&SocketAddr::new(0, 0)).unwrap();
-    socket
-}
-
-fn receive_netlink_messages(message: RtnlMessage) -> Vec<NetlinkMessage<RtnlMessage>> {
-    let socket = socket();
-    send_request(&socket, message);
-
-    let mut receive_buffer = vec![0; 4096];
-    let mut offset = 0;
-
-    let mut messages = Vec::new();
-    while let Ok(size) = socket.recv(&mut &mut receive_buffer[..], 0) {
-        loop {
-            let bytes = &receive_buffer[offset..];
-            let rx_packet = <NetlinkMessage<RtnlMessage>>::deserialize(bytes).unwrap();
-            messages.push(rx_packet.clone());
-            let payload = rx_packet.payload;
-            if let NetlinkPayload::Error(err) = payload {
-                panic!("Netlink error: {:?}", err);
-            }
-            if let NetlinkPayload::Done(_) = payload {
-                return messages;
-            }
-
-            offset += rx_packet.header.length as usize;
-            if offset == size || rx_packet.header.length == 0 {
-                offset = 0;
-                break;
-            }
-        }
-    }
-
-    messages
-}
-
-fn get_qdiscs() -> Vec<NetlinkMessage<RtnlMessage>> {
-    receive_netlink_messages(RtnlMessage::GetQueueDiscipline(TcMessage::default()))
-}
-
-fn get_classes(index: i32) -> Vec<NetlinkMessage<RtnlMessage>> {
-    let header = TcHeader {
-        index,
-        ..Default::default()
-    };
-    let mut message = TcMessage::default();
-    message.header = header;
-    receive_netlink_messages(RtnlMessage::GetTrafficClass(message))
-}
-
-fn send_request(socket: &Socket, message: RtnlMessage) {
-    let mut nl_hdr = NetlinkHeader::default();
-    nl_hdr.flags = NLM_F_REQUEST | NLM_F_DUMP;
-
-    let mut packet = NetlinkMessage::new(nl_hdr, NetlinkPayload::from(message));
-    packet.finalize();
-
-    let mut buf = vec![0; packet.header.length as usize];
-    packet.serialize(&mut buf[..]);
-
-    socket.send(&buf[..], 0).unwrap();
+fn handle() -> TcHandle {
+    let mut opts = ParseOptions::new();
+    opts.fail_on_unknown_netlink_message(false)
+        .fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false);
+    TcHandle::with_options(opts).unwrap()
 }
 
 fn get_links() -> BTreeSet<i32> {
@@ -93,13 +41,7 @@ fn get_links() -> BTreeSet<i32> {
 
 #[test]
 fn test_qdiscs() {
-    let messages = get_qdiscs();
-    let tcs = ParseOptions::new()
-        .fail_on_unknown_netlink_message(false)
-        .fail_on_unknown_attribute(false)
-        .fail_on_unknown_option(false)
-        .tc(messages)
-        .unwrap();
+    let tcs = handle().qdiscs().unwrap();
     for tc in tcs {
         let attr = tc.attr;
         assert!(!attr.kind.is_empty());
@@ -113,13 +55,299 @@ fn test_classes() {
     let links = get_links();
     assert!(!links.is_empty());
 
+    let mut handle = handle();
     for link in links {
-        let messages = get_classes(link);
-        let classes = ParseOptions::new()
-            .fail_on_unknown_netlink_message(false)
-            .fail_on_unknown_attribute(false)
-            .fail_on_unknown_option(false)
-            .tc(messages);
+        let classes = handle.classes(link);
         assert!(classes.is_ok());
     }
 }
+
+#[test]
+fn test_qdiscs_for() {
+    let mut handle = handle();
+    for link in get_links() {
+        let qdiscs = handle.qdiscs_for(link).unwrap();
+        assert!(qdiscs.iter().all(|tc| tc.msg.index == link as u32));
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_async_handle() {
+    use netlink_tc::AsyncTcHandle;
+
+    let mut opts = ParseOptions::new();
+    opts.fail_on_unknown_netlink_message(false)
+        .fail_on_unknown_attribute(false)
+        .fail_on_unknown_option(false);
+    let handle = AsyncTcHandle::with_options(opts).unwrap();
+
+    let links = get_links();
+    let link = *links.first().unwrap();
+    let (qdiscs, qdiscs_for, classes, actions) = tokio::join!(
+        handle.qdiscs(),
+        handle.qdiscs_for(link),
+        handle.classes(link),
+        handle.actions("mirred")
+    );
+    let qdiscs = qdiscs.unwrap();
+    assert!(qdiscs.iter().all(|tc| !tc.attr.kind.is_empty()));
+    assert!(qdiscs_for
+        .unwrap()
+        .iter()
+        .all(|tc| tc.msg.index == link as u32));
+    assert!(classes.is_ok());
+    assert!(actions
+        .unwrap()
+        .iter()
+        .all(|action| action.kind == "mirred"));
+}
+
+#[test]
+fn test_filters() {
+    let mut handle = handle();
+    for link in get_links() {
+        for hook in [ClsactHook::Ingress, ClsactHook::Egress] {
+            let filters = handle.filters(link, hook.parent()).unwrap();
+            for tc in filters {
+                assert_eq!(tc.msg.index, link as u32);
+                assert!(tc.attr.filter.is_some());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_bpf_programs() {
+    let mut handle = handle();
+    for link in get_links() {
+        for hook in [ClsactHook::Ingress, ClsactHook::Egress] {
+            let programs = handle.bpf_programs(link, hook).unwrap();
+            for tc in programs {
+                let filter = tc.attr.filter.unwrap();
+                assert!(matches!(filter.classifier, Some(Classifier::Bpf(_))));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_actions() {
+    let actions = handle().actions("mirred").unwrap();
+    for action in actions {
+        assert_eq!(action.kind, "mirred");
+        assert!(action.stats.is_some());
+    }
+}
+
+/// Loads a `BPF_PROG_TYPE_SCHED_CLS` program returning `TC_ACT_OK`.
+fn load_bpf_program(name: &str) -> OwnedFd {
+    const BPF_PROG_LOAD: libc::c_long = 5;
+    const BPF_PROG_TYPE_SCHED_CLS: u32 = 3;
+    // r0 = 0; exit
+    let insns: [u8; 16] = [0xb7, 0, 0, 0, 0, 0, 0, 0, 0x95, 0, 0, 0, 0, 0, 0, 0];
+    let license = c"GPL";
+
+    let mut attr = [0u8; 64];
+    attr[0..4].copy_from_slice(&BPF_PROG_TYPE_SCHED_CLS.to_ne_bytes());
+    attr[4..8].copy_from_slice(&2u32.to_ne_bytes());
+    attr[8..16].copy_from_slice(&(insns.as_ptr() as u64).to_ne_bytes());
+    attr[16..24].copy_from_slice(&(license.as_ptr() as u64).to_ne_bytes());
+    attr[48..48 + name.len()].copy_from_slice(name.as_bytes());
+    let fd = unsafe { libc::syscall(libc::SYS_bpf, BPF_PROG_LOAD, attr.as_ptr(), attr.len()) };
+    assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+    unsafe { OwnedFd::from_raw_fd(fd as i32) }
+}
+
+#[test]
+fn test_bpf_attach() {
+    // the test needs an interface with a clsact qdisc, e.g.
+    // `ip link add ifb0 type ifb && tc qdisc add dev ifb0 clsact`
+    let Ok(index) = if_nametoindex("ifb0") else {
+        return;
+    };
+    let index = index as i32;
+    let mut handle = handle();
+    let attached = |handle: &mut TcHandle| {
+        handle
+            .bpf_programs(index, ClsactHook::Egress)
+            .unwrap()
+            .into_iter()
+            .filter_map(|tc| tc.attr.filter)
+            .filter(|filter| filter.priority == 49152 && filter.handle == 1)
+            .collect::<Vec<_>>()
+    };
+
+    let prog = load_bpf_program("first");
+    let mut request = FilterRequest::new(
+        index,
+        ClsactHook::Egress.parent(),
+        Classifier::Bpf(Bpf::with_fd(prog.as_raw_fd(), "first")),
+    );
+    request.priority(49152).handle(1);
+    request.replace().unwrap();
+
+    let filters = attached(&mut handle);
+    assert_eq!(filters.len(), 1);
+    let Some(Classifier::Bpf(bpf)) = &filters[0].classifier else {
+        panic!("expected a bpf filter");
+    };
+    assert_eq!(bpf.name.as_deref(), Some("first"));
+    assert!(bpf.direct_action());
+    assert!(bpf.id.is_some());
+    let first_id = bpf.id;
+
+    // replacing the program keeps a single filter
+    let prog = load_bpf_program("second");
+    let mut request = FilterRequest::new(
+        index,
+        ClsactHook::Egress.parent(),
+        Classifier::Bpf(Bpf::with_fd(prog.as_raw_fd(), "second")),
+    );
+    request.priority(49152).handle(1);
+    request.replace().unwrap();
+
+    let filters = attached(&mut handle);
+    assert_eq!(filters.len(), 1);
+    let Some(Classifier::Bpf(bpf)) = &filters[0].classifier else {
+        panic!("expected a bpf filter");
+    };
+    assert_eq!(bpf.name.as_deref(), Some("second"));
+    assert_ne!(bpf.id, first_id);
+
+    request.delete().unwrap();
+    assert!(attached(&mut handle).is_empty());
+}
+
+#[test]
+fn test_mirred_action() {
+    // mirrors the packets of ifb0 to ifb1, see `test_bpf_attach`
+    let (Ok(index), Ok(target)) = (if_nametoindex("ifb0"), if_nametoindex("ifb1")) else {
+        return;
+    };
+    let index = index as i32;
+    let mirred = Mirred::with_ifindex(TCA_EGRESS_MIRROR, target);
+    let bpf = Bpf {
+        // ret #-1, matches all packets
+        ops: vec![BpfOp {
+            code: 6,
+            k: u32::MAX,
+            ..Default::default()
+        }],
+        actions: vec![Action::new("mirred", Some(ActionOptions::Mirred(mirred)))],
+        ..Default::default()
+    };
+    let mut request = FilterRequest::new(index, ClsactHook::Egress.parent(), Classifier::Bpf(bpf));
+    request.priority(49153).handle(1);
+    request.replace().unwrap();
+
+    let filter = handle()
+        .bpf_programs(index, ClsactHook::Egress)
+        .unwrap()
+        .into_iter()
+        .filter_map(|tc| tc.attr.filter)
+        .find(|filter| filter.priority == 49153)
+        .unwrap();
+    request.delete().unwrap();
+
+    let Some(Classifier::Bpf(bpf)) = filter.classifier else {
+        panic!("expected a bpf filter");
+    };
+    assert_eq!(bpf.actions.len(), 1);
+    let Some(ActionOptions::Mirred(mirred)) = &bpf.actions[0].options else {
+        panic!("expected a mirred action");
+    };
+    assert_eq!(mirred.parms.ifindex, target);
+    assert!(mirred.is_mirror());
+}
+
+#[test]
+fn test_bpf_open_pinned_missing() {
+    assert!(open_pinned("/sys/fs/bpf/netlink-tc-missing").is_err());
+}
+
+#[test]
+fn test_qdisc_stab() {
+    let Ok(index) = if_nametoindex("lo") else {
+        return;
+    };
+    let index = index as i32;
+    let stab = StabBuilder::new(LinkLayer::Atm).overhead(10).build();
+    let mut request = QDiscRequest::new(index, QDisc::Htb(HtbGlob::default()));
+    request.handle(tc_handle(0x4e54, 0)).stab(stab.clone());
+    request.replace().unwrap();
+
+    let qdisc = handle()
+        .qdiscs_for(index)
+        .unwrap()
+        .into_iter()
+        .find(|tc| tc.msg.handle == tc_handle(0x4e54, 0));
+    request.delete().unwrap();
+
+    // the kernel only reports the parameters of the table
+    assert_eq!(
+        qdisc.unwrap().attr.stab.map(|stab| stab.base),
+        Some(stab.base)
+    );
+}
+
+#[test]
+fn test_class_estimator() {
+    // ifb1 has an HTB root qdisc 1:
+    let Ok(index) = if_nametoindex("ifb1") else {
+        return;
+    };
+    let index = index as i32;
+    let htb = HtbClassBuilder::new(125_000).build().unwrap();
+    let estimator = Estimator::new(Duration::from_millis(250), Duration::from_secs(1)).unwrap();
+    let mut request = ClassRequest::new(index, tc_handle(1, 0x4e), Class::Htb(htb));
+    request.estimator(estimator);
+    request.replace().unwrap();
+
+    let class = handle()
+        .classes(index)
+        .unwrap()
+        .into_iter()
+        .find(|tc| tc.msg.handle == tc_handle(1, 0x4e));
+    request.delete().unwrap();
+
+    let stats2 = class.unwrap().attr.stats2.unwrap();
+    assert_eq!(stats2.rate_est, Some(StatsRateEst::default()));
+}
+
+#[test]
+fn test_tbf_qdisc() {
+    // ifb1 has an HTB class 1:10 to attach the qdisc to
+    let Ok(index) = if_nametoindex("ifb1") else {
+        return;
+    };
+    let index = index as i32;
+    let tbf = TbfBuilder::new(125_000, 10_000)
+        .latency(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let expected = tbf
+        .parms
+        .as_ref()
+        .map(|parms| (parms.rate.rate, parms.limit));
+    let mut request = QDiscRequest::new(index, QDisc::Tbf(tbf));
+    request
+        .handle(tc_handle(0x4e55, 0))
+        .parent(tc_handle(1, 0x10));
+    request.replace().unwrap();
+
+    let qdisc = handle()
+        .qdiscs_for(index)
+        .unwrap()
+        .into_iter()
+        .find(|tc| tc.msg.handle == tc_handle(0x4e55, 0));
+    request.delete().unwrap();
+
+    let Some(QDisc::Tbf(tbf)) = qdisc.unwrap().attr.qdisc else {
+        panic!("tbf qdisc not found");
+    };
+    // the kernel only reports the parameters, with the buffer recomputed from the burst
+    let parms = tbf.parms.unwrap();
+    assert_eq!(Some((parms.rate.rate, parms.limit)), expected);
+    assert!(tbf.rtab.is_empty() && tbf.burst.is_none());
+}